@@ -0,0 +1,328 @@
+//! Get the status of a Bedrock Edition server, using the RakNet
+//! [Unconnected Ping](https://wiki.vg/Raknet_Protocol#Unconnected_Ping).
+//! See documentation for [`status_bedrock`] for more information.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rand::random;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io,
+    net::{lookup_host, UdpSocket},
+    time::timeout,
+};
+
+use crate::{
+    errors::{timeout_err, BedrockProtocolError, Result},
+    socket::host_port,
+};
+
+/// The magic bytes that mark offline (unconnected) RakNet messages.
+const MAGIC: [u8; 16] = [
+    0x00, 0xff, 0xff, 0x00, 0xfe, 0xfe, 0xfe, 0xfe, 0xfd, 0xfd, 0xfd, 0xfd, 0x12, 0x34, 0x56, 0x78,
+];
+
+/// The packet ID of an Unconnected Ping.
+const UNCONNECTED_PING: u8 = 0x01;
+
+/// The packet ID of an Unconnected Pong.
+const UNCONNECTED_PONG: u8 = 0x1c;
+
+/// The largest payload a UDP datagram can carry over IPv4.
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// The status of a Bedrock Edition server, sent as the server info string of its
+/// [Unconnected Pong](https://wiki.vg/Raknet_Protocol#Unconnected_Pong).
+///
+/// Only the fields up to [`max_players`](Self::max_players) are sent by every server,
+/// the rest are `None` if the server left them out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BedrockStatusResponse {
+    /// The edition of the game, `MCPE` for Bedrock Edition and `MCEE` for Education Edition.
+    pub edition: String,
+
+    /// The first line of the message shown in the server list.
+    pub motd: String,
+
+    /// The protocol version of the server.
+    pub protocol: i32,
+
+    /// The name of the game version the server is running, like `1.20.10`.
+    pub version: String,
+
+    /// The current number of online players.
+    pub online_players: u32,
+
+    /// Maximum players online this server allows.
+    pub max_players: u32,
+
+    /// A random ID the server picks when it starts. Kept as a string, since servers
+    /// don't agree on whether it is signed.
+    pub server_id: Option<String>,
+
+    /// The second line of the message shown in the server list.
+    /// Vanilla servers send the level name here.
+    pub sub_motd: Option<String>,
+
+    /// The name of the default game mode, like `Survival`.
+    pub game_mode: Option<String>,
+
+    /// The numeric ID of the default game mode.
+    pub game_mode_id: Option<u8>,
+
+    /// The port the server accepts IPv4 connections on.
+    pub port_v4: Option<u16>,
+
+    /// The port the server accepts IPv6 connections on.
+    pub port_v6: Option<u16>,
+}
+
+impl BedrockStatusResponse {
+    /// Parse the semicolon-delimited server info string of an Unconnected Pong.
+    fn parse(info: &str) -> io::Result<Self> {
+        let mut fields = info.split(';');
+        let invalid = || io::Error::from(BedrockProtocolError::InvalidServerInfo);
+
+        // servers can leave the MOTD or version empty, but not leave them out
+        let mut required = || fields.next().map(str::to_string).ok_or_else(invalid);
+
+        let edition = required()?;
+        let motd = required()?;
+        let protocol = required()?.parse().map_err(|_| invalid())?;
+        let version = required()?;
+        let online_players = required()?.parse().map_err(|_| invalid())?;
+        let max_players = required()?.parse().map_err(|_| invalid())?;
+
+        // the fields after the player counts are optional, and empty ones count as missing
+        let mut next = || fields.next().filter(|field| !field.is_empty());
+
+        Ok(Self {
+            edition,
+            motd,
+            protocol,
+            version,
+            online_players,
+            max_players,
+            server_id: next().map(str::to_string),
+            sub_motd: next().map(str::to_string),
+            game_mode: next().map(str::to_string),
+            game_mode_id: next().and_then(|id| id.parse().ok()),
+            port_v4: next().and_then(|port| port.parse().ok()),
+            port_v6: next().and_then(|port| port.parse().ok()),
+        })
+    }
+}
+
+/// Ping a Bedrock Edition server for its status, with a RakNet
+/// [Unconnected Ping](https://wiki.vg/Raknet_Protocol#Unconnected_Ping).
+///
+/// Bedrock servers answer these without any kind of handshake, and the answer is what
+/// the game shows in its server list.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port the server is running on, usually
+///   [`DEFAULT_BEDROCK_PORT`](crate::DEFAULT_BEDROCK_PORT).
+/// * `dur` - How long to wait for the response.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue, the server sent invalid data, or the
+/// response was not received within `dur`.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::bedrock::status_bedrock;
+/// use std::time::Duration;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let data = status_bedrock("localhost", 19132, Duration::from_secs(5)).await?;
+///     println!("{} players online", data.online_players);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_bedrock(host: &str, port: u16, dur: Duration) -> Result<BedrockStatusResponse> {
+    Ok(timeout(dur, ping(host, port))
+        .await
+        .unwrap_or(timeout_err())?)
+}
+
+/// Send an Unconnected Ping, and wait for the matching Unconnected Pong.
+async fn ping(host: &str, port: u16) -> io::Result<BedrockStatusResponse> {
+    let addr = lookup_host(host_port(host, port))
+        .await?
+        .next()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any address",
+            )
+        })?;
+    let bind_ip = if addr.is_ipv4() {
+        IpAddr::from(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::from(Ipv6Addr::UNSPECIFIED)
+    };
+
+    let socket = UdpSocket::bind((bind_ip, 0)).await?;
+    socket.connect(addr).await?;
+
+    // the server echoes the time back, which tells its pong apart from stale ones
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis() as i64);
+
+    let mut ping = BytesMut::with_capacity(1 + 8 + MAGIC.len() + 8);
+    ping.put_u8(UNCONNECTED_PING);
+    ping.put_i64(time);
+    ping.put_slice(&MAGIC);
+    ping.put_i64(random()); // the client's GUID
+    socket.send(&ping).await?;
+
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+
+        if let Some(info) = parse_pong(Bytes::copy_from_slice(&buf[..len]), time)? {
+            return BedrockStatusResponse::parse(&info);
+        }
+    }
+}
+
+/// Parse an Unconnected Pong, returning its server info string, or `None` if it is the
+/// answer to a ping with another time.
+fn parse_pong(mut pong: Bytes, time: i64) -> io::Result<Option<String>> {
+    if pong.remaining() < 1 + 8 + 8 + MAGIC.len() + 2 {
+        return Err(BedrockProtocolError::TruncatedPacket.into());
+    }
+
+    if pong.get_u8() != UNCONNECTED_PONG {
+        return Err(BedrockProtocolError::InvalidPacketId.into());
+    }
+
+    let echoed = pong.get_i64();
+    let _server_guid = pong.get_i64();
+
+    if pong.split_to(MAGIC.len()) != MAGIC[..] {
+        return Err(BedrockProtocolError::InvalidMagic.into());
+    }
+
+    let len = pong.get_u16() as usize;
+    if pong.remaining() < len {
+        return Err(BedrockProtocolError::TruncatedPacket.into());
+    }
+
+    if echoed != time {
+        return Ok(None);
+    }
+
+    String::from_utf8(pong.split_to(len).to_vec())
+        .map(Some)
+        .map_err(|_| BedrockProtocolError::InvalidServerInfo.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_pong, status_bedrock, BedrockStatusResponse, MAGIC};
+    use bytes::{Buf, BufMut, Bytes, BytesMut};
+    use std::time::Duration;
+    use tokio::{io, net::UdpSocket};
+
+    const SERVER_INFO: &str = "MCPE;Dedicated Server;594;1.20.10;2;10;13253860892328930865;\
+        Bedrock level;Survival;1;19132;19133;";
+
+    fn pong(time: i64, info: &str) -> Bytes {
+        let mut pong = BytesMut::new();
+        pong.put_u8(0x1c);
+        pong.put_i64(time);
+        pong.put_i64(42);
+        pong.put_slice(&MAGIC);
+        pong.put_u16(u16::try_from(info.len()).unwrap());
+        pong.put_slice(info.as_bytes());
+        pong.freeze()
+    }
+
+    #[test]
+    fn test_parse_server_info() {
+        let res = BedrockStatusResponse::parse(SERVER_INFO).unwrap();
+        assert_eq!(res.edition, "MCPE");
+        assert_eq!(res.motd, "Dedicated Server");
+        assert_eq!(res.protocol, 594);
+        assert_eq!(res.version, "1.20.10");
+        assert_eq!(res.online_players, 2);
+        assert_eq!(res.max_players, 10);
+        assert_eq!(res.server_id.as_deref(), Some("13253860892328930865"));
+        assert_eq!(res.sub_motd.as_deref(), Some("Bedrock level"));
+        assert_eq!(res.game_mode.as_deref(), Some("Survival"));
+        assert_eq!(res.game_mode_id, Some(1));
+        assert_eq!(res.port_v4, Some(19132));
+        assert_eq!(res.port_v6, Some(19133));
+
+        // older servers stop after the player counts
+        let res = BedrockStatusResponse::parse("MCPE;A server;390;1.14.60;0;20").unwrap();
+        assert_eq!(res.max_players, 20);
+        assert_eq!(res.server_id, None);
+        assert_eq!(res.port_v6, None);
+
+        // an empty MOTD or version is still a valid response
+        let res = BedrockStatusResponse::parse("MCPE;;594;;2;10;;;Survival").unwrap();
+        assert_eq!(res.motd, "");
+        assert_eq!(res.version, "");
+        assert_eq!(res.server_id, None);
+        assert_eq!(res.sub_motd, None);
+        assert_eq!(res.game_mode.as_deref(), Some("Survival"));
+
+        assert!(BedrockStatusResponse::parse("MCPE;A server;390;1.14.60;0").is_err());
+        assert!(BedrockStatusResponse::parse("MCPE;A server;390;1.14.60;;20").is_err());
+        assert!(BedrockStatusResponse::parse("MCPE;A server;new;1.14.60;0;20").is_err());
+    }
+
+    #[test]
+    fn test_parse_pong() {
+        let info = parse_pong(pong(1234, SERVER_INFO), 1234).unwrap();
+        assert_eq!(info.as_deref(), Some(SERVER_INFO));
+
+        // a pong to an older ping
+        assert_eq!(parse_pong(pong(1000, SERVER_INFO), 1234).unwrap(), None);
+
+        let mut bad_magic = BytesMut::from(&pong(1234, SERVER_INFO)[..]);
+        bad_magic[18] = 0;
+        assert!(parse_pong(bad_magic.freeze(), 1234).is_err());
+
+        let full = pong(1234, SERVER_INFO);
+        assert!(parse_pong(full.slice(..full.len() - 1), 1234).is_err());
+        assert!(parse_pong(full.slice(..10), 1234).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_status_bedrock() -> io::Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (len, client) = server.recv_from(&mut buf).await?;
+            assert_eq!(len, 33);
+            assert_eq!(buf[0], 0x01);
+            assert_eq!(buf[9..25], MAGIC);
+            let time = (&buf[1..9]).get_i64();
+
+            // a stale pong first, which must be skipped
+            server.send_to(&pong(time - 1, "stale"), client).await?;
+            server.send_to(&pong(time, SERVER_INFO), client).await?;
+
+            Ok::<_, io::Error>(())
+        });
+
+        let res = status_bedrock("127.0.0.1", port, Duration::from_secs(5)).await?;
+        assert_eq!(res.version, "1.20.10");
+        assert_eq!(res.online_players, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+//! Blocking versions of the most common functions, for use outside of an async runtime,
+//! like in a small CLI or behind an FFI boundary.
+//!
+//! Each function takes the same arguments and returns the same types as the async
+//! function of the same name, and drives it to completion on a small current-thread
+//! [`tokio`] runtime created for the call. An [`RconClient`] keeps its runtime around
+//! for as long as its connection.
+//!
+//! None of these may be called from within an async runtime, since that would block
+//! it. Use the async functions there instead.
+//!
+//! Requires the `blocking` feature, which pulls in tokio's `rt` feature.
+
+#[cfg(any(feature = "status", feature = "query"))]
+use std::future::Future;
+#[cfg(any(feature = "status", feature = "query", feature = "rcon"))]
+use std::time::Duration;
+#[cfg(any(feature = "status", feature = "query", feature = "rcon"))]
+use tokio::runtime::{Builder, Runtime};
+
+#[cfg(any(feature = "status", feature = "query", feature = "rcon"))]
+use crate::errors::Result;
+#[cfg(feature = "query")]
+use crate::query::{self, BasicStatResponse, FullStatResponse};
+#[cfg(feature = "rcon")]
+use crate::rcon;
+#[cfg(feature = "status")]
+use crate::status::{self, data::StatusResponse};
+
+/// Create the runtime a blocking call is driven on.
+#[cfg(any(feature = "status", feature = "query", feature = "rcon"))]
+fn runtime() -> Result<Runtime> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
+
+/// Drive `fut` to completion on a new runtime.
+#[cfg(any(feature = "status", feature = "query"))]
+fn block_on<T>(fut: impl Future<Output = Result<T>>) -> Result<T> {
+    runtime()?.block_on(fut)
+}
+
+/// Blocking version of [`status::status`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`status::status`] does, and also when the
+/// runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::blocking;
+///
+/// fn main() -> mc_query::Result<()> {
+///     let data = blocking::status("localhost", 25565)?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "status")]
+pub fn status(host: &str, port: u16) -> Result<StatusResponse> {
+    block_on(status::status(host, port))
+}
+
+/// Blocking version of [`status::status_with_timeout`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`status::status_with_timeout`] does, and also
+/// when the runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+#[cfg(feature = "status")]
+pub fn status_with_timeout(host: &str, port: u16, dur: Duration) -> Result<StatusResponse> {
+    block_on(status::status_with_timeout(host, port, dur))
+}
+
+/// Blocking version of [`query::stat_basic`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`query::stat_basic`] does, and also when the
+/// runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+#[cfg(feature = "query")]
+pub fn stat_basic(host: &str, port: u16) -> Result<BasicStatResponse> {
+    block_on(query::stat_basic(host, port))
+}
+
+/// Blocking version of [`query::stat_basic_with_timeout`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`query::stat_basic_with_timeout`] does, and also
+/// when the runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+#[cfg(feature = "query")]
+pub fn stat_basic_with_timeout(host: &str, port: u16, dur: Duration) -> Result<BasicStatResponse> {
+    block_on(query::stat_basic_with_timeout(host, port, dur))
+}
+
+/// Blocking version of [`query::stat_full`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`query::stat_full`] does, and also when the
+/// runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+#[cfg(feature = "query")]
+pub fn stat_full(host: &str, port: u16) -> Result<FullStatResponse> {
+    block_on(query::stat_full(host, port))
+}
+
+/// Blocking version of [`query::stat_full_with_timeout`].
+///
+/// # Errors
+/// Returns `Err` on any condition that [`query::stat_full_with_timeout`] does, and also
+/// when the runtime could not be created.
+///
+/// # Panics
+/// Panics when called from within an async runtime.
+#[cfg(feature = "query")]
+pub fn stat_full_with_timeout(host: &str, port: u16, dur: Duration) -> Result<FullStatResponse> {
+    block_on(query::stat_full_with_timeout(host, port, dur))
+}
+
+/// Blocking version of [`rcon::RconClient`].
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::blocking::RconClient;
+///
+/// fn main() -> mc_query::Result<()> {
+///     let mut client = RconClient::new("localhost", 25575)?;
+///     client.authenticate("password")?;
+///     println!("{}", client.run_command("list")?);
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "rcon")]
+#[derive(Debug)]
+pub struct RconClient {
+    runtime: Runtime,
+    inner: rcon::RconClient,
+}
+
+#[cfg(feature = "rcon")]
+impl RconClient {
+    /// Blocking version of [`rcon::RconClient::new`].
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or the runtime could not be created.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn new(host: &str, port: u16) -> Result<Self> {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(rcon::RconClient::new(host, port))?;
+
+        Ok(Self { runtime, inner })
+    }
+
+    /// Blocking version of [`rcon::RconClient::with_timeout`].
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or the runtime could not be created.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn with_timeout(host: &str, port: u16, timeout: Duration) -> Result<Self> {
+        let mut client = Self::new(host, port)?;
+        client.set_timeout(Some(timeout));
+
+        Ok(client)
+    }
+
+    /// See [`rcon::RconClient::set_timeout`].
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.inner.set_timeout(timeout);
+    }
+
+    /// See [`rcon::RconClient::set_auto_reconnect`].
+    pub fn set_auto_reconnect(&mut self, auto_reconnect: bool) {
+        self.inner.set_auto_reconnect(auto_reconnect);
+    }
+
+    /// Blocking version of [`rcon::RconClient::reconnect`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`rcon::RconClient::reconnect`] does.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.reconnect())
+    }
+
+    /// Blocking version of [`rcon::RconClient::authenticate`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`rcon::RconClient::authenticate`] does.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn authenticate(&mut self, password: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.authenticate(password))
+    }
+
+    /// Blocking version of [`rcon::RconClient::run_command`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`rcon::RconClient::run_command`] does.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn run_command(&mut self, command: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.run_command(command))
+    }
+
+    /// Blocking version of [`rcon::RconClient::run_commands`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`rcon::RconClient::run_commands`] does.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn run_commands(&mut self, commands: &[&str]) -> Result<Vec<String>> {
+        self.runtime.block_on(self.inner.run_commands(commands))
+    }
+
+    /// Blocking version of [`rcon::RconClient::disconnect`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`rcon::RconClient::disconnect`] does.
+    ///
+    /// # Panics
+    /// Panics when called from within an async runtime.
+    pub fn disconnect(self) -> Result<()> {
+        self.runtime.block_on(self.inner.disconnect())
+    }
+}
+
+#[cfg(all(test, any(feature = "status", feature = "rcon")))]
+mod tests {
+    use std::{net::TcpListener, time::Duration};
+
+    #[cfg(feature = "status")]
+    #[test]
+    fn test_blocking_status_timeout() {
+        use super::status_with_timeout;
+
+        // connections to this listener are never accepted, so the server never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let err = status_with_timeout("127.0.0.1", port, Duration::from_millis(50)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(feature = "rcon")]
+    #[test]
+    fn test_blocking_rcon() {
+        use super::RconClient;
+        use std::{
+            io::{Read, Write},
+            thread,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // answer a login with success, echoing its request id
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = [0; 4 + 4 + 4 + 8 + 2];
+            socket.read_exact(&mut request).unwrap();
+
+            let mut response = vec![10, 0, 0, 0];
+            response.extend_from_slice(&request[4..8]);
+            response.extend_from_slice(&[2, 0, 0, 0, 0, 0]);
+            socket.write_all(&response).unwrap();
+        });
+
+        let mut client =
+            RconClient::with_timeout("127.0.0.1", port, Duration::from_secs(5)).unwrap();
+        client.authenticate("password").unwrap();
+        server.join().unwrap();
+    }
+}
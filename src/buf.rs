@@ -0,0 +1,110 @@
+//! Reading Minecraft protocol data types from in-memory buffers.
+//!
+//! These mirror the reads done on sockets by the rest of the crate, with the same
+//! validation and length limits, for when the data has already been received.
+
+use bytes::{Buf, Bytes};
+use std::io::{Error, ErrorKind};
+use tokio::io::Result;
+
+use crate::{
+    errors::MinecraftProtocolError,
+    socket::check_field_len,
+    varint::{VarInt, CONTINUE_BIT, MAX_VARINT_LEN},
+};
+
+/// Read a [`VarInt`](https://wiki.vg/Protocol#VarInt_and_VarLong) from the start of
+/// the buffer, advancing past it.
+///
+/// # Errors
+/// Returns `Err` if the buffer ends before the end of the `VarInt`, or if it is
+/// longer than the maximum of 5 bytes.
+///
+/// # Examples
+/// ```
+/// use bytes::Bytes;
+/// use mc_query::buf::read_varint_buf;
+///
+/// let mut buf = Bytes::from_static(b"\xdd\xc7\x01rest");
+/// assert_eq!(read_varint_buf(&mut buf).unwrap(), 25565);
+/// assert_eq!(buf, "rest");
+/// ```
+pub fn read_varint_buf(buf: &mut Bytes) -> Result<i32> {
+    let len = buf
+        .iter()
+        .take(MAX_VARINT_LEN)
+        .position(|byte| byte & CONTINUE_BIT == 0)
+        .ok_or_else(|| {
+            if buf.len() < MAX_VARINT_LEN {
+                Error::from(ErrorKind::UnexpectedEof)
+            } else {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    MinecraftProtocolError::InvalidVarInt,
+                )
+            }
+        })?;
+
+    VarInt::new(buf.split_to(len + 1))
+        .try_into()
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+/// Read a length-prefixed byte array from the start of the buffer, advancing past it.
+///
+/// # Errors
+/// Returns `Err` if the length prefix is invalid, or the buffer is shorter than it indicates.
+pub fn read_mc_bytes_buf(buf: &mut Bytes) -> Result<Bytes> {
+    let len = check_field_len(read_varint_buf(buf)?)?;
+    if buf.remaining() < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(buf.split_to(len))
+}
+
+/// Read a [string](https://wiki.vg/Protocol#Data_types) (UTF-8, prefixed with its
+/// length as a `VarInt`) from the start of the buffer, advancing past it.
+///
+/// # Errors
+/// Returns `Err` if the length prefix is invalid, the buffer is shorter than it indicates,
+/// or the string is not valid UTF-8.
+pub fn read_mc_string_buf(buf: &mut Bytes) -> Result<String> {
+    let bytes = read_mc_bytes_buf(buf)?;
+
+    String::from_utf8(bytes.to_vec()).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_mc_string_buf, read_varint_buf};
+    use bytes::Bytes;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_read_varint_buf() {
+        let mut buf = Bytes::from_static(b"\xff\xff\xff\xff\x0f\x00");
+        assert_eq!(read_varint_buf(&mut buf).unwrap(), -1);
+        assert_eq!(read_varint_buf(&mut buf).unwrap(), 0);
+
+        let mut truncated = Bytes::from_static(b"\x80\x80");
+        let err = read_varint_buf(&mut truncated).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let mut too_long = Bytes::from_static(b"\xff\xff\xff\xff\xff\x01");
+        let err = read_varint_buf(&mut too_long).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_mc_string_buf() {
+        let mut buf = Bytes::from_static(b"\x05hello\x05hi");
+        assert_eq!(read_mc_string_buf(&mut buf).unwrap(), "hello");
+
+        let err = read_mc_string_buf(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let mut negative = Bytes::from_static(b"\xff\xff\xff\xff\x0f");
+        assert!(read_mc_string_buf(&mut negative).is_err());
+    }
+}
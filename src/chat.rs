@@ -0,0 +1,105 @@
+//! Helpers for working with legacy `§`-formatted text.
+
+/// The section sign, which prefixes legacy formatting codes.
+pub(crate) const SECTION_SIGN: char = '§';
+
+/// Remove `§` formatting codes (the section sign and the character following it)
+/// from the given string, leaving everything else as it is.
+///
+/// Servers send these codes in the MOTD of a [Query](crate::query) response, in
+/// [RCON](crate::rcon) output, and in the legacy status, where they show up as
+/// garbage anywhere that doesn't render them. A section sign at the very end of the
+/// string, with no code after it, is removed as well.
+///
+/// # Examples
+/// ```
+/// use mc_query::chat::strip_formatting;
+///
+/// assert_eq!(strip_formatting("§6§lA §rMinecraft Server"), "A Minecraft Server");
+/// assert_eq!(strip_formatting("50%§"), "50%");
+/// ```
+#[must_use]
+pub fn strip_formatting(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == SECTION_SIGN {
+            // skip the code character as well
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// The named colors, with their legacy format codes and RGB values.
+/// See <https://wiki.vg/Chat#Colors>.
+#[cfg(any(feature = "status", test))]
+const COLORS: [(&str, char, u32); 16] = [
+    ("black", '0', 0x00_00_00),
+    ("dark_blue", '1', 0x00_00_aa),
+    ("dark_green", '2', 0x00_aa_00),
+    ("dark_aqua", '3', 0x00_aa_aa),
+    ("dark_red", '4', 0xaa_00_00),
+    ("dark_purple", '5', 0xaa_00_aa),
+    ("gold", '6', 0xff_aa_00),
+    ("gray", '7', 0xaa_aa_aa),
+    ("dark_gray", '8', 0x55_55_55),
+    ("blue", '9', 0x55_55_ff),
+    ("green", 'a', 0x55_ff_55),
+    ("aqua", 'b', 0x55_ff_ff),
+    ("red", 'c', 0xff_55_55),
+    ("light_purple", 'd', 0xff_55_ff),
+    ("yellow", 'e', 0xff_ff_55),
+    ("white", 'f', 0xff_ff_ff),
+];
+
+/// Get the RGB value of a color of a chat component, either a named color
+/// (such as `gold`) or a hex color (such as `#ffaa00`).
+#[cfg(any(feature = "status", test))]
+pub(crate) fn parse_color(color: &str) -> Option<u32> {
+    if let Some(hex) = color.strip_prefix('#') {
+        return if hex.len() == 6 {
+            u32::from_str_radix(hex, 16).ok()
+        } else {
+            None
+        };
+    }
+
+    COLORS
+        .iter()
+        .find(|(name, _, _)| *name == color)
+        .map(|(_, _, rgb)| *rgb)
+}
+
+/// Get the name of the color selected by a legacy format code (such as `6` in `§6`).
+#[cfg(any(feature = "status", test))]
+pub(crate) fn legacy_color_name(code: char) -> Option<&'static str> {
+    let code = code.to_ascii_lowercase();
+
+    COLORS
+        .iter()
+        .find(|(_, c, _)| *c == code)
+        .map(|(name, _, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{legacy_color_name, parse_color, strip_formatting};
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(parse_color("gold"), Some(0xff_aa_00));
+        assert_eq!(parse_color("#12aBcD"), Some(0x12_ab_cd));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("reset"), None);
+        assert_eq!(legacy_color_name('A'), Some("green"));
+        assert_eq!(legacy_color_name('l'), None);
+        assert_eq!(strip_formatting("\u{a7}6gold\u{a7}r!"), "gold!");
+        assert_eq!(strip_formatting("\u{a7}\u{a7}a\u{a7}"), "a");
+        assert_eq!(strip_formatting("no codes: 100%"), "no codes: 100%");
+    }
+}
@@ -0,0 +1,148 @@
+//! Find out which protocol a server speaks on a given port.
+//! See documentation for [`detect_protocol`] for more information.
+
+use std::time::Duration;
+use tokio::{net::TcpStream, time::timeout};
+
+use crate::{
+    errors::{timeout_err, Result},
+    rcon::RconClient,
+    socket::host_port,
+    status::{status_legacy_with_timeout, status_with_timeout},
+};
+
+/// A protocol detected by [`detect_protocol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedProtocol {
+    /// The modern [Server List Ping](https://wiki.vg/Server_List_Ping), spoken by
+    /// Minecraft 1.7 and later. Use [`status`](crate::status::status).
+    ModernStatus,
+
+    /// Only the [legacy Server List Ping](https://wiki.vg/Server_List_Ping#1.6), spoken by
+    /// Minecraft 1.6 and earlier. Use [`status_legacy`](crate::status::status_legacy).
+    LegacyStatus,
+
+    /// [RCON](https://wiki.vg/RCON). Use [`RconClient`].
+    Rcon,
+
+    /// The port accepts connections, but none of the protocols above was recognized.
+    Unknown,
+}
+
+/// Connect to the given port and find out which protocol it speaks.
+///
+/// The protocols are probed one after the other, each on a new connection, and the
+/// first one the server responds to correctly is returned:
+/// 1. A modern status request, which must be answered with a valid status response.
+/// 2. A legacy (1.6) status request, which must be answered with a valid kick packet.
+///    Modern servers answer these too, which is why they are tried second.
+/// 3. An RCON command without authenticating first, which must be answered with a
+///    valid RCON packet. Servers refuse to run the command, so nothing is executed.
+///
+/// The probes are heuristics, so their limits apply: a server that is too slow to
+/// answer within `dur`, or answers with data that doesn't quite follow the protocol,
+/// is reported as [`DetectedProtocol::Unknown`]. [Query](crate::query) runs over UDP,
+/// so it is never detected.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to probe.
+/// * `dur` - How long to wait for each probe, so the whole detection can take up to
+///   four times as long.
+///
+/// # Errors
+/// Returns `Err` if the port could not be connected to within `dur` at all.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::detect::detect_protocol;
+/// use std::time::Duration;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let protocol = detect_protocol("localhost", 25575, Duration::from_secs(2)).await?;
+///     println!("{protocol:?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn detect_protocol(host: &str, port: u16, dur: Duration) -> Result<DetectedProtocol> {
+    // make sure there's something to probe, so connection errors are reported
+    // instead of being mistaken for an unknown protocol
+    timeout(dur, TcpStream::connect(host_port(host, port)))
+        .await
+        .unwrap_or(timeout_err())?;
+
+    if status_with_timeout(host, port, dur).await.is_ok() {
+        return Ok(DetectedProtocol::ModernStatus);
+    }
+
+    if status_legacy_with_timeout(host, port, dur).await.is_ok() {
+        return Ok(DetectedProtocol::LegacyStatus);
+    }
+
+    let rcon = async {
+        let mut client = RconClient::new(host, port).await?;
+        client.probe().await
+    };
+    if timeout(dur, rcon).await.is_ok_and(|res| res.is_ok()) {
+        return Ok(DetectedProtocol::Rcon);
+    }
+
+    Ok(DetectedProtocol::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_protocol, DetectedProtocol};
+    use std::time::Duration;
+    use tokio::{
+        io::{self, AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_detect_rcon() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        // answers the packet of an empty command (length 10) with a failed authentication,
+        // and closes any other connection
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                if conn.read_i32_le().await.ok() == Some(10) {
+                    let mut rest = [0; 10];
+                    conn.read_exact(&mut rest).await.unwrap();
+                    conn.write_all(b"\x0a\x00\x00\x00\xff\xff\xff\xff\x02\x00\x00\x00\x00\x00")
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let protocol = detect_protocol("127.0.0.1", port, Duration::from_secs(1)).await?;
+        assert_eq!(protocol, DetectedProtocol::Rcon);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_detect_unknown() -> io::Result<()> {
+        // accepts connections, but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let protocol = detect_protocol("127.0.0.1", port, Duration::from_millis(50)).await?;
+        assert_eq!(protocol, DetectedProtocol::Unknown);
+
+        drop(listener);
+        assert!(
+            detect_protocol("127.0.0.1", port, Duration::from_millis(50))
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -3,6 +3,181 @@
 use std::io::{self, ErrorKind};
 use thiserror::Error;
 
+/// The error type returned by all functions in this crate.
+///
+/// Network errors are passed through as [`Error::Io`], while violations of one of the
+/// protocols are returned as the error of that protocol, so they can be matched on
+/// without downcasting.
+///
+/// This converts to and from [`io::Error`], so `?` can be used to return it from
+/// functions that return an [`io::Result`]. Protocol errors become errors of kind
+/// [`ErrorKind::InvalidData`] that way (except for
+/// [`QueryProtocolError::QueryDisabled`], which is a timeout), and are unwrapped again
+/// when converted back.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::{errors::RconProtocolError, rcon::RconClient, Error};
+///
+/// #[tokio::main]
+/// async fn main() -> std::io::Result<()> {
+///     let mut client = RconClient::new("localhost", 25575).await?;
+///
+///     match client.authenticate("password").await {
+///         Ok(()) => println!("authenticated"),
+///         Err(Error::Rcon(RconProtocolError::AuthFailed)) => println!("wrong password"),
+///         Err(err) => return Err(err.into()),
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Error, Debug)]
+pub enum Error {
+    /// A network error, or another error from the OS.
+    #[error(transparent)]
+    Io(io::Error),
+
+    /// An error from the Minecraft networking protocol, used by the Server List Ping.
+    #[error(transparent)]
+    Minecraft(#[from] MinecraftProtocolError),
+
+    /// An error from the RCON protocol.
+    #[error(transparent)]
+    Rcon(#[from] RconProtocolError),
+
+    /// An error from the Query protocol.
+    #[error(transparent)]
+    Query(#[from] QueryProtocolError),
+
+    /// An error from the RakNet protocol, used by the
+    /// [Bedrock Edition status](crate::bedrock::status_bedrock).
+    #[error(transparent)]
+    Bedrock(#[from] BedrockProtocolError),
+
+    /// One of several commands failed, returned by
+    /// [`RconClient::run_commands`](crate::rcon::RconClient::run_commands).
+    #[error("command {index} (`{command}`) failed: {source}")]
+    Command {
+        /// The position of the command that failed in the list of commands.
+        index: usize,
+
+        /// The command that failed.
+        command: String,
+
+        /// Why the command failed.
+        source: Box<Error>,
+    },
+
+    /// An error from a request to the server at the given address, returned by functions
+    /// that talk to many servers at once, like [`Scanner`](crate::status::Scanner), so each
+    /// error can be told apart. Errors of single requests can be wrapped with
+    /// [`with_addr`](Self::with_addr).
+    #[error("{addr}: {source}")]
+    Server {
+        /// The address of the server, as `host:port`.
+        addr: String,
+
+        /// Why the request failed.
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The kind of [`io::Error`] this converts to. Protocol errors are
+    /// [`ErrorKind::InvalidData`], except for [`QueryProtocolError::QueryDisabled`], which
+    /// is [`ErrorKind::TimedOut`].
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(err) => err.kind(),
+            Self::Query(err) => err.kind(),
+            Self::Minecraft(_) | Self::Rcon(_) | Self::Bedrock(_) => ErrorKind::InvalidData,
+            Self::Command { source, .. } | Self::Server { source, .. } => source.kind(),
+        }
+    }
+
+    /// Wrap this error in [`Error::Server`], along with the address of the server it
+    /// came from.
+    #[must_use]
+    pub fn with_addr(self, addr: impl Into<String>) -> Self {
+        Self::Server {
+            addr: addr.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The address of the server this error came from, if it was wrapped in
+    /// [`Error::Server`].
+    #[must_use]
+    pub fn addr(&self) -> Option<&str> {
+        match self {
+            Self::Server { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Whether this error is the result of a timeout, see [`is_timeout`].
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        self.kind() == ErrorKind::TimedOut
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        // errors of this crate that were converted into io::Errors along the way
+        let is_protocol_error = err.get_ref().is_some_and(|inner| {
+            inner.is::<Error>()
+                || inner.is::<MinecraftProtocolError>()
+                || inner.is::<RconProtocolError>()
+                || inner.is::<QueryProtocolError>()
+                || inner.is::<BedrockProtocolError>()
+        });
+        if !is_protocol_error {
+            return Self::Io(err);
+        }
+
+        let inner = err.into_inner().expect("checked above");
+        let inner = match inner.downcast::<Error>() {
+            Ok(err) => return *err,
+            Err(inner) => inner,
+        };
+        let inner = match inner.downcast::<MinecraftProtocolError>() {
+            Ok(err) => return Self::Minecraft(*err),
+            Err(inner) => inner,
+        };
+        let inner = match inner.downcast::<RconProtocolError>() {
+            Ok(err) => return Self::Rcon(*err),
+            Err(inner) => inner,
+        };
+        let inner = match inner.downcast::<QueryProtocolError>() {
+            Ok(err) => return Self::Query(*err),
+            Err(inner) => inner,
+        };
+        match inner.downcast::<BedrockProtocolError>() {
+            Ok(err) => Self::Bedrock(*err),
+            Err(_) => unreachable!("checked above"),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            Error::Minecraft(err) => err.into(),
+            Error::Rcon(err) => err.into(),
+            Error::Query(err) => err.into(),
+            Error::Bedrock(err) => err.into(),
+            err @ (Error::Command { .. } | Error::Server { .. }) => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+/// A [`Result`](std::result::Result) with this crate's [`Error`](enum@Error) type.
+pub type Result<T> = std::result::Result<T, Error>;
+
 /// An error from the Minecraft networking protocol.
 #[derive(Error, Debug)]
 pub enum MinecraftProtocolError {
@@ -10,6 +185,10 @@ pub enum MinecraftProtocolError {
     #[error("invalid varint data")]
     InvalidVarInt,
 
+    /// `VarLong` data was invalid according to the spec.
+    #[error("invalid varlong data")]
+    InvalidVarLong,
+
     /// Received invalid state information from the server.
     #[error("invalid state")]
     InvalidState,
@@ -17,6 +196,18 @@ pub enum MinecraftProtocolError {
     /// Received incorrectly formatted status response from the server.
     #[error("invalid status response")]
     InvalidStatusResponse,
+
+    /// A length prefix was negative or exceeded the maximum allowed length.
+    #[error("invalid length")]
+    InvalidLength,
+
+    /// The response was larger than the configured maximum.
+    #[error("response too large")]
+    ResponseTooLarge,
+
+    /// The favicon is not a base64 encoded PNG image.
+    #[error("invalid favicon")]
+    InvalidFavicon,
 }
 
 impl From<MinecraftProtocolError> for io::Error {
@@ -36,6 +227,11 @@ pub enum RconProtocolError {
     #[error("non-ascii payload")]
     NonAsciiPayload,
 
+    /// Received a payload that is not valid UTF-8 from the server, with UTF-8 payloads
+    /// enabled (see [`RconClient::set_allow_utf8`](crate::rcon::RconClient::set_allow_utf8)).
+    #[error("invalid UTF-8 payload")]
+    InvalidUtf8,
+
     /// Authentication failed. You probably entered the wrong RCON password.
     #[error("authentication failed")]
     AuthFailed,
@@ -64,6 +260,23 @@ pub enum RconProtocolError {
     /// This variant is returned if any *other* request ID was received.
     #[error("request id mismatch")]
     RequestIdMismatch,
+
+    /// A command was run before authenticating with
+    /// [`RconClient::authenticate`](crate::rcon::RconClient::authenticate).
+    #[error("not authenticated")]
+    NotAuthenticated,
+
+    /// A previous operation was cancelled before it finished, so the connection
+    /// is in an unknown state. A new connection must be established.
+    #[error("connection desynchronized by a cancelled operation")]
+    Desynchronized,
+
+    /// The output of a command was not in the expected format.
+    ///
+    /// Returned by the helpers that run a specific command and parse its output,
+    /// for example when the server doesn't know the command.
+    #[error("unexpected command output")]
+    UnexpectedOutput,
 }
 
 impl From<RconProtocolError> for io::Error {
@@ -106,14 +319,181 @@ pub enum QueryProtocolError {
     /// Expecting something like [this](https://wiki.vg/Query#K.2C_V_section)
     #[error("invalid key/value section")]
     InvalidKeyValueSection,
+
+    /// The packet ended before all the data that should be in it was read.
+    #[error("truncated packet")]
+    TruncatedPacket,
+
+    /// The server never answered the handshake, even after
+    /// [retrying](crate::query::QueryConfig::retries).
+    ///
+    /// This usually means that Query is disabled on the server (`enable-query=false`
+    /// in its properties), or that the Query port is wrong or blocked by a firewall.
+    ///
+    /// Like any other timeout, this is of kind [`ErrorKind::TimedOut`], so it is
+    /// reported by [`is_timeout`] whether the handshake or a timeout around the whole
+    /// request gave up first.
+    #[error("no response to the handshake, is query enabled on the server?")]
+    QueryDisabled,
+}
+
+impl QueryProtocolError {
+    /// The kind of [`io::Error`] this converts to: [`ErrorKind::TimedOut`] for
+    /// [`QueryDisabled`](Self::QueryDisabled), and [`ErrorKind::InvalidData`] otherwise.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::QueryDisabled => ErrorKind::TimedOut,
+            _ => ErrorKind::InvalidData,
+        }
+    }
 }
 
 impl From<QueryProtocolError> for io::Error {
     fn from(err: QueryProtocolError) -> Self {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// An error from the RakNet protocol, used by the Bedrock Edition status.
+#[derive(Error, Debug)]
+pub enum BedrockProtocolError {
+    /// Received a packet other than an Unconnected Pong.
+    #[error("invalid packet id")]
+    InvalidPacketId,
+
+    /// The packet did not contain the magic bytes of offline RakNet messages.
+    #[error("invalid magic")]
+    InvalidMagic,
+
+    /// The server info string was not valid UTF-8, or was missing required fields.
+    #[error("invalid server info")]
+    InvalidServerInfo,
+
+    /// The packet ended before all the data that should be in it was read.
+    #[error("truncated packet")]
+    TruncatedPacket,
+}
+
+impl From<BedrockProtocolError> for io::Error {
+    fn from(err: BedrockProtocolError) -> Self {
         io::Error::new(ErrorKind::InvalidData, err)
     }
 }
 
+/// Whether the error is the result of a timeout, either one given to a function of this
+/// crate, or one reported by the OS while connecting.
+///
+/// Every timeout in this crate is reported as an error of kind [`ErrorKind::TimedOut`],
+/// which is what this checks, so it can be told apart from a closed connection or an
+/// invalid response without looking at the message. This includes a Query handshake that
+/// was never answered, which carries [`QueryProtocolError::QueryDisabled`] as its inner
+/// error to tell it apart from other timeouts. Use [`Error::is_timeout`] for the
+/// errors returned by this crate directly.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::{errors::is_timeout, status::status_with_timeout};
+/// use std::{io, time::Duration};
+///
+/// #[tokio::main]
+/// async fn main() -> io::Result<()> {
+///     for _ in 0..3 {
+///         match status_with_timeout("localhost", 25565, Duration::from_secs(1)).await {
+///             Ok(data) => {
+///                 println!("{data:#?}");
+///                 break;
+///             }
+///             Err(err) => {
+///                 let err = io::Error::from(err);
+///                 if !is_timeout(&err) {
+///                     return Err(err);
+///                 }
+///             }
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[must_use]
+pub fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == ErrorKind::TimedOut
+}
+
+/// The error returned when a timeout expires, of kind [`ErrorKind::TimedOut`].
+#[cfg(any(
+    feature = "status",
+    feature = "query",
+    feature = "rcon",
+    feature = "bedrock"
+))]
 pub(crate) fn timeout_err<T>() -> io::Result<T> {
     Err(io::Error::new(ErrorKind::TimedOut, "connection timed out"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_timeout, Error, QueryProtocolError, RconProtocolError};
+    use std::io::{self, ErrorKind};
+
+    #[test]
+    fn test_io_error_round_trip() {
+        let err = io::Error::from(RconProtocolError::AuthFailed);
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = Error::from(err);
+        assert!(matches!(err, Error::Rcon(RconProtocolError::AuthFailed)));
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = Error::from(io::Error::new(ErrorKind::TimedOut, "connection timed out"));
+        assert!(matches!(err, Error::Io(_)));
+        assert_eq!(io::Error::from(err).kind(), ErrorKind::TimedOut);
+
+        let err = Error::Command {
+            index: 1,
+            command: "tps".to_string(),
+            source: Box::new(RconProtocolError::UnexpectedOutput.into()),
+        };
+        assert_eq!(
+            err.to_string(),
+            "command 1 (`tps`) failed: unexpected command output"
+        );
+        let err = Error::from(io::Error::from(err));
+        assert!(matches!(err, Error::Command { index: 1, .. }));
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = Error::from(RconProtocolError::AuthFailed).with_addr("localhost:25575");
+        assert_eq!(err.to_string(), "localhost:25575: authentication failed");
+        let err = Error::from(io::Error::from(err));
+        assert_eq!(err.addr(), Some("localhost:25575"));
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_is_timeout() {
+        let err = io::Error::from(ErrorKind::TimedOut);
+        assert!(is_timeout(&err));
+        assert!(Error::from(err).is_timeout());
+
+        assert!(!is_timeout(&io::Error::from(ErrorKind::ConnectionReset)));
+        assert!(!Error::from(RconProtocolError::AuthFailed).is_timeout());
+
+        // an unanswered query handshake stays a timeout through both conversions
+        let err = io::Error::from(QueryProtocolError::QueryDisabled);
+        assert!(is_timeout(&err));
+        let err = Error::from(err);
+        assert!(matches!(
+            err,
+            Error::Query(QueryProtocolError::QueryDisabled)
+        ));
+        assert!(err.is_timeout());
+
+        let err = Error::Command {
+            index: 0,
+            command: "list".to_string(),
+            source: Box::new(io::Error::from(ErrorKind::TimedOut).into()),
+        };
+        assert!(err.is_timeout());
+    }
+}
@@ -0,0 +1,141 @@
+//! Combine the information a server reports over the [Server List Ping](https://wiki.vg/Server_List_Ping)
+//! and [Query](https://wiki.vg/Query) protocols.
+//! See documentation for [`server_info`] for more information.
+
+use crate::{
+    errors::Result,
+    query::{stat_full, FullStatResponse},
+    status::{data::StatusResponse, status},
+};
+
+/// Everything a server reports about itself, over both the [Server List Ping](https://wiki.vg/Server_List_Ping)
+/// and [Query](https://wiki.vg/Query) protocols.
+#[derive(Debug)]
+pub struct ServerInfo {
+    /// The response to the status request.
+    pub status: StatusResponse,
+
+    /// The response to the full stat query.
+    pub query: FullStatResponse,
+}
+
+/// How the player counts a server reports over the two protocols compare.
+/// Returned by [`ServerInfo::player_count_discrepancy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerCountDiscrepancy {
+    /// The number of players online according to the status response.
+    pub status: u32,
+
+    /// The number of players online according to the query response.
+    pub query: u32,
+
+    /// Whether the counts differ by more than can be explained by players
+    /// joining or leaving between the two requests.
+    pub significant: bool,
+}
+
+impl ServerInfo {
+    /// Compare the number of players online in the status response against the one in
+    /// the query response.
+    ///
+    /// Some servers inflate the player count in their status response to rank higher on
+    /// server lists. This is easy to do with plugins, since the status response is
+    /// entirely made up by the server, while the count in the query response is
+    /// usually left alone. When the two differ, the query count is generally the more
+    /// trustworthy one.
+    ///
+    /// A difference is considered significant if it is larger than 2 players, or 10% of
+    /// the query count, whichever is greater.
+    #[must_use]
+    pub fn player_count_discrepancy(&self) -> PlayerCountDiscrepancy {
+        let status = self.status.players.online;
+        let query = u32::try_from(self.query.num_players).unwrap_or(u32::MAX);
+
+        let tolerance = (query / 10).max(2);
+
+        PlayerCountDiscrepancy {
+            status,
+            query,
+            significant: status.abs_diff(query) > tolerance,
+        }
+    }
+}
+
+/// Get the [status](crate::status) and [full query](crate::query::stat_full) of a server
+/// at the same time.
+///
+/// Note that the server must have `enable-query=true` set in its properties.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `status_port` - The port the server accepts status requests on.
+/// * `query_port` - The port the server accepts queries on.
+///
+/// # Errors
+/// Returns `Err` if either request fails, for any of the reasons [`status`]
+/// and [`stat_full`] do.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::info::server_info;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let info = server_info("localhost", 25565, 25565).await?;
+///     let check = info.player_count_discrepancy();
+///     if check.significant {
+///         println!("status says {}, query says {}", check.status, check.query);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn server_info(host: &str, status_port: u16, query_port: u16) -> Result<ServerInfo> {
+    let (status, query) = tokio::try_join!(status(host, status_port), stat_full(host, query_port))?;
+
+    Ok(ServerInfo { status, query })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServerInfo;
+    use crate::query::FullStatResponse;
+    use std::collections::HashMap;
+
+    fn info(status_online: u32, query_online: usize) -> ServerInfo {
+        let status = serde_json::from_value(serde_json::json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "players": { "max": 100, "online": status_online },
+        }))
+        .unwrap();
+
+        let query = FullStatResponse {
+            motd: String::new(),
+            game_type: "SMP".to_string(),
+            game_id: "MINECRAFT".to_string(),
+            version: "1.20.1".to_string(),
+            plugins: String::new(),
+            map: "world".to_string(),
+            num_players: query_online,
+            max_players: 100,
+            host_port: 25565,
+            host_ip: "127.0.0.1".to_string(),
+            players: Vec::new(),
+            extra: HashMap::new(),
+        };
+
+        ServerInfo { status, query }
+    }
+
+    #[test]
+    fn test_player_count_discrepancy() {
+        let check = info(12, 10).player_count_discrepancy();
+        assert_eq!((check.status, check.query), (12, 10));
+        assert!(!check.significant);
+
+        assert!(info(50, 10).player_count_discrepancy().significant);
+        assert!(!info(95, 100).player_count_discrepancy().significant);
+        assert!(info(0, 5).player_count_discrepancy().significant);
+    }
+}
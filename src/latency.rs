@@ -0,0 +1,76 @@
+//! Statistics over latency measurements, shared by the Server List Ping and Query.
+
+use std::time::Duration;
+
+/// The time [`status_latency_samples`](crate::status::status_latency_samples) and
+/// [`QueryClient::handshake_latency`](crate::query::QueryClient::handshake_latency)
+/// wait between taking two samples.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Statistics over several latency measurements of a server.
+///
+/// Returned by [`status_latency_samples`](crate::status::status_latency_samples) and
+/// [`QueryClient::handshake_latency`](crate::query::QueryClient::handshake_latency).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// The number of samples taken.
+    pub samples: usize,
+
+    /// The lowest latency measured.
+    pub min: Duration,
+
+    /// The highest latency measured.
+    pub max: Duration,
+
+    /// The average of all latencies measured.
+    pub mean: Duration,
+
+    /// The median of all latencies measured.
+    pub median: Duration,
+}
+
+impl LatencyStats {
+    /// Compute the statistics over the given samples.
+    /// Returns `None` if there are no samples.
+    pub(crate) fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+
+        let count = samples.len();
+        let median = if count.is_multiple_of(2) {
+            (samples[count / 2 - 1] + samples[count / 2]) / 2
+        } else {
+            samples[count / 2]
+        };
+
+        Some(Self {
+            samples: count,
+            min: samples[0],
+            max: samples[count - 1],
+            mean: samples.iter().sum::<Duration>() / count as u32,
+            median,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LatencyStats;
+    use std::time::Duration;
+
+    #[test]
+    fn test_latency_stats() {
+        let samples = [40, 10, 30, 20].map(Duration::from_millis).to_vec();
+        let stats = LatencyStats::from_samples(samples).unwrap();
+
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.max, Duration::from_millis(40));
+        assert_eq!(stats.mean, Duration::from_millis(25));
+        assert_eq!(stats.median, Duration::from_millis(25));
+
+        assert_eq!(LatencyStats::from_samples(vec![]), None);
+    }
+}
@@ -9,12 +9,17 @@
 #![allow(clippy::cast_sign_loss)]
 #![allow(clippy::cast_lossless)]
 
+#[cfg(any(feature = "status", feature = "query"))]
 macro_rules! create_timeout {
     ($name:ident, $ret:ty) => {
         ::paste::paste! {
             #[doc = concat!("Similar to [`", stringify!($name), "`]")]
             /// but with an added argument for timeout.
             ///
+            /// The timeout covers the whole operation, including connecting
+            /// to the server. When it expires, the connection is dropped and
+            /// its socket closed immediately.
+            ///
             /// Note that timeouts are not precise, and may vary on the order
             /// of milliseconds, because of the way the async event loop works.
             ///
@@ -30,23 +35,70 @@ macro_rules! create_timeout {
                 host: &str,
                 port: u16,
                 dur: ::std::time::Duration,
-            ) -> ::std::io::Result<$ret> {
+            ) -> crate::errors::Result<$ret> {
                 use crate::errors::timeout_err;
                 use ::tokio::time::timeout;
 
                 timeout(dur, $name(host, port))
                     .await
-                    .unwrap_or(timeout_err::<$ret>())
+                    .unwrap_or_else(|_| timeout_err().map_err(Into::into))
             }
         }
     };
 }
 
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod buf;
+pub mod chat;
+#[cfg(all(feature = "status", feature = "rcon"))]
+pub mod detect;
 pub mod errors;
+#[cfg(all(feature = "status", feature = "query"))]
+pub mod info;
+#[cfg(any(feature = "status", feature = "query"))]
+mod latency;
+#[cfg(feature = "status")]
+pub mod login;
+#[cfg(test)]
+// each test only talks to the mock over the protocols it covers
+#[cfg_attr(
+    not(all(feature = "status", feature = "query", feature = "rcon")),
+    allow(dead_code)
+)]
+mod mock;
+#[cfg(feature = "proxy-protocol")]
+pub mod proxy;
+#[cfg(feature = "query")]
 pub mod query;
+#[cfg(feature = "rcon")]
 pub mod rcon;
+pub mod server;
 mod socket;
+#[cfg(feature = "srv")]
+pub mod srv;
+#[cfg(feature = "status")]
 pub mod status;
-mod varint;
+pub mod varint;
 
+pub use errors::{Error, Result};
+pub use server::Server;
+#[cfg(feature = "status")]
 pub use status::status;
+
+/// The default port servers listen on for the [Server List Ping](https://wiki.vg/Server_List_Ping)
+/// (and for players to join), set by `server-port` in `server.properties`.
+pub const DEFAULT_STATUS_PORT: u16 = 25565;
+
+/// The default port for [RCON](https://wiki.vg/RCON), set by `rcon.port` in `server.properties`.
+pub const DEFAULT_RCON_PORT: u16 = 25575;
+
+/// The default port for [Query](https://wiki.vg/Query), set by `query.port` in `server.properties`.
+///
+/// Query uses UDP, so it shares the same port number as the (TCP) server port by default.
+pub const DEFAULT_QUERY_PORT: u16 = 25565;
+
+/// The default port Bedrock Edition servers listen on, set by `server-port` in `server.properties`.
+pub const DEFAULT_BEDROCK_PORT: u16 = 19132;
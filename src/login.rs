@@ -0,0 +1,257 @@
+//! Low-level probing of a server's [login sequence](https://wiki.vg/Protocol_FAQ#What.27s_the_normal_login_sequence_for_a_client.3F).
+//! See the documentation for [`login_start`] and [`is_whitelisted`] for more information.
+
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::{
+    io::{self, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+
+use crate::{
+    buf::{read_mc_string_buf, read_varint_buf},
+    errors::{timeout_err, Result},
+    socket::{host_port, ReadWriteMinecraftBytes},
+    status::{
+        data::ChatObject,
+        packet::{Packet, PacketId},
+    },
+};
+
+/// The protocol version sent in the handshake (that of 1.20.1).
+///
+/// Servers kick clients with a different protocol version during login,
+/// which [`login_start`] reports as a normal disconnect response.
+pub const LOGIN_PROTOCOL_VERSION: i32 = 763;
+
+/// The first packet the server sent in response to a login attempt.
+///
+/// See [wiki.vg](https://wiki.vg/Protocol#Login) for the possible packets.
+/// The most interesting one is usually the Disconnect packet (id `0x00`),
+/// whose payload is the JSON chat message the server kicked the player with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginResponse {
+    /// The id of the packet.
+    pub packet_id: i32,
+
+    /// The payload of the packet, without its length and id.
+    pub payload: Bytes,
+}
+
+/// Start logging in to the server as the given player, and return the first packet
+/// the server responds with.
+///
+/// This performs a handshake with the next state set to login, and sends a Login Start
+/// packet. It does **not** complete a real login: the connection is closed as soon as
+/// the first packet is received. This is enough to read the messages servers send before
+/// letting a player in, like the position in a login queue, or a whitelist kick message.
+///
+/// Servers in online mode respond with an Encryption Request (id `0x01`), as continuing
+/// from there requires authenticating with Mojang.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `username` - The name of the player to log in as.
+/// * `dur` - How long to wait for the whole exchange to complete.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue, the server sent invalid data,
+/// or the response was not recieved within `dur`.
+pub async fn login_start(
+    host: &str,
+    port: u16,
+    username: &str,
+    dur: Duration,
+) -> Result<LoginResponse> {
+    Ok(timeout(dur, login_start_raw(host, port, username))
+        .await
+        .unwrap_or(timeout_err())?)
+}
+
+/// Whether a player is allowed to join a server with a whitelist, as determined by
+/// [`is_whitelisted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WhitelistStatus {
+    /// The server let the player in, so either the player is whitelisted, or the
+    /// whitelist is off.
+    Whitelisted,
+
+    /// The server kicked the player with a whitelist message.
+    NotWhitelisted,
+
+    /// The server's response doesn't tell. This is always the case for servers in
+    /// online mode, which only check the whitelist after the player authenticated
+    /// with Mojang, and for servers that kicked the player for another reason.
+    Unknown,
+}
+
+/// Phrases in kick messages that indicate the player is not on the whitelist.
+const WHITELIST_PHRASES: [&str; 3] = ["white-listed", "whitelisted", "whitelist"];
+
+/// Check whether the given player is allowed to join the server, by starting to
+/// log in as them with [`login_start`].
+///
+/// Servers with a whitelist kick players that are not on it as they log in.
+/// The kick message is the only way to tell why a player was kicked, so this relies on
+/// it mentioning the whitelist, as the vanilla message ("You are not white-listed on this
+/// server!") does. Plugins and translations change the message, so a server kicking
+/// players with a custom message is reported as [`WhitelistStatus::Unknown`].
+///
+/// Only servers in offline mode (such as backends behind a proxy) can be checked,
+/// see [`WhitelistStatus::Unknown`].
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `username` - The name of the player to check.
+/// * `dur` - How long to wait for the whole exchange to complete.
+///
+/// # Errors
+/// Returns `Err` under the same conditions as [`login_start`].
+pub async fn is_whitelisted(
+    host: &str,
+    port: u16,
+    username: &str,
+    dur: Duration,
+) -> Result<WhitelistStatus> {
+    let response = login_start(host, port, username, dur).await?;
+
+    Ok(classify_whitelist(&response))
+}
+
+fn classify_whitelist(response: &LoginResponse) -> WhitelistStatus {
+    match response.packet_id {
+        // Disconnect
+        0x00 => {
+            let Ok(message) = read_mc_string_buf(&mut response.payload.clone()) else {
+                return WhitelistStatus::Unknown;
+            };
+            let message = serde_json::from_str::<ChatObject>(&message)
+                .map_or(message, |chat| chat.to_plain_text())
+                .to_lowercase();
+
+            if WHITELIST_PHRASES
+                .iter()
+                .any(|phrase| message.contains(phrase))
+            {
+                WhitelistStatus::NotWhitelisted
+            } else {
+                WhitelistStatus::Unknown
+            }
+        }
+        // Login Success or Set Compression, which are only sent after the whitelist check
+        0x02 | 0x03 => WhitelistStatus::Whitelisted,
+        _ => WhitelistStatus::Unknown,
+    }
+}
+
+async fn login_start_raw(host: &str, port: u16, username: &str) -> io::Result<LoginResponse> {
+    let mut socket = TcpStream::connect(host_port(host, port)).await?;
+
+    // https://wiki.vg/Protocol#Handshake
+    let handshake = Packet::builder(PacketId::Handshake)
+        .add_varint(LOGIN_PROTOCOL_VERSION)
+        .add_string(host)
+        .add_u16(port)
+        .add_varint(PacketId::Login)
+        .build();
+
+    socket.write_all(&handshake.bytes()).await?;
+
+    // https://wiki.vg/index.php?title=Protocol&oldid=18375#Login_Start
+    let login_start = Packet::builder(PacketId::LoginStart)
+        .add_string(username)
+        .add_bool(false) // no player UUID
+        .build();
+
+    socket.write_all(&login_start.bytes()).await?;
+
+    // a packet is framed just like a byte array, prefixed with its length
+    let mut payload = Bytes::from(socket.read_mc_bytes().await?);
+    let packet_id = read_varint_buf(&mut payload)?;
+    socket.shutdown().await?;
+
+    Ok(LoginResponse { packet_id, payload })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_whitelist, login_start, LoginResponse, WhitelistStatus};
+    use crate::status::packet::{Packet, PacketId};
+    use bytes::Bytes;
+    use std::time::Duration;
+    use tokio::{
+        io::{self, AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_login_start_disconnect() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let mut buf = [0; 256];
+            let _ = socket.read(&mut buf).await?;
+
+            let disconnect = Packet::builder(PacketId::LoginStart)
+                .add_string(r#"{"text":"You are not white-listed on this server!"}"#)
+                .build();
+            socket.write_all(&disconnect.bytes()).await
+        });
+
+        let response = login_start("127.0.0.1", port, "Notch", Duration::from_secs(1)).await?;
+        server.await??;
+
+        assert_eq!(response.packet_id, 0);
+        assert!(response
+            .payload
+            .ends_with(b"white-listed on this server!\"}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_classify_whitelist() {
+        let disconnect = |message: &str| LoginResponse {
+            packet_id: 0,
+            payload: Packet::builder(PacketId::LoginStart)
+                .add_string(message)
+                .build()
+                .bytes()
+                .slice(2..),
+        };
+
+        assert_eq!(
+            classify_whitelist(&disconnect(
+                r#"{"translate":"multiplayer.disconnect.not_whitelisted"}"#
+            )),
+            WhitelistStatus::NotWhitelisted
+        );
+        assert_eq!(
+            classify_whitelist(&disconnect(
+                r#"{"text":"You are not white-listed on this server!"}"#
+            )),
+            WhitelistStatus::NotWhitelisted
+        );
+        assert_eq!(
+            classify_whitelist(&disconnect(r#""Outdated client! Please use 1.20.4""#)),
+            WhitelistStatus::Unknown
+        );
+
+        let success = LoginResponse {
+            packet_id: 2,
+            payload: Bytes::new(),
+        };
+        assert_eq!(classify_whitelist(&success), WhitelistStatus::Whitelisted);
+
+        let encryption = LoginResponse {
+            packet_id: 1,
+            payload: Bytes::new(),
+        };
+        assert_eq!(classify_whitelist(&encryption), WhitelistStatus::Unknown);
+    }
+}
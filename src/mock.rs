@@ -0,0 +1,216 @@
+//! A minimal server speaking the Server List Ping, Query, and RCON, so tests don't need
+//! a real Minecraft server.
+//!
+//! Each protocol listens on its own ephemeral port on `127.0.0.1`, and serves any number of
+//! clients until the [`MockServer`] is dropped. The responses are canned, but framed like a
+//! vanilla server frames them.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{future::Future, io::Cursor};
+use tokio::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+    task::JoinHandle,
+};
+
+use crate::socket::{ReadWriteMinecraftBytes, ReadWriteMinecraftString, ReadWriteVarInt};
+
+/// The status the mock server responds with.
+pub(crate) const STATUS_JSON: &str = r#"{"version":{"name":"1.20.1","protocol":763},"players":{"max":20,"online":1,"sample":[{"name":"Notch","id":"069a79f4-44e9-4726-a5be-fca90e38aaf5"}]},"description":{"text":"A Minecraft Server"}}"#;
+
+/// The challenge token the mock server hands out for Query requests.
+pub(crate) const CHALLENGE_TOKEN: i32 = 9_513_307;
+
+/// The password the mock server accepts for RCON.
+pub(crate) const RCON_PASSWORD: &str = "mc-query-test";
+
+/// The RCON packet types, as sent on the wire.
+const RCON_RESPONSE: i32 = 0;
+const RCON_COMMAND: i32 = 2;
+const RCON_LOGIN: i32 = 3;
+
+/// A running mock server, which stops when dropped.
+pub(crate) struct MockServer {
+    /// The TCP port of the Server List Ping.
+    pub status_port: u16,
+    /// The UDP port of Query.
+    pub query_port: u16,
+    /// The TCP port of RCON.
+    pub rcon_port: u16,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start listening for all three protocols.
+    pub async fn start() -> io::Result<Self> {
+        let status = TcpListener::bind("127.0.0.1:0").await?;
+        let query = UdpSocket::bind("127.0.0.1:0").await?;
+        let rcon = TcpListener::bind("127.0.0.1:0").await?;
+
+        Ok(Self {
+            status_port: status.local_addr()?.port(),
+            query_port: query.local_addr()?.port(),
+            rcon_port: rcon.local_addr()?.port(),
+            tasks: vec![
+                tokio::spawn(accept(status, serve_status)),
+                tokio::spawn(async move {
+                    let _ = serve_query(query).await;
+                }),
+                tokio::spawn(accept(rcon, serve_rcon)),
+            ],
+        })
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Accept connections, serving each one in its own task.
+async fn accept<F, Fut>(listener: TcpListener, serve: F)
+where
+    F: Fn(TcpStream) -> Fut,
+    Fut: Future<Output = io::Result<()>> + Send + 'static,
+{
+    while let Ok((conn, _)) = listener.accept().await {
+        let fut = serve(conn);
+        tokio::spawn(async move {
+            let _ = fut.await;
+        });
+    }
+}
+
+/// Answer a status request, and then echo a ping if the client sends one.
+async fn serve_status(mut conn: TcpStream) -> io::Result<()> {
+    // the handshake, which has to ask for the status, and the status request after it
+    let mut handshake = Cursor::new(conn.read_mc_bytes().await?);
+    let _id = handshake.read_varint().await?;
+    let _protocol = handshake.read_varint().await?;
+    let _host = handshake.read_mc_string().await?;
+    let _port = handshake.read_u16().await?;
+    if handshake.read_varint().await? != 1 {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    conn.read_mc_bytes().await?;
+
+    // the packet ID, and the JSON as a string, framed with the length of both
+    let mut response = Cursor::new(Vec::new());
+    response.write_varint(0x00).await?;
+    response.write_mc_string(STATUS_JSON).await?;
+    conn.write_mc_bytes(response.get_ref()).await?;
+
+    let mut ping = [0; 10];
+    conn.read_exact(&mut ping).await?;
+    conn.write_all(&ping).await
+}
+
+/// Answer Query handshakes and stat requests.
+async fn serve_query(socket: UdpSocket) -> io::Result<()> {
+    let mut buf = [0; 64];
+    loop {
+        let (len, client) = socket.recv_from(&mut buf).await?;
+        let mut request = &buf[..len];
+        if request.len() < 7 || request.get_u16() != 0xfe_fd {
+            continue;
+        }
+        let packet_type = request.get_u8();
+        let session_id = request.get_i32();
+
+        let mut response = BytesMut::new();
+        response.put_u8(packet_type);
+        response.put_i32(session_id);
+
+        match packet_type {
+            9 => response.put_slice(format!("{CHALLENGE_TOKEN}\0").as_bytes()),
+            0 if request.len() >= 4 && request.get_i32() == CHALLENGE_TOKEN => {
+                // full stat requests are padded with 4 more bytes
+                if request.is_empty() {
+                    put_basic_stat(&mut response);
+                } else {
+                    put_full_stat(&mut response);
+                }
+            }
+            _ => continue,
+        }
+
+        socket.send_to(&response, client).await?;
+    }
+}
+
+fn put_basic_stat(response: &mut BytesMut) {
+    response.put_slice(b"A Minecraft Server\0SMP\0world\x001\x0020\0");
+    response.put_u16_le(25565);
+    response.put_slice(b"127.0.0.1\0");
+}
+
+fn put_full_stat(response: &mut BytesMut) {
+    response.put_slice(b"splitnum\0\x80\0");
+    for (key, value) in [
+        ("hostname", "A Minecraft Server"),
+        ("gametype", "SMP"),
+        ("game_id", "MINECRAFT"),
+        ("version", "1.20.1"),
+        ("plugins", ""),
+        ("map", "world"),
+        ("numplayers", "1"),
+        ("maxplayers", "20"),
+        ("hostport", "25565"),
+        ("hostip", "127.0.0.1"),
+    ] {
+        response.put_slice(key.as_bytes());
+        response.put_u8(0);
+        response.put_slice(value.as_bytes());
+        response.put_u8(0);
+    }
+    response.put_slice(b"\0\x01player_\0\0Notch\0\0");
+}
+
+/// Answer RCON logins and commands, until the client disconnects.
+async fn serve_rcon(mut conn: TcpStream) -> io::Result<()> {
+    let mut authenticated = false;
+    loop {
+        let len = conn.read_i32_le().await?;
+        let mut body = vec![0; usize::try_from(len).unwrap_or_default()];
+        conn.read_exact(&mut body).await?;
+        let mut body = Bytes::from(body);
+        if body.remaining() < 10 {
+            return Ok(());
+        }
+
+        let request_id = body.get_i32_le();
+        let packet_type = body.get_i32_le();
+        let payload = String::from_utf8_lossy(&body[..body.len() - 2]).into_owned();
+
+        let (request_id, packet_type, output) = match packet_type {
+            RCON_LOGIN => {
+                authenticated = payload == RCON_PASSWORD;
+                let id = if authenticated { request_id } else { -1 };
+                (id, RCON_COMMAND, String::new())
+            }
+            RCON_COMMAND if authenticated => (request_id, RCON_RESPONSE, run_command(&payload)),
+            _ => (-1, RCON_RESPONSE, String::new()),
+        };
+
+        let mut response = BytesMut::new();
+        response.put_i32_le(output.len() as i32 + 10);
+        response.put_i32_le(request_id);
+        response.put_i32_le(packet_type);
+        response.put_slice(output.as_bytes());
+        response.put_slice(&[0, 0]);
+        conn.write_all(&response).await?;
+    }
+}
+
+/// The output of a command, like a vanilla server with one player online.
+fn run_command(command: &str) -> String {
+    match command {
+        "time set day" => "Set the time to 1000".to_string(),
+        "list" => "There are 1 of a max of 20 players online: Notch".to_string(),
+        "seed" => "Seed: [-4172144997902289642]".to_string(),
+        _ => format!("Unknown or incomplete command, see below for error{command}<--[HERE]"),
+    }
+}
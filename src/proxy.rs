@@ -0,0 +1,178 @@
+//! Support for the HAProxy [PROXY protocol](https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt).
+//!
+//! Servers that sit behind a TCP proxy can only see the proxy's address for every
+//! connection. The PROXY protocol fixes this by having the proxy send a small header
+//! with the original client's address before any other data. Servers set up to expect
+//! this header reject (or silently drop) connections that don't start with one.
+//!
+//! This is the case for servers behind TCPShield and similar DDoS-protection providers,
+//! and for Velocity, BungeeCord, and Paper servers with their `haproxy-protocol` /
+//! `proxy-protocol` options enabled. To talk to such a server directly (for example
+//! from inside the proxy's network), a [`ProxyHeader`] can be sent with
+//! [`status_with_proxy_header`](crate::status::status_with_proxy_header) or
+//! [`RconClient::with_proxy_header`](crate::rcon::RconClient::with_proxy_header).
+//!
+//! Requires the `proxy-protocol` feature.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::net::{IpAddr, SocketAddr};
+
+/// The signature every version 2 header starts with.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The version of the PROXY protocol header to send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocolVersion {
+    /// The human-readable text format.
+    V1,
+
+    /// The binary format.
+    V2,
+}
+
+/// A PROXY protocol header, sent before any other data on a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// Which version of the protocol to encode the header with.
+    pub version: ProxyProtocolVersion,
+
+    /// The address of the (original) client the connection is made on behalf of.
+    pub source: SocketAddr,
+
+    /// The address the client (originally) connected to.
+    pub destination: SocketAddr,
+}
+
+impl ProxyHeader {
+    /// Construct a new [`ProxyHeader`].
+    ///
+    /// # Arguments
+    /// * `version` - the version of the protocol to encode the header with.
+    /// * `source` - the address of the client the connection is made on behalf of.
+    /// * `destination` - the address the client connected to.
+    #[must_use]
+    pub fn new(version: ProxyProtocolVersion, source: SocketAddr, destination: SocketAddr) -> Self {
+        Self {
+            version,
+            source,
+            destination,
+        }
+    }
+
+    /// Encode the header into the bytes to send at the start of a connection.
+    ///
+    /// If only one of the addresses is IPv6, the other is sent as an IPv4-mapped
+    /// IPv6 address, as both addresses must belong to the same family.
+    #[must_use]
+    pub fn bytes(&self) -> Bytes {
+        let (source, destination) = match (self.source.ip(), self.destination.ip()) {
+            (IpAddr::V4(_), IpAddr::V6(_)) => (to_ipv6(self.source), self.destination),
+            (IpAddr::V6(_), IpAddr::V4(_)) => (self.source, to_ipv6(self.destination)),
+            _ => (self.source, self.destination),
+        };
+
+        match self.version {
+            ProxyProtocolVersion::V1 => encode_v1(source, destination),
+            ProxyProtocolVersion::V2 => encode_v2(source, destination),
+        }
+    }
+}
+
+fn to_ipv6(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) => SocketAddr::new(IpAddr::V6(ip.to_ipv6_mapped()), addr.port()),
+        IpAddr::V6(_) => addr,
+    }
+}
+
+fn encode_v1(source: SocketAddr, destination: SocketAddr) -> Bytes {
+    let family = if source.is_ipv4() { "TCP4" } else { "TCP6" };
+
+    Bytes::from(format!(
+        "PROXY {family} {} {} {} {}\r\n",
+        source.ip(),
+        destination.ip(),
+        source.port(),
+        destination.port()
+    ))
+}
+
+fn encode_v2(source: SocketAddr, destination: SocketAddr) -> Bytes {
+    let mut bytes = BytesMut::with_capacity(52);
+    bytes.put_slice(&V2_SIGNATURE);
+    bytes.put_u8(0x21); // version 2, PROXY command
+
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            bytes.put_u8(0x11); // TCP over IPv4
+            bytes.put_u16(12);
+            bytes.put_slice(&src.octets());
+            bytes.put_slice(&dst.octets());
+        }
+        (src, dst) => {
+            bytes.put_u8(0x21); // TCP over IPv6
+            bytes.put_u16(36);
+            bytes.put_slice(&to_ipv6_octets(src));
+            bytes.put_slice(&to_ipv6_octets(dst));
+        }
+    }
+
+    bytes.put_u16(source.port());
+    bytes.put_u16(destination.port());
+
+    bytes.freeze()
+}
+
+fn to_ipv6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProxyHeader, ProxyProtocolVersion};
+
+    #[test]
+    fn test_v1_header() {
+        let header = ProxyHeader::new(
+            ProxyProtocolVersion::V1,
+            "192.168.0.1:56324".parse().unwrap(),
+            "192.168.0.11:25565".parse().unwrap(),
+        );
+
+        assert_eq!(
+            header.bytes(),
+            b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 25565\r\n".as_slice()
+        );
+    }
+
+    #[test]
+    fn test_v2_header() {
+        let header = ProxyHeader::new(
+            ProxyProtocolVersion::V2,
+            "127.0.0.1:1234".parse().unwrap(),
+            "127.0.0.2:25565".parse().unwrap(),
+        );
+
+        assert_eq!(
+            header.bytes(),
+            b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c\x7f\x00\x00\x01\x7f\x00\x00\x02\x04\xd2\x63\xdd"
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn test_v2_mixed_families() {
+        let header = ProxyHeader::new(
+            ProxyProtocolVersion::V2,
+            "127.0.0.1:1234".parse().unwrap(),
+            "[::1]:25565".parse().unwrap(),
+        );
+        let bytes = header.bytes();
+
+        assert_eq!(bytes[13], 0x21);
+        assert_eq!(bytes.len(), 16 + 36);
+    }
+}
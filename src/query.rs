@@ -1,21 +1,23 @@
 //! Implementation of the [Query](https://wiki.vg/Query) protocol.
 
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use rand::random;
-use std::collections::HashMap;
-use std::time::Duration;
-use tokio::io;
-use tokio::net::UdpSocket;
-use tokio::time::timeout;
+mod client;
+mod packet;
 
-use crate::errors::QueryProtocolError;
+use crate::errors::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, net::SocketAddr};
+use tokio::net::ToSocketAddrs;
+
+pub use crate::latency::{LatencyStats, SAMPLE_INTERVAL};
+pub use client::{QueryClient, QueryConfig};
+pub use packet::{QueryPacket, QueryPacketBuilder, QueryPacketType};
 
 const QUERY_MAGIC: u16 = 0xfe_fd;
 const SESSION_ID_MASK: u32 = 0x0f_0f_0f_0f;
 
 /// A response from the server's basic query.
 /// Taken from [wiki.vg](https://wiki.vg/Query#Response_2)
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BasicStatResponse {
     /// The "motd" - message shown in the server list by the client.
     pub motd: String,
@@ -42,7 +44,7 @@ pub struct BasicStatResponse {
 
 /// A response from the server's full query.
 /// Taken from [wiki.vg](https://wiki.vg/Query#Response_3)
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FullStatResponse {
     /// The "motd" - message shown in the server list by the client.
     pub motd: String,
@@ -80,17 +82,83 @@ pub struct FullStatResponse {
 
     /// The current list of online players.
     pub players: Vec<String>,
+
+    /// Every other key in the response, along with its value.
+    /// Vanilla servers don't send any, but some server platforms and plugins add their own.
+    #[serde(default)]
+    pub extra: HashMap<String, String>,
+}
+
+/// A plugin listed in the `plugins` field of a [`FullStatResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Plugin {
+    /// The name of the plugin.
+    pub name: String,
+
+    /// The version of the plugin, if the server listed one.
+    pub version: Option<String>,
 }
 
-async fn stat_send(sock: &UdpSocket, bytes: &[u8]) -> io::Result<Bytes> {
-    sock.send(bytes).await?;
-    Box::pin(timeout(Duration::from_millis(250), recv_packet(sock))).await?
+impl FullStatResponse {
+    /// Parse the [`plugins`](Self::plugins) field into the server's brand and the plugins
+    /// it lists, in the format Bukkit-based servers use: `Paper on 1.19.2: WorldEdit 7.2; EssentialsX 2.19`.
+    ///
+    /// Vanilla servers leave the field empty, which gives no brand and no plugins.
+    /// The version of a plugin is everything after the last space in its entry, so entries
+    /// without any spaces are taken to be a name without a version.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use mc_query::query;
+    /// use tokio::io::Result;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let res = query::stat_full("localhost", 25565).await?;
+    ///     let (brand, plugins) = res.parse_plugins();
+    ///
+    ///     println!("{} plugins on {}", plugins.len(), brand.as_deref().unwrap_or("vanilla"));
+    ///     for plugin in plugins {
+    ///         println!("{} {}", plugin.name, plugin.version.unwrap_or_default());
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn parse_plugins(&self) -> (Option<String>, Vec<Plugin>) {
+        let (brand, list) = self.plugins.split_once(':').unwrap_or((&self.plugins, ""));
+
+        let brand = Some(brand.trim())
+            .filter(|brand| !brand.is_empty())
+            .map(ToString::to_string);
+
+        let plugins = list
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.rsplit_once(' ') {
+                Some((name, version)) => Plugin {
+                    name: name.trim_end().to_string(),
+                    version: Some(version.to_string()),
+                },
+                None => Plugin {
+                    name: entry.to_string(),
+                    version: None,
+                },
+            })
+            .collect();
+
+        (brand, plugins)
+    }
 }
 
 /// Perform a basic stat query of the server per the [Query Protocol](https://wiki.vg/Query#Basic_Stat).
 /// Note that the server must have `query-enabled=true` set in its properties to get a response.
 /// The `query.port` property might also be different from `server.port`.
 ///
+/// This creates a [`QueryClient`] with the default [`QueryConfig`] for a single request.
+///
 /// # Arguments
 /// * `host` - the hostname/IP of thr server to query
 /// * `port` - the port that the server's Query is running on
@@ -100,7 +168,7 @@ async fn stat_send(sock: &UdpSocket, bytes: &[u8]) -> io::Result<Bytes> {
 /// invalid data was recieved.
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use mc_query::query;
 /// use tokio::io::Result;
 ///
@@ -112,54 +180,16 @@ async fn stat_send(sock: &UdpSocket, bytes: &[u8]) -> io::Result<Bytes> {
 ///     Ok(())
 /// }
 /// ```
-pub async fn stat_basic(host: &str, port: u16) -> io::Result<BasicStatResponse> {
-    let socket = UdpSocket::bind("0.0.0.0:0").await?;
-    socket.connect(format!("{host}:{port}")).await?;
-
-    let (token, session) = Box::pin(handshake(&socket)).await?;
-
-    let mut bytes = BytesMut::new();
-    bytes.put_u16(QUERY_MAGIC);
-    bytes.put_u8(0); // packet type 0 - stat
-    bytes.put_i32(session);
-    bytes.put_i32(token);
-
-    let mut res = match stat_send(&socket, &bytes).await {
-        Ok(v) => v,
-        Err(_) => stat_send(&socket, &bytes).await?,
-    };
-
-    validate_packet(&mut res, 0, session)?;
-
-    let motd = get_string(&mut res)?;
-    let game_type = get_string(&mut res)?;
-    let map = get_string(&mut res)?;
-    let num_players = get_string(&mut res)?
-        .parse()
-        .map_err::<io::Error, _>(|_| QueryProtocolError::CannotParseInt.into())?;
-    let max_players = get_string(&mut res)?
-        .parse()
-        .map_err::<io::Error, _>(|_| QueryProtocolError::CannotParseInt.into())?;
-
-    let host_port = res.get_u16_le(); // shorts are little endian per protocol
-
-    let host_ip = get_string(&mut res)?;
-
-    Ok(BasicStatResponse {
-        motd,
-        game_type,
-        map,
-        num_players,
-        max_players,
-        host_port,
-        host_ip,
-    })
+pub async fn stat_basic(host: &str, port: u16) -> Result<BasicStatResponse> {
+    QueryClient::new(host, port).await?.stat_basic().await
 }
 
 /// Perform a full stat query of the server per the [Query Protocol](https://wiki.vg/Query#Full_stat).
 /// Note that the server must have `query-enabled=true` set in its properties to get a response.
 /// The `query.port` property might also be different from `server.port`.
 ///
+/// This creates a [`QueryClient`] with the default [`QueryConfig`] for a single request.
+///
 /// # Arguments
 /// * `host` - the hostname/IP of thr server to query
 /// * `port` - the port that the server's Query is running on
@@ -169,7 +199,7 @@ pub async fn stat_basic(host: &str, port: u16) -> io::Result<BasicStatResponse>
 /// if invalid data was recieved.
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use mc_query::query;
 /// use tokio::io::Result;
 ///
@@ -181,194 +211,671 @@ pub async fn stat_basic(host: &str, port: u16) -> io::Result<BasicStatResponse>
 ///     Ok(())
 /// }
 /// ```
-pub async fn stat_full(host: &str, port: u16) -> io::Result<FullStatResponse> {
-    let socket = UdpSocket::bind("0.0.0.0:0").await?;
-    socket.connect(format!("{host}:{port}")).await?;
-
-    let (token, session) = Box::pin(handshake(&socket)).await?;
-
-    let mut bytes = BytesMut::new();
-    bytes.put_u16(QUERY_MAGIC);
-    bytes.put_u8(0); // packet type 0 - stat
-    bytes.put_i32(session);
-    bytes.put_i32(token);
-    bytes.put_u32(0); // 4 extra bytes required for full stat vs. basic
-
-    let mut res = match stat_send(&socket, &bytes).await {
-        Ok(v) => v,
-        Err(_) => stat_send(&socket, &bytes).await?,
+pub async fn stat_full(host: &str, port: u16) -> Result<FullStatResponse> {
+    QueryClient::new(host, port).await?.stat_full().await
+}
+
+/// Perform a basic stat query of the server like [`stat_basic`], at an address that was already
+/// resolved, or anything else that can be turned into one, such as a [`SocketAddr`] or an
+/// `(IpAddr, u16)` tuple.
+///
+/// # Arguments
+/// * `addr` - the address of the server's Query port
+///
+/// # Errors
+/// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or if
+/// invalid data was recieved.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::query;
+/// use std::net::Ipv4Addr;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let res = query::stat_basic_addr((Ipv4Addr::LOCALHOST, 25565)).await?;
+///     println!("{res:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn stat_basic_addr(addr: impl ToSocketAddrs) -> Result<BasicStatResponse> {
+    QueryClient::connect(addr, QueryConfig::default())
+        .await?
+        .stat_basic()
+        .await
+}
+
+/// Perform a full stat query of the server like [`stat_full`], at an address that was already
+/// resolved, see [`stat_basic_addr`].
+///
+/// # Arguments
+/// * `addr` - the address of the server's Query port
+///
+/// # Errors
+/// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or if
+/// invalid data was recieved.
+pub async fn stat_full_addr(addr: impl ToSocketAddrs) -> Result<FullStatResponse> {
+    QueryClient::connect(addr, QueryConfig::default())
+        .await?
+        .stat_full()
+        .await
+}
+
+/// Perform a basic stat query of the server like [`stat_basic`], sending it from the
+/// given local address.
+///
+/// This is a shorthand for setting [`QueryConfig::bind_addr`]. Binding to a specific address
+/// is useful on multi-homed hosts, or when firewall rules require a specific source interface
+/// or port. Both IPv4 and IPv6 addresses work, as long as the server is reachable over the same
+/// protocol.
+///
+/// # Arguments
+/// * `bind_addr` - the local address to send the query from, with port 0 to let the OS pick one
+/// * `host` - the hostname/IP of the server to query
+/// * `port` - the port that the server's Query is running on
+///
+/// # Errors
+/// Will return `Err` if the address could not be bound, if there was a network error, if the
+/// challenge token wasn't obtainable, or if invalid data was recieved.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::query;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let bind_addr = "192.0.2.10:0".parse().unwrap();
+///     let res = query::stat_basic_from(bind_addr, "localhost", 25565).await?;
+///     println!("{res:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn stat_basic_from(
+    bind_addr: SocketAddr,
+    host: &str,
+    port: u16,
+) -> Result<BasicStatResponse> {
+    let config = QueryConfig {
+        bind_addr,
+        ..QueryConfig::default()
     };
 
-    validate_packet(&mut res, 0, session)?;
+    QueryClient::with_config(host, port, config)
+        .await?
+        .stat_basic()
+        .await
+}
 
-    // skip 11 meaningless padding bytes
-    res.advance(11);
+/// Perform a full stat query of the server like [`stat_full`], sending it from the
+/// given local address.
+///
+/// This is a shorthand for setting [`QueryConfig::bind_addr`], see [`stat_basic_from`].
+///
+/// # Arguments
+/// * `bind_addr` - the local address to send the query from, with port 0 to let the OS pick one
+/// * `host` - the hostname/IP of the server to query
+/// * `port` - the port that the server's Query is running on
+///
+/// # Errors
+/// Will return `Err` if the address could not be bound, if there was a network error, if the
+/// challenge token wasn't obtainable, or if invalid data was recieved.
+pub async fn stat_full_from(
+    bind_addr: SocketAddr,
+    host: &str,
+    port: u16,
+) -> Result<FullStatResponse> {
+    let config = QueryConfig {
+        bind_addr,
+        ..QueryConfig::default()
+    };
+
+    QueryClient::with_config(host, port, config)
+        .await?
+        .stat_full()
+        .await
+}
 
-    // K,V section
-    let mut kv = HashMap::new();
-    loop {
-        let key = get_string(&mut res)?;
-        if key.is_empty() {
-            break;
+/// Perform both a basic and a full stat query of the server, sharing a single handshake.
+/// Note that the server must have `query-enabled=true` set in its properties to get a response.
+///
+/// This creates a [`QueryClient`] with the default [`QueryConfig`], and calls
+/// [`QueryClient::stat_all`] on it. It takes one round trip less than calling
+/// [`stat_basic`] and [`stat_full`] separately.
+///
+/// # Arguments
+/// * `host` - the hostname/IP of the server to query
+/// * `port` - the port that the server's Query is running on
+///
+/// # Errors
+/// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or
+/// if invalid data was recieved.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::query;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let (basic, full) = query::stat_all("localhost", 25565).await?;
+///     println!("{} is running {}", basic.motd, full.version);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn stat_all(host: &str, port: u16) -> Result<(BasicStatResponse, FullStatResponse)> {
+    QueryClient::new(host, port).await?.stat_all().await
+}
+
+create_timeout!(stat_basic, BasicStatResponse);
+create_timeout!(stat_full, FullStatResponse);
+create_timeout!(stat_all, (BasicStatResponse, FullStatResponse));
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::{Error, QueryProtocolError};
+    use crate::mock::MockServer;
+    use bytes::{BufMut, BytesMut};
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+    use tokio::{io, net::UdpSocket, time::sleep};
+
+    use super::{
+        stat_all, stat_basic, stat_basic_from, stat_basic_with_timeout, stat_full, stat_full_addr,
+        stat_full_with_timeout, BasicStatResponse, FullStatResponse, Plugin, QueryClient,
+        QueryConfig, QueryPacket, QueryPacketType,
+    };
+
+    #[tokio::test]
+    async fn test_config_retries() -> io::Result<()> {
+        // a server that never responds
+        let silent = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = silent.local_addr()?.port();
+
+        let config = QueryConfig {
+            handshake_timeout: Duration::from_millis(20),
+            retries: 2,
+            ..QueryConfig::default()
+        };
+        let client = QueryClient::with_config("127.0.0.1", port, config).await?;
+
+        let err = client.stat_basic().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Query(QueryProtocolError::QueryDisabled)
+        ));
+        assert!(err.is_timeout());
+
+        // the initial attempt plus two retries
+        let mut buf = [0u8; 64];
+        for _ in 0..3 {
+            silent.try_recv(&mut buf)?;
         }
-        let value = get_string(&mut res)?;
-        kv.insert(key, value);
+        assert!(silent.try_recv(&mut buf).is_err());
+
+        Ok(())
     }
 
-    // excuse this horrendous code, I don't know of a better way
-    let motd = kv
-        .remove("hostname")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let game_type = kv
-        .remove("gametype")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let game_id = kv
-        .remove("game_id")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let version = kv
-        .remove("version")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let plugins = kv
-        .remove("plugins")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let map = kv
-        .remove("map")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-    let num_players = kv
-        .remove("numplayers")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?
-        .parse()
-        .map_err(|_| QueryProtocolError::CannotParseInt)?;
-    let max_players = kv
-        .remove("maxplayers")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?
-        .parse()
-        .map_err(|_| QueryProtocolError::CannotParseInt)?;
-    let host_port = kv
-        .remove("hostport")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?
-        .parse()
-        .map_err(|_| QueryProtocolError::CannotParseInt)?;
-    let host_ip = kv
-        .remove("hostip")
-        .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
-
-    // skip 10 meaningless padding bytes
-    for _ in 0..10 {
-        res.get_u8();
+    #[tokio::test]
+    async fn test_query_disabled() -> io::Result<()> {
+        let config = QueryConfig {
+            handshake_timeout: Duration::from_millis(20),
+            ..QueryConfig::default()
+        };
+
+        // a port nothing listens on
+        let closed = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = closed.local_addr()?.port();
+        drop(closed);
+
+        // the host refuses, which is reported as is rather than as a timeout
+        let client = QueryClient::with_config("127.0.0.1", port, config.clone()).await?;
+        let err = client.stat_full().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+
+        // a server that answers, but not with a handshake response
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (_, client) = server.recv_from(&mut buf).await?;
+            server.send_to(b"\x00garbage", client).await
+        });
+
+        let client = QueryClient::with_config("127.0.0.1", port, config).await?;
+        let err = client.stat_full().await.unwrap_err();
+        assert!(matches!(err, Error::Query(_)));
+        assert!(!matches!(
+            err,
+            Error::Query(QueryProtocolError::QueryDisabled)
+        ));
+
+        Ok(())
     }
 
-    // players section
-    let mut players = vec![];
-    loop {
-        let username = get_string(&mut res)?;
-        if username.is_empty() {
-            break;
-        }
-        players.push(username);
+    #[tokio::test]
+    async fn test_query_with_timeout() -> io::Result<()> {
+        // a server that never responds, so the default retries would take over a second
+        let silent = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = silent.local_addr()?.port();
+
+        let start = Instant::now();
+        let basic = stat_basic_with_timeout("127.0.0.1", port, Duration::from_millis(50)).await;
+        let full = stat_full_with_timeout("127.0.0.1", port, Duration::from_millis(50)).await;
+
+        assert_eq!(basic.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(full.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        Ok(())
     }
 
-    Ok(FullStatResponse {
-        motd,
-        game_type,
-        game_id,
-        version,
-        plugins,
-        map,
-        num_players,
-        max_players,
-        host_port,
-        host_ip,
-        players,
-    })
-}
+    #[tokio::test]
+    async fn test_config_phase_timeouts() -> io::Result<()> {
+        // a server that answers the handshake right away, but is slow to answer the stat request
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (_, client) = server.recv_from(&mut buf).await?;
+            let session = [buf[3], buf[4], buf[5], buf[6]];
+
+            let mut res = BytesMut::new();
+            res.put_u8(9);
+            res.put_slice(&session);
+            res.put_slice(b"12345\0");
+            server.send_to(&res, client).await?;
+
+            server.recv_from(&mut buf).await?;
+            sleep(Duration::from_millis(100)).await;
+
+            let mut res = BytesMut::new();
+            res.put_u8(0);
+            res.put_slice(&session);
+            res.put_slice(b"A Minecraft Server\0SMP\0world\x003\x0020\0");
+            res.put_u16_le(25565);
+            res.put_slice(b"127.0.0.1\0");
+            server.send_to(&res, client).await?;
+
+            Ok::<_, io::Error>(())
+        });
+
+        let config = QueryConfig {
+            handshake_timeout: Duration::from_millis(50),
+            stat_timeout: Duration::from_secs(2),
+            retries: 0,
+            ..QueryConfig::default()
+        };
+        let client = QueryClient::with_config("127.0.0.1", port, config).await?;
+
+        let res = client.stat_basic().await?;
+        assert_eq!(res.num_players, 3);
+        assert_eq!(res.max_players, 20);
+        // nothing past the end of the datagram is read
+        assert_eq!(res.host_ip, "127.0.0.1");
 
-create_timeout!(stat_basic, BasicStatResponse);
-create_timeout!(stat_full, FullStatResponse);
+        Ok(())
+    }
 
-/// Perform a handshake request per <https://wiki.vg/Query#Handshake>
-///
-/// # Returns
-/// A tuple `(challenge_token, session_id)` to be used in subsequent server interactions
-///
-/// # Errors
-/// Returns `Err` if there was a network error, or if the returned token was not valid.
-async fn handshake(socket: &UdpSocket) -> io::Result<(i32, i32)> {
-    // generate new token per interaction to avoid reset problems
-    #[allow(clippy::cast_possible_wrap)] // this is fine, we don't care about the value
-    let session_id = (random::<u32>() & SESSION_ID_MASK) as i32;
+    /// Answer a handshake, and then a stat request with `payload` (everything after the
+    /// type and session ID of the response).
+    async fn serve_stat(server: UdpSocket, payload: &'static [u8]) -> io::Result<()> {
+        let mut buf = [0u8; 64];
+        let (_, client) = server.recv_from(&mut buf).await?;
+        let session = [buf[3], buf[4], buf[5], buf[6]];
 
-    let mut req = BytesMut::with_capacity(7);
-    req.put_u16(QUERY_MAGIC);
-    req.put_u8(9); // packet type 9 - handshake
-    req.put_i32(session_id);
-    // no payload for handshake requests
+        let mut res = BytesMut::new();
+        res.put_u8(9);
+        res.put_slice(&session);
+        res.put_slice(b"12345\0");
+        server.send_to(&res, client).await?;
 
-    socket.send(&req).await?;
+        server.recv_from(&mut buf).await?;
 
-    let mut response = Box::pin(recv_packet(socket)).await?;
-    validate_packet(&mut response, 9, session_id)?;
+        let mut res = BytesMut::new();
+        res.put_u8(0);
+        res.put_slice(&session);
+        res.put_slice(payload);
+        server.send_to(&res, client).await?;
 
-    let token_str = get_string(&mut response)?;
+        Ok(())
+    }
 
-    token_str
-        .parse()
-        .map(|t| (t, session_id))
-        .map_err(|_| QueryProtocolError::CannotParseInt.into())
-}
+    #[tokio::test]
+    async fn test_missing_player_terminator() -> io::Result<()> {
+        const FULL_STAT: &[u8] = b"splitnum\x00\x80\x00\
+            hostname\x00A Minecraft Server\x00gametype\x00SMP\x00game_id\x00MINECRAFT\x00\
+            version\x001.20.1\x00plugins\x00\x00map\x00world\x00numplayers\x002\x00maxplayers\x0020\x00\
+            hostport\x0025565\x00hostip\x00127.0.0.1\x00\x00\
+            \x01player_\x00\x00\
+            Notch\x00jeb_\x00";
+
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+        tokio::spawn(serve_stat(server, FULL_STAT));
+
+        let client = QueryClient::new("127.0.0.1", port).await?;
+        let res = client.stat_full().await?;
+        assert_eq!(res.num_players, 2);
+        assert_eq!(res.players, ["Notch", "jeb_"]);
 
-async fn recv_packet(socket: &UdpSocket) -> io::Result<Bytes> {
-    let mut buf = [0u8; 65536];
-    socket.recv(&mut buf).await?;
+        Ok(())
+    }
 
-    Ok(Bytes::copy_from_slice(&buf))
-}
+    #[tokio::test]
+    async fn test_truncated_response() -> io::Result<()> {
+        let payloads: [&'static [u8]; 3] = [
+            b"A Minecraft Server\0SMP\0wor",
+            b"A Minecraft Server\0SMP\0world\x003\x0020\0\xdd",
+            b"splitnum\x00",
+        ];
+
+        for (i, payload) in payloads.into_iter().enumerate() {
+            let server = UdpSocket::bind("127.0.0.1:0").await?;
+            let port = server.local_addr()?.port();
+            tokio::spawn(serve_stat(server, payload));
+
+            let client = QueryClient::new("127.0.0.1", port).await?;
+            let err = if i < 2 {
+                client.stat_basic().await.unwrap_err()
+            } else {
+                client.stat_full().await.unwrap_err()
+            };
+            assert!(matches!(
+                err,
+                Error::Query(QueryProtocolError::TruncatedPacket)
+            ));
+        }
 
-fn validate_packet(packet: &mut Bytes, expected_type: u8, expected_session: i32) -> io::Result<()> {
-    let recv_type = packet.get_u8();
-    if recv_type != expected_type {
-        return Err(QueryProtocolError::InvalidPacketType.into());
+        Ok(())
     }
 
-    let recv_session = packet.get_i32();
-    if recv_session != expected_session {
-        return Err(QueryProtocolError::SessionIdMismatch.into());
+    #[tokio::test]
+    async fn test_full_stat_extra_keys() -> io::Result<()> {
+        const FULL_STAT: &[u8] = b"splitnum\x00\x80\x00\
+            hostname\x00A Minecraft Server\x00gametype\x00SMP\x00game_id\x00MINECRAFT\x00\
+            version\x001.20.1\x00plugins\x00\x00map\x00world\x00numplayers\x000\x00maxplayers\x0020\x00\
+            hostport\x0025565\x00hostip\x00127.0.0.1\x00whitelist\x00on\x00\x00\
+            \x01player_\x00\x00\x00";
+
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+        tokio::spawn(serve_stat(server, FULL_STAT));
+
+        let client = QueryClient::new("127.0.0.1", port).await?;
+        let res = client.stat_full().await?;
+        assert_eq!(res.map, "world");
+        assert_eq!(res.extra.len(), 1);
+        assert_eq!(res.extra["whitelist"], "on");
+
+        Ok(())
     }
 
-    Ok(())
-}
+    #[tokio::test]
+    async fn test_shared_handshake() -> io::Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        let handshakes = tokio::spawn(async move {
+            let mut handshakes = 0;
+            let mut buf = [0u8; 64];
+            for _ in 0..3 {
+                let (len, client) = server.recv_from(&mut buf).await?;
+                let session = [buf[3], buf[4], buf[5], buf[6]];
+
+                let mut res = BytesMut::new();
+                if buf[2] == 9 {
+                    handshakes += 1;
+                    res.put_u8(9);
+                    res.put_slice(&session);
+                    res.put_slice(b"12345\0");
+                } else if len == 11 {
+                    res.put_u8(0);
+                    res.put_slice(&session);
+                    res.put_slice(b"A Minecraft Server\0SMP\0world\x000\x0020\0");
+                    res.put_u16_le(25565);
+                    res.put_slice(b"127.0.0.1\0");
+                } else {
+                    res.put_u8(0);
+                    res.put_slice(&session);
+                    res.put_slice(
+                        b"splitnum\x00\x80\x00\
+                        hostname\x00A Minecraft Server\x00gametype\x00SMP\x00game_id\x00MINECRAFT\x00\
+                        version\x001.20.1\x00plugins\x00\x00map\x00world\x00numplayers\x000\x00\
+                        maxplayers\x0020\x00hostport\x0025565\x00hostip\x00127.0.0.1\x00\x00\
+                        \x01player_\x00\x00\x00",
+                    );
+                }
+                server.send_to(&res, client).await?;
+            }
+
+            Ok::<_, io::Error>(handshakes)
+        });
+
+        let (basic, full) = stat_all("127.0.0.1", port).await?;
+        assert_eq!(basic.map, "world");
+        assert_eq!(full.version, "1.20.1");
+        assert_eq!(handshakes.await??, 1);
 
-fn get_string(bytes: &mut Bytes) -> io::Result<String> {
-    let mut buf = vec![];
-    loop {
-        let byte = bytes.get_u8();
-        if byte == 0 {
-            break;
-        }
-        buf.push(byte);
+        Ok(())
     }
 
-    String::from_utf8(buf).map_err(|_| QueryProtocolError::InvalidUtf8.into())
-}
+    #[tokio::test]
+    async fn test_bind_addr() -> io::Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        // reserve a port for the client, and make sure the query is sent from it
+        let bind_addr = UdpSocket::bind("127.0.0.1:0").await?.local_addr()?;
+        let source = tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (_, client) = server.recv_from(&mut buf).await?;
+            Ok::<_, io::Error>(client)
+        });
+
+        let res = stat_basic_from(bind_addr, "127.0.0.1", port).await;
+        assert!(res.is_err());
+        assert_eq!(source.await??, bind_addr);
 
-#[cfg(test)]
-mod tests {
-    use tokio::io;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_addr() -> io::Result<()> {
+        const FULL_STAT: &[u8] = b"splitnum\x00\x80\x00\
+            hostname\x00A Minecraft Server\x00gametype\x00SMP\x00game_id\x00MINECRAFT\x00\
+            version\x001.20.1\x00plugins\x00\x00map\x00world\x00numplayers\x000\x00maxplayers\x0020\x00\
+            hostport\x0025565\x00hostip\x00127.0.0.1\x00\x00\
+            \x01player_\x00\x00\x00";
+
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(serve_stat(server, FULL_STAT));
+
+        let res = stat_full_addr(addr).await?;
+        assert_eq!(res.version, "1.20.1");
 
-    use super::{stat_basic, stat_full};
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_ipv6() -> io::Result<()> {
+        let server = UdpSocket::bind("[::1]:0").await?;
+        let port = server.local_addr()?.port();
+        tokio::spawn(serve_stat(
+            server,
+            b"A Minecraft Server\0SMP\0world\x000\x0020\0\xdd\x63::1\0",
+        ));
+
+        // the default bind address is IPv4, so this only works if it is switched to IPv6
+        let res = stat_basic("::1", port).await?;
+        assert_eq!(res.host_ip, "::1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_plugins() {
+        let mut res = FullStatResponse {
+            motd: String::new(),
+            game_type: "SMP".to_string(),
+            game_id: "MINECRAFT".to_string(),
+            version: "1.19.2".to_string(),
+            plugins: "Paper on 1.19.2: WorldEdit 7.2; EssentialsX 2.19;Vault".to_string(),
+            map: "world".to_string(),
+            num_players: 0,
+            max_players: 20,
+            host_port: 25565,
+            host_ip: "127.0.0.1".to_string(),
+            players: Vec::new(),
+            extra: HashMap::new(),
+        };
+
+        let (brand, plugins) = res.parse_plugins();
+        assert_eq!(brand.as_deref(), Some("Paper on 1.19.2"));
+        assert_eq!(
+            plugins,
+            [
+                Plugin {
+                    name: "WorldEdit".to_string(),
+                    version: Some("7.2".to_string()),
+                },
+                Plugin {
+                    name: "EssentialsX".to_string(),
+                    version: Some("2.19".to_string()),
+                },
+                Plugin {
+                    name: "Vault".to_string(),
+                    version: None,
+                },
+            ]
+        );
+
+        res.plugins = "CraftBukkit on Bukkit 1.19.2".to_string();
+        assert_eq!(res.parse_plugins(), (Some(res.plugins.clone()), Vec::new()));
+
+        res.plugins = String::new();
+        assert_eq!(res.parse_plugins(), (None, Vec::new()));
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let res = BasicStatResponse {
+            motd: "A Minecraft Server".to_string(),
+            game_type: "SMP".to_string(),
+            map: "world".to_string(),
+            num_players: 3,
+            max_players: 20,
+            host_port: 25565,
+            host_ip: "127.0.0.1".to_string(),
+        };
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BasicStatResponse>(&json).unwrap(),
+            res
+        );
+
+        // responses cached before `extra` was added still deserialize
+        let full: FullStatResponse = serde_json::from_value(serde_json::json!({
+            "motd": "A Minecraft Server",
+            "game_type": "SMP",
+            "game_id": "MINECRAFT",
+            "version": "1.20.1",
+            "plugins": "",
+            "map": "world",
+            "num_players": 1,
+            "max_players": 20,
+            "host_port": 25565,
+            "host_ip": "127.0.0.1",
+            "players": ["Notch"],
+        }))
+        .unwrap();
+        assert!(full.extra.is_empty());
+        assert_eq!(
+            serde_json::from_str::<FullStatResponse>(&serde_json::to_string(&full).unwrap())
+                .unwrap(),
+            full
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_latency() -> io::Result<()> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            while let Ok((_, client)) = server.recv_from(&mut buf).await {
+                let mut res = BytesMut::new();
+                res.put_u8(9);
+                res.put_slice(&buf[3..7]);
+                res.put_slice(b"12345\0");
+                server.send_to(&res, client).await.unwrap();
+            }
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        socket.connect(("127.0.0.1", port)).await?;
+        let client = QueryClient::from_socket(socket, QueryConfig::default());
+
+        let stats = client.handshake_latency(3).await?;
+        assert_eq!(stats.samples, 3);
+        assert!(stats.min <= stats.mean && stats.mean <= stats.max);
+
+        assert!(client.handshake_latency(0).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_packet() -> io::Result<()> {
+        // a server that answers every packet with its type, session id and payload
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let port = server.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            while let Ok((len, client)) = server.recv_from(&mut buf).await {
+                server.send_to(&buf[2..len], client).await.unwrap();
+            }
+        });
+
+        let client = QueryClient::new("127.0.0.1", port).await?;
+        let packet = QueryPacket::builder(QueryPacketType::Other(0x42), 0x0102_0304)
+            .add_i32(-1)
+            .add_bytes(b"hi")
+            .build();
+
+        let res = client.send_packet(&packet).await?;
+        assert_eq!(&res[..], b"\x42\x01\x02\x03\x04\xff\xff\xff\xffhi");
+
+        Ok(())
+    }
 
     #[tokio::test]
     async fn test_stat_basic() -> io::Result<()> {
-        let response = stat_basic("localhost", 25565).await?;
-        println!("{response:#?}");
+        let server = MockServer::start().await?;
+        let response = stat_basic("127.0.0.1", server.query_port).await?;
+        assert_eq!(response.motd, "A Minecraft Server");
+        assert_eq!(response.num_players, 1);
+        assert_eq!(response.max_players, 20);
 
         Ok(())
     }
 
     #[tokio::test]
     async fn test_stat_full() -> io::Result<()> {
-        let response = stat_full("localhost", 25565).await?;
-        println!("{response:#?}");
+        let server = MockServer::start().await?;
+        let response = stat_full("127.0.0.1", server.query_port).await?;
+        assert_eq!(response.version, "1.20.1");
+        assert_eq!(response.players, ["Notch"]);
 
         Ok(())
     }
@@ -0,0 +1,632 @@
+//! A reusable client for the [Query](https://wiki.vg/Query) protocol.
+
+use bytes::{Buf, Bytes};
+use rand::random;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io;
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout};
+
+use super::{
+    packet::{QueryPacket, QueryPacketType},
+    BasicStatResponse, FullStatResponse, SESSION_ID_MASK,
+};
+use crate::{
+    errors::{timeout_err, QueryProtocolError, Result},
+    latency::{LatencyStats, SAMPLE_INTERVAL},
+    socket::host_port,
+    DEFAULT_QUERY_PORT,
+};
+
+/// The largest payload a UDP datagram can carry over IPv4.
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/// Configuration for the UDP behaviour of a [`QueryClient`].
+///
+/// The [`Default`] configuration matches the behaviour of the free functions
+/// in the [`query`](crate::query) module.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mc_query::query::{QueryClient, QueryConfig};
+/// use std::time::Duration;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let config = QueryConfig {
+///         stat_timeout: Duration::from_secs(1),
+///         retries: 3,
+///         ..QueryConfig::default()
+///     };
+///
+///     let client = QueryClient::with_config("localhost", 25565, config).await?;
+///     let res = client.stat_full().await?;
+///     println!("Online players: {:?}", res.players);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryConfig {
+    /// How long to wait for the response to each handshake (challenge token) request.
+    ///
+    /// Defaults to 250 milliseconds.
+    pub handshake_timeout: Duration,
+
+    /// How long to wait for the response to each stat request.
+    ///
+    /// Servers usually answer the handshake right away, but can take noticeably longer to
+    /// put together a full stat response, especially with many players online or plugins
+    /// installed. Defaults to 500 milliseconds.
+    pub stat_timeout: Duration,
+
+    /// How many times to resend a request whose response did not arrive within
+    /// its timeout. UDP does not guarantee delivery, so a single
+    /// retry is usually enough to ride out a dropped packet.
+    ///
+    /// Defaults to 1.
+    pub retries: usize,
+
+    /// The local address to bind the UDP socket to.
+    ///
+    /// Useful on multi-homed hosts, or when firewall rules require a specific
+    /// source interface or port. Defaults to `0.0.0.0:0` (any interface,
+    /// OS-assigned port), which is switched to `[::]:0` for IPv6 servers.
+    pub bind_addr: SocketAddr,
+
+    /// Whether to discard any datagrams already waiting on the socket before
+    /// starting a new request.
+    ///
+    /// When a client is reused, late responses to a previous (timed out) request
+    /// can still arrive and would otherwise be mistaken for the response to the
+    /// next one. Defaults to `true`.
+    pub drain_stale: bool,
+
+    /// How long to wait for the server's address to be resolved and the socket to be
+    /// connected to it, when constructing a client.
+    ///
+    /// UDP sockets don't exchange anything with the server to connect, but resolving a
+    /// hostname can take as long as the system resolver is willing to wait. Defaults to
+    /// `None`, which leaves it to the resolver.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            handshake_timeout: Duration::from_millis(250),
+            stat_timeout: Duration::from_millis(500),
+            retries: 1,
+            bind_addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            drain_stale: true,
+            connect_timeout: None,
+        }
+    }
+}
+
+/// A UDP socket connected to a server's Query port, along with the
+/// [`QueryConfig`] used for requests sent through it.
+///
+/// The free functions in the [`query`](crate::query) module create a new client
+/// per call. Creating a client directly allows tuning its behaviour, and reusing
+/// the same socket for many requests.
+///
+/// Note that the server must have `enable-query=true` set in its properties to get a response.
+#[allow(clippy::module_name_repetitions)]
+pub struct QueryClient {
+    socket: UdpSocket,
+    config: QueryConfig,
+    /// The buffer datagrams are received into, which is reused for every response.
+    recv_buf: Mutex<Box<[u8]>>,
+}
+
+impl fmt::Debug for QueryClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryClient")
+            .field("socket", &self.socket)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QueryClient {
+    /// Construct a [`QueryClient`] for the given host and port, using the default [`QueryConfig`].
+    ///
+    /// # Arguments
+    /// * `host` - the hostname/IP of the server to query
+    /// * `port` - the port that the server's Query is running on
+    ///
+    /// # Errors
+    /// Returns `Err` if the socket could not be bound or connected.
+    pub async fn new(host: &str, port: u16) -> Result<Self> {
+        Self::with_config(host, port, QueryConfig::default()).await
+    }
+
+    /// Construct a [`QueryClient`] for the given host on the [default Query port](crate::DEFAULT_QUERY_PORT),
+    /// using the default [`QueryConfig`].
+    ///
+    /// # Arguments
+    /// * `host` - the hostname/IP of the server to query
+    ///
+    /// # Errors
+    /// Returns `Err` if the socket could not be bound or connected.
+    pub async fn new_default(host: &str) -> Result<Self> {
+        Self::new(host, DEFAULT_QUERY_PORT).await
+    }
+
+    /// Construct a [`QueryClient`] for the given host and port with the given configuration.
+    ///
+    /// # Arguments
+    /// * `host` - the hostname/IP of the server to query
+    /// * `port` - the port that the server's Query is running on
+    /// * `config` - the [`QueryConfig`] to use for all requests made by this client
+    ///
+    /// # Errors
+    /// Returns `Err` if the socket could not be bound or connected.
+    pub async fn with_config(host: &str, port: u16, config: QueryConfig) -> Result<Self> {
+        Self::connect(host_port(host, port), config).await
+    }
+
+    /// Construct a [`QueryClient`] for an address that was already resolved, or anything else
+    /// that can be turned into one, such as a [`SocketAddr`] or an `(IpAddr, u16)` tuple.
+    ///
+    /// If `addr` resolves to several addresses, the first one of the same IP version as the
+    /// [`bind_addr`](QueryConfig::bind_addr) is used, or the first one at all if there is none.
+    /// An unspecified `bind_addr`, like the default one, is switched to the IP version of the
+    /// address used, so IPv6 servers can be queried without changing the configuration.
+    ///
+    /// # Arguments
+    /// * `addr` - the address of the server's Query port
+    /// * `config` - the [`QueryConfig`] to use for all requests made by this client
+    ///
+    /// # Errors
+    /// Returns `Err` if the socket could not be bound or connected, or if that took longer
+    /// than the [`connect_timeout`](QueryConfig::connect_timeout).
+    pub async fn connect(addr: impl ToSocketAddrs, config: QueryConfig) -> Result<Self> {
+        let socket = match config.connect_timeout {
+            None => bind_connected(addr, config.bind_addr).await?,
+            Some(dur) => timeout(dur, bind_connected(addr, config.bind_addr))
+                .await
+                .unwrap_or_else(|_| timeout_err())?,
+        };
+
+        Ok(Self::from_socket(socket, config))
+    }
+
+    /// Construct a [`QueryClient`] from a UDP socket created by the caller.
+    ///
+    /// This allows configuring the socket in ways [`QueryConfig`] doesn't cover, for example
+    /// setting socket options, or reusing a socket set up for NAT traversal.
+    /// The socket must already be [connected](UdpSocket::connect) to the server's Query port,
+    /// since all requests are sent to, and responses received from, its connected address.
+    /// [`QueryConfig::bind_addr`] is ignored.
+    ///
+    /// # Arguments
+    /// * `socket` - a bound UDP socket, connected to the server's Query port
+    /// * `config` - the [`QueryConfig`] to use for all requests made by this client
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use mc_query::query::{QueryClient, QueryConfig};
+    /// use tokio::{io::Result, net::UdpSocket};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    ///     socket.set_ttl(16)?;
+    ///     socket.connect("localhost:25565").await?;
+    ///
+    ///     let client = QueryClient::from_socket(socket, QueryConfig::default());
+    ///     let res = client.stat_basic().await?;
+    ///     println!("{res:#?}");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn from_socket(socket: UdpSocket, config: QueryConfig) -> Self {
+        Self {
+            socket,
+            config,
+            recv_buf: Mutex::new(vec![0; MAX_DATAGRAM_SIZE].into_boxed_slice()),
+        }
+    }
+
+    /// Get the configuration used by this client.
+    #[must_use]
+    pub fn config(&self) -> &QueryConfig {
+        &self.config
+    }
+
+    /// Perform a basic stat query of the server per the [Query Protocol](https://wiki.vg/Query#Basic_Stat).
+    ///
+    /// # Errors
+    /// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or if
+    /// invalid data was recieved.
+    pub async fn stat_basic(&self) -> Result<BasicStatResponse> {
+        let (token, session) = self.handshake().await?;
+
+        self.request_basic(token, session).await
+    }
+
+    /// Perform a full stat query of the server per the [Query Protocol](https://wiki.vg/Query#Full_stat).
+    ///
+    /// # Errors
+    /// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or
+    /// if invalid data was recieved.
+    pub async fn stat_full(&self) -> Result<FullStatResponse> {
+        let (token, session) = self.handshake().await?;
+
+        self.request_full(token, session).await
+    }
+
+    /// Perform both a basic and a full stat query of the server, sharing a single handshake.
+    ///
+    /// This takes one round trip less than calling [`stat_basic`](Self::stat_basic) and
+    /// [`stat_full`](Self::stat_full) separately. If the full stat request goes unanswered,
+    /// as happens when the challenge token expired in between, it is retried once with a
+    /// new token.
+    ///
+    /// # Errors
+    /// Will return `Err` if there was a network error, if the challenge token wasn't obtainable, or
+    /// if invalid data was recieved.
+    pub async fn stat_all(&self) -> Result<(BasicStatResponse, FullStatResponse)> {
+        let (token, session) = self.handshake().await?;
+
+        let basic = self.request_basic(token, session).await?;
+        let full = match self.request_full(token, session).await {
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                let (token, session) = self.handshake().await?;
+                self.request_full(token, session).await?
+            }
+            res => res?,
+        };
+
+        Ok((basic, full))
+    }
+
+    /// Send a basic stat request with a token obtained from [`handshake`](Self::handshake).
+    async fn request_basic(&self, token: i32, session: i32) -> Result<BasicStatResponse> {
+        let request = QueryPacket::builder(QueryPacketType::Stat, session)
+            .add_i32(token)
+            .build();
+
+        let mut res = self
+            .send_recv(&request.bytes(), self.config.stat_timeout)
+            .await?;
+        validate_packet(&mut res, 0, session)?;
+
+        let motd = get_string(&mut res)?;
+        let game_type = get_string(&mut res)?;
+        let map = get_string(&mut res)?;
+        let num_players = get_string(&mut res)?
+            .parse()
+            .map_err::<io::Error, _>(|_| QueryProtocolError::CannotParseInt.into())?;
+        let max_players = get_string(&mut res)?
+            .parse()
+            .map_err::<io::Error, _>(|_| QueryProtocolError::CannotParseInt.into())?;
+
+        ensure_remaining(&res, 2)?;
+        let host_port = res.get_u16_le(); // shorts are little endian per protocol
+
+        let host_ip = get_string(&mut res)?;
+
+        Ok(BasicStatResponse {
+            motd,
+            game_type,
+            map,
+            num_players,
+            max_players,
+            host_port,
+            host_ip,
+        })
+    }
+
+    /// Send a full stat request with a token obtained from [`handshake`](Self::handshake).
+    async fn request_full(&self, token: i32, session: i32) -> Result<FullStatResponse> {
+        let request = QueryPacket::builder(QueryPacketType::Stat, session)
+            .add_i32(token)
+            .add_bytes(&[0; 4]) // 4 extra bytes required for full stat vs. basic
+            .build();
+
+        let mut res = self
+            .send_recv(&request.bytes(), self.config.stat_timeout)
+            .await?;
+        validate_packet(&mut res, 0, session)?;
+
+        // skip 11 meaningless padding bytes
+        ensure_remaining(&res, 11)?;
+        res.advance(11);
+
+        // K,V section
+        let mut kv = HashMap::new();
+        loop {
+            let key = get_string(&mut res)?;
+            if key.is_empty() {
+                break;
+            }
+            let value = get_string(&mut res)?;
+            kv.insert(key, value);
+        }
+
+        // excuse this horrendous code, I don't know of a better way
+        let motd = kv
+            .remove("hostname")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let game_type = kv
+            .remove("gametype")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let game_id = kv
+            .remove("game_id")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let version = kv
+            .remove("version")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let plugins = kv
+            .remove("plugins")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let map = kv
+            .remove("map")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+        let num_players = kv
+            .remove("numplayers")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?
+            .parse()
+            .map_err(|_| QueryProtocolError::CannotParseInt)?;
+        let max_players = kv
+            .remove("maxplayers")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?
+            .parse()
+            .map_err(|_| QueryProtocolError::CannotParseInt)?;
+        let host_port = kv
+            .remove("hostport")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?
+            .parse()
+            .map_err(|_| QueryProtocolError::CannotParseInt)?;
+        let host_ip = kv
+            .remove("hostip")
+            .ok_or(QueryProtocolError::InvalidKeyValueSection)?;
+
+        // skip 10 meaningless padding bytes
+        ensure_remaining(&res, 10)?;
+        res.advance(10);
+
+        // players section, which some servers end without the empty string
+        // that terminates it
+        let mut players = vec![];
+        while res.has_remaining() {
+            let username = get_string(&mut res)?;
+            if username.is_empty() {
+                break;
+            }
+            players.push(username);
+        }
+
+        Ok(FullStatResponse {
+            motd,
+            game_type,
+            game_id,
+            version,
+            plugins,
+            map,
+            num_players,
+            max_players,
+            host_port,
+            host_ip,
+            players,
+            extra: kv,
+        })
+    }
+
+    /// Measure the round trip time to the server over UDP, by sending it `samples`
+    /// handshakes, one after the other.
+    ///
+    /// This complements [`status_latency_samples`](crate::status::status_latency_samples),
+    /// which measures latency over TCP: query and game traffic don't necessarily take the
+    /// same path, and how much the samples vary (their jitter) says a lot about the
+    /// quality of the UDP path. Handshakes are the cheapest requests a server answers,
+    /// and samples are spaced [`SAMPLE_INTERVAL`](crate::query::SAMPLE_INTERVAL) apart,
+    /// to avoid tripping the rate limits some hosts and firewalls put on UDP traffic.
+    ///
+    /// Each sample is subject to the [`handshake_timeout`](QueryConfig::handshake_timeout)
+    /// and [`retries`](QueryConfig::retries) of this client. A handshake that had to be
+    /// retried counts the time spent waiting for the lost response, so packet loss shows
+    /// up as high latency.
+    ///
+    /// # Errors
+    /// Returns `Err` if `samples` is 0, or if any of the handshakes fails.
+    pub async fn handshake_latency(&self, samples: usize) -> Result<LatencyStats> {
+        let mut latencies = Vec::with_capacity(samples);
+
+        for i in 0..samples {
+            if i != 0 {
+                sleep(SAMPLE_INTERVAL).await;
+            }
+
+            let start = Instant::now();
+            self.handshake().await?;
+            latencies.push(start.elapsed());
+        }
+
+        LatencyStats::from_samples(latencies).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "at least one sample is required",
+            )
+            .into()
+        })
+    }
+
+    /// Send a custom packet to the server, and return its raw response.
+    ///
+    /// This is meant for servers that extend the protocol. Requests that need a challenge
+    /// token can get one (along with the session ID it was issued for) with
+    /// [`handshake`](Self::handshake).
+    ///
+    /// The packet is resent according to the [`retries`](QueryConfig::retries) of this
+    /// client, waiting up to [`stat_timeout`](QueryConfig::stat_timeout) for each response.
+    ///
+    /// # Returns
+    /// The response as it was received, starting with its type and session ID.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or no response was received in time.
+    pub async fn send_packet(&self, packet: &QueryPacket) -> Result<Bytes> {
+        if self.config.drain_stale {
+            self.drain();
+        }
+
+        Ok(self
+            .send_recv(&packet.bytes(), self.config.stat_timeout)
+            .await?)
+    }
+
+    /// Perform a handshake request per <https://wiki.vg/Query#Handshake>
+    ///
+    /// # Returns
+    /// A tuple `(challenge_token, session_id)` to be used in subsequent server interactions
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or if the returned token was not valid.
+    /// Returns [`QueryProtocolError::QueryDisabled`] if the server didn't respond at all
+    /// within the [`handshake_timeout`](QueryConfig::handshake_timeout) and its retries.
+    pub async fn handshake(&self) -> Result<(i32, i32)> {
+        if self.config.drain_stale {
+            self.drain();
+        }
+
+        // generate new token per interaction to avoid reset problems
+        #[allow(clippy::cast_possible_wrap)] // this is fine, we don't care about the value
+        let session_id = (random::<u32>() & SESSION_ID_MASK) as i32;
+
+        // no payload for handshake requests
+        let request = QueryPacket::builder(QueryPacketType::Handshake, session_id).build();
+
+        let mut response = match self
+            .send_recv(&request.bytes(), self.config.handshake_timeout)
+            .await
+        {
+            // nothing came back at all, as opposed to an invalid response or a refusal
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                return Err(QueryProtocolError::QueryDisabled.into())
+            }
+            res => res?,
+        };
+        validate_packet(&mut response, 9, session_id)?;
+
+        let token_str = get_string(&mut response)?;
+
+        token_str
+            .parse()
+            .map(|t| (t, session_id))
+            .map_err(|_| QueryProtocolError::CannotParseInt.into())
+    }
+
+    /// Send the given bytes and wait up to `dur` for a response, resending up to
+    /// [`QueryConfig::retries`] times if no response arrives in time.
+    async fn send_recv(&self, bytes: &[u8], dur: Duration) -> io::Result<Bytes> {
+        for _ in 0..=self.config.retries {
+            self.socket.send(bytes).await?;
+
+            if let Ok(res) = timeout(dur, self.recv_packet()).await {
+                return res;
+            }
+        }
+
+        timeout_err()
+    }
+
+    /// Receive a single datagram, keeping only the bytes that actually arrived.
+    async fn recv_packet(&self) -> io::Result<Bytes> {
+        let mut buf = self.recv_buf.lock().await;
+        let len = self.socket.recv(&mut buf).await?;
+
+        Ok(Bytes::copy_from_slice(&buf[..len]))
+    }
+
+    /// Discard any datagrams that are already waiting to be read.
+    fn drain(&self) {
+        let mut buf = [0u8; 1];
+        while self.socket.try_recv(&mut buf).is_ok() {}
+    }
+}
+
+/// Resolve `addr`, and connect a UDP socket bound to `bind_addr` to it.
+///
+/// If `addr` resolves to several addresses, the first one of the same IP version as
+/// `bind_addr` is used. An unspecified `bind_addr` is switched to the IP version of the
+/// address used.
+async fn bind_connected(
+    addr: impl ToSocketAddrs,
+    mut bind_addr: SocketAddr,
+) -> io::Result<UdpSocket> {
+    let addrs = lookup_host(addr).await?.collect::<Vec<_>>();
+    let addr = addrs
+        .iter()
+        .find(|addr| addr.is_ipv4() == bind_addr.is_ipv4())
+        .or_else(|| addrs.first())
+        .copied()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any address",
+            )
+        })?;
+
+    if bind_addr.ip().is_unspecified() && bind_addr.is_ipv4() != addr.is_ipv4() {
+        let ip = if addr.is_ipv4() {
+            IpAddr::from(Ipv4Addr::UNSPECIFIED)
+        } else {
+            IpAddr::from(Ipv6Addr::UNSPECIFIED)
+        };
+        bind_addr.set_ip(ip);
+    }
+
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await?;
+
+    Ok(socket)
+}
+
+/// Make sure at least `len` more bytes can be read from the packet.
+fn ensure_remaining(packet: &Bytes, len: usize) -> io::Result<()> {
+    if packet.remaining() < len {
+        return Err(QueryProtocolError::TruncatedPacket.into());
+    }
+
+    Ok(())
+}
+
+fn validate_packet(packet: &mut Bytes, expected_type: u8, expected_session: i32) -> io::Result<()> {
+    ensure_remaining(packet, 5)?;
+
+    let recv_type = packet.get_u8();
+    if recv_type != expected_type {
+        return Err(QueryProtocolError::InvalidPacketType.into());
+    }
+
+    let recv_session = packet.get_i32();
+    if recv_session != expected_session {
+        return Err(QueryProtocolError::SessionIdMismatch.into());
+    }
+
+    Ok(())
+}
+
+fn get_string(bytes: &mut Bytes) -> io::Result<String> {
+    let len = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or(QueryProtocolError::TruncatedPacket)?;
+
+    let string = bytes.split_to(len);
+    bytes.advance(1); // the null terminator
+
+    String::from_utf8(string.to_vec()).map_err(|_| QueryProtocolError::InvalidUtf8.into())
+}
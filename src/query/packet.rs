@@ -0,0 +1,130 @@
+//! Building request packets for the [Query](https://wiki.vg/Query) protocol.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::QUERY_MAGIC;
+
+/// The type of a [`QueryPacket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryPacketType {
+    /// A [handshake](https://wiki.vg/Query#Handshake), requesting a challenge token.
+    Handshake,
+
+    /// A [basic](https://wiki.vg/Query#Basic_stat) or [full](https://wiki.vg/Query#Full_stat)
+    /// stat request.
+    Stat,
+
+    /// Any other packet type, for servers that implement extensions to the protocol.
+    Other(u8),
+}
+
+impl From<QueryPacketType> for u8 {
+    fn from(packet_type: QueryPacketType) -> Self {
+        match packet_type {
+            QueryPacketType::Handshake => 9,
+            QueryPacketType::Stat => 0,
+            QueryPacketType::Other(id) => id,
+        }
+    }
+}
+
+/// A request packet sent to the server, built with [`QueryPacket::builder`].
+///
+/// Use [`QueryClient::send_packet`](super::QueryClient::send_packet) to send it.
+#[derive(Debug, Clone)]
+pub struct QueryPacket {
+    packet_type: QueryPacketType,
+    session_id: i32,
+    payload: Bytes,
+}
+
+impl QueryPacket {
+    /// Start building a packet of the given type.
+    ///
+    /// # Arguments
+    /// * `packet_type` - The type of the packet.
+    /// * `session_id` - The session ID, which the server repeats in its response. Only the
+    ///   lower 4 bits of each byte are used by vanilla servers, so IDs should be masked
+    ///   with `0x0F0F0F0F`.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::query::{QueryPacket, QueryPacketType};
+    ///
+    /// // a basic stat request, with the challenge token from a handshake
+    /// let packet = QueryPacket::builder(QueryPacketType::Stat, 1)
+    ///     .add_i32(9_513_307)
+    ///     .build();
+    ///
+    /// assert_eq!(&packet.bytes()[..], b"\xfe\xfd\x00\x00\x00\x00\x01\x00\x91\x29\x5b");
+    /// ```
+    #[must_use]
+    pub fn builder(packet_type: QueryPacketType, session_id: i32) -> QueryPacketBuilder {
+        QueryPacketBuilder {
+            packet_type,
+            session_id,
+            bytes: BytesMut::new(),
+        }
+    }
+
+    /// The type of the packet.
+    #[must_use]
+    pub fn packet_type(&self) -> QueryPacketType {
+        self.packet_type
+    }
+
+    /// The session ID of the packet.
+    #[must_use]
+    pub fn session_id(&self) -> i32 {
+        self.session_id
+    }
+
+    /// Encode the packet, as it is sent to the server.
+    #[must_use]
+    pub fn bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(7 + self.payload.len());
+        bytes.put_u16(QUERY_MAGIC);
+        bytes.put_u8(self.packet_type.into());
+        bytes.put_i32(self.session_id);
+        bytes.extend_from_slice(&self.payload);
+
+        bytes.freeze()
+    }
+}
+
+/// Builder for a [`QueryPacket`], returned by [`QueryPacket::builder`].
+///
+/// Values are appended to the payload in the order they are added.
+#[derive(Debug)]
+pub struct QueryPacketBuilder {
+    packet_type: QueryPacketType,
+    session_id: i32,
+    bytes: BytesMut,
+}
+
+impl QueryPacketBuilder {
+    /// Append a big endian `i32`, such as the challenge token.
+    #[must_use]
+    pub fn add_i32(mut self, int: i32) -> Self {
+        self.bytes.put_i32(int);
+        self
+    }
+
+    /// Append the given bytes as they are, such as the 4 bytes of padding
+    /// that turn a basic stat request into a full stat request.
+    #[must_use]
+    pub fn add_bytes(mut self, bytes: &[u8]) -> Self {
+        self.bytes.put_slice(bytes);
+        self
+    }
+
+    /// Finish building the packet.
+    #[must_use]
+    pub fn build(self) -> QueryPacket {
+        QueryPacket {
+            packet_type: self.packet_type,
+            session_id: self.session_id,
+            payload: self.bytes.freeze(),
+        }
+    }
+}
@@ -5,7 +5,7 @@ mod client;
 mod packet;
 
 #[allow(clippy::module_name_repetitions)]
-pub use client::RconClient;
+pub use client::{ConsoleTail, RconClient, RconClientBuilder, CONSOLE_IDLE_TIMEOUT};
 
 const MAX_LEN_CLIENTBOUND: usize = 4096;
 const MAX_LEN_SERVERBOUND: usize = 1446;
@@ -1,17 +1,32 @@
 //! Implementation of the [RCON](https://wiki.vg/RCON) protocol.
 
 use super::{
-    packet::{RconPacket, RconPacketType},
+    packet::{decode_payload, is_valid_remaining_length, LoginPacket, RconPacket, RconPacketType},
     MAX_LEN_CLIENTBOUND,
 };
-use crate::errors::{timeout_err, RconProtocolError};
-use bytes::{BufMut, BytesMut};
-use std::time::Duration;
+use crate::{
+    chat::strip_formatting,
+    errors::{timeout_err, RconProtocolError, Result},
+    socket::{connect_timeout, host_port, is_dropped},
+    DEFAULT_RCON_PORT,
+};
+use bytes::BytesMut;
+use futures_core::Stream;
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::{poll_fn, Future},
+    mem::size_of,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
 use tokio::{
     io::{self, AsyncReadExt, AsyncWriteExt, Error},
-    net::TcpStream,
+    net::{TcpStream, ToSocketAddrs},
     time::timeout,
 };
+use zeroize::Zeroize;
 
 /// Struct that stores the connection and other state of the RCON protocol with the server.
 ///
@@ -32,11 +47,74 @@ use tokio::{
 ///     Ok(())
 /// }
 /// ```
+///
+/// # Cancellation
+///
+/// The futures returned by [`authenticate`](Self::authenticate) and
+/// [`run_command`](Self::run_command) can be dropped at any point, for example
+/// with `tokio::select!` when a user aborts a slow command, or when a timeout expires.
+/// An operation cancelled that way may have sent only part of its request, or
+/// left part of the response unread, so the connection can no longer be used
+/// reliably. The client keeps track of this: every operation after a cancelled one
+/// returns [`RconProtocolError::Desynchronized`] (see [`is_desynchronized`](Self::is_desynchronized))
+/// instead of misinterpreting leftover data as its own response.
+/// Call [`reconnect`](Self::reconnect), or connect a new client, to continue.
+///
+/// # Reconnecting
+///
+/// The server closes all RCON connections when it restarts, after which every operation
+/// fails. With [`set_auto_reconnect`](Self::set_auto_reconnect), commands that fail
+/// because the connection was closed are retried once on a new connection, which is
+/// authenticated with the password the server last accepted. This is off by default, and
+/// has to be enabled before [authenticating](Self::authenticate), since the client only
+/// keeps the password around while it is on.
+///
+/// A retried command is run *at least once*, not exactly once: the connection may be
+/// closed after the server received the command, and even after it ran it, but before
+/// its output arrived. Such a command is sent again on the new connection, and so runs
+/// twice. Only enable auto-reconnect for commands that are safe to repeat, like `list`.
 #[allow(clippy::module_name_repetitions)]
+#[allow(clippy::struct_excessive_bools)] // independent flags, not a state machine
 #[derive(Debug)]
 pub struct RconClient {
     socket: TcpStream,
     timeout: Option<Duration>,
+    /// Whether an exchange with the server has been started but not finished.
+    in_exchange: bool,
+    /// The request id to use for the next packet sent to the server.
+    request_id: i32,
+    /// Whether payloads are sent and received as UTF-8 instead of ASCII.
+    utf8: bool,
+    /// Whether the server accepted the password sent with `authenticate`.
+    authenticated: bool,
+    /// The address to connect to when reconnecting, unless constructed from a stream.
+    addr: Option<String>,
+    /// How long to wait for the connection to be established when reconnecting.
+    connect_timeout: Option<Duration>,
+    /// The password the server last accepted, to authenticate with when reconnecting.
+    password: Option<Password>,
+    /// Whether to reconnect when the connection is closed during a command.
+    auto_reconnect: bool,
+    /// The PROXY protocol header to send at the start of every connection.
+    #[cfg(feature = "proxy-protocol")]
+    proxy_header: Option<crate::proxy::ProxyHeader>,
+}
+
+/// A password kept for reconnecting, which is left out of [`Debug`] output, and
+/// overwritten with zeros when dropped.
+#[derive(Clone)]
+struct Password(String);
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Password(..)")
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
 }
 
 impl RconClient {
@@ -52,13 +130,137 @@ impl RconClient {
     ///
     /// # Errors
     /// Returns `Err` if there was a network error.
-    pub async fn new(host: &str, port: u16) -> io::Result<Self> {
-        let connection = TcpStream::connect(format!("{host}:{port}")).await?;
+    pub async fn new(host: &str, port: u16) -> Result<Self> {
+        let addr = host_port(host, port);
+        let connection = TcpStream::connect(&addr).await?;
+
+        let mut client = Self::from_stream(connection);
+        client.addr = Some(addr);
+
+        Ok(client)
+    }
+
+    /// Construct an [`RconClient`] that connects to an address that was already resolved, or
+    /// anything else that can be turned into one, such as a [`SocketAddr`](std::net::SocketAddr)
+    /// or an `(IpAddr, u16)` tuple.
+    /// Note: to authenticate use the `authenticate` method, this method does not take a password.
+    ///
+    /// # Arguments
+    /// * `addr` - The address of the server's RCON port. If it resolves to several addresses,
+    ///   they are tried in order until a connection succeeds.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error.
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let connection = TcpStream::connect(addr).await?;
+        let addr = connection.peer_addr()?.to_string();
+
+        let mut client = Self::from_stream(connection);
+        client.addr = Some(addr);
+
+        Ok(client)
+    }
+
+    /// Start building an [`RconClient`] that connects to the given host and port, for
+    /// configuring more than the other constructors allow.
+    /// Note: to authenticate use the `authenticate` method, the builder does not take a password.
+    ///
+    /// # Arguments
+    /// * `host` - A string slice that holds the hostname of the server to connect to.
+    /// * `port` - The port to connect to.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use mc_query::rcon::RconClient;
+    /// use std::time::Duration;
+    /// use tokio::io::Result;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let mut client = RconClient::builder("localhost", 25575)
+    ///         .connect_timeout(Duration::from_secs(3))
+    ///         .timeout(Duration::from_secs(10))
+    ///         .allow_utf8(true)
+    ///         .connect()
+    ///         .await?;
+    ///     client.authenticate("password").await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn builder(host: &str, port: u16) -> RconClientBuilder {
+        RconClientBuilder {
+            host: host.to_string(),
+            port,
+            timeout: None,
+            connect_timeout: None,
+            allow_utf8: false,
+            auto_reconnect: false,
+        }
+    }
 
-        Ok(Self {
-            socket: connection,
+    /// Construct an [`RconClient`] from a connection to the server's RCON port that was
+    /// established by the caller.
+    /// Note: to authenticate use the `authenticate` method, this method does not take a password.
+    ///
+    /// This allows connecting in ways [`new`](Self::new) doesn't cover, for example through an
+    /// SSH tunnel or a SOCKS proxy, or with socket options set before connecting. Nothing must
+    /// have been sent or received over the connection yet, apart from anything a tunnel or
+    /// proxy needs to set it up.
+    ///
+    /// Clients constructed this way can't [`reconnect`](Self::reconnect), since they don't
+    /// know how the connection was established.
+    ///
+    /// # Arguments
+    /// * `stream` - A TCP connection to the server's RCON port.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use mc_query::rcon::RconClient;
+    /// use tokio::{io::Result, net::TcpStream};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     // for example, the local end of `ssh -L 25575:localhost:25575 server`
+    ///     let stream = TcpStream::connect("127.0.0.1:25575").await?;
+    ///     stream.set_nodelay(true)?;
+    ///
+    ///     let mut client = RconClient::from_stream(stream);
+    ///     client.authenticate("password").await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    #[must_use]
+    pub fn from_stream(stream: TcpStream) -> Self {
+        Self {
+            socket: stream,
             timeout: None,
-        })
+            in_exchange: false,
+            request_id: 1,
+            utf8: false,
+            authenticated: false,
+            addr: None,
+            connect_timeout: None,
+            password: None,
+            auto_reconnect: false,
+            #[cfg(feature = "proxy-protocol")]
+            proxy_header: None,
+        }
+    }
+
+    /// Construct an [`RconClient`] that connects to the given host on the
+    /// [default RCON port](crate::DEFAULT_RCON_PORT).
+    /// Note: to authenticate use the `authenticate` method, this method does not take a password.
+    ///
+    /// # Arguments
+    /// * `host` - A string slice that holds the hostname of the server to connect to.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error.
+    pub async fn new_default(host: &str) -> Result<Self> {
+        Self::new(host, DEFAULT_RCON_PORT).await
     }
 
     /// Construct an [`RconClient`] that connects to the given host and port, and a connection
@@ -73,50 +275,190 @@ impl RconClient {
     /// * `port` - The port to connect to.
     /// * `timeout` - A duration to wait for each response to arrive in.
     ///
+    /// The timeout doesn't apply to connecting, use the [`builder`](Self::builder) with a
+    /// [`connect_timeout`](RconClientBuilder::connect_timeout) to bound that as well.
+    ///
     /// # Errors
     /// Returns `Err` if there was a network error.
-    pub async fn with_timeout(host: &str, port: u16, timeout: Duration) -> io::Result<Self> {
+    pub async fn with_timeout(host: &str, port: u16, timeout: Duration) -> Result<Self> {
         let mut client = Self::new(host, port).await?;
         client.set_timeout(Some(timeout));
 
         Ok(client)
     }
 
+    /// Construct an [`RconClient`] that connects to the given host and port, sending the
+    /// given [PROXY protocol](crate::proxy) header as soon as the connection is established.
+    /// This is needed for servers that only accept connections from a proxy.
+    /// The header is sent again on every new connection made by [`reconnect`](Self::reconnect).
+    /// Note: to authenticate use the `authenticate` method, this method does not take a password.
+    ///
+    /// Requires the `proxy-protocol` feature.
+    ///
+    /// # Arguments
+    /// * `host` - A string slice that holds the hostname of the server to connect to.
+    /// * `port` - The port to connect to.
+    /// * `header` - The [`ProxyHeader`](crate::proxy::ProxyHeader) to send.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error.
+    #[cfg(feature = "proxy-protocol")]
+    pub async fn with_proxy_header(
+        host: &str,
+        port: u16,
+        header: &crate::proxy::ProxyHeader,
+    ) -> Result<Self> {
+        let mut client = Self::new(host, port).await?;
+        client.socket.write_all(&header.bytes()).await?;
+        client.proxy_header = Some(header.clone());
+
+        Ok(client)
+    }
+
     /// Change the timeout for future requests.
     ///
     /// # Arguments
     /// * `timeout` - an option specifying the duration to wait for a response.
-    ///               if none, the client may wait forever.
+    ///   if none, the client may wait forever.
     pub fn set_timeout(&mut self, timeout: Option<Duration>) {
         self.timeout = timeout;
     }
 
+    /// Change whether commands and their output are sent as UTF-8.
+    ///
+    /// The RCON protocol only allows ASCII (plus the section sign in output), which is what
+    /// the client enforces by default, rejecting other commands with
+    /// [`RconProtocolError::NonAsciiPayload`]. Some servers and plugins send and accept UTF-8
+    /// anyway, for example for player names or translated messages. With this enabled,
+    /// commands and the password are sent as UTF-8, and output is decoded as UTF-8, failing
+    /// with [`RconProtocolError::InvalidUtf8`] if it isn't valid.
+    ///
+    /// Note that the payload length limits apply to the encoded length, so non-ASCII
+    /// characters count for more than one byte.
+    ///
+    /// # Arguments
+    /// * `allow_utf8` - whether to send and receive UTF-8 payloads.
+    pub fn set_allow_utf8(&mut self, allow_utf8: bool) {
+        self.utf8 = allow_utf8;
+    }
+
+    /// Whether commands and their output are sent as UTF-8, see [`set_allow_utf8`](Self::set_allow_utf8).
+    #[must_use]
+    pub fn allows_utf8(&self) -> bool {
+        self.utf8
+    }
+
+    /// Change whether commands that fail because the server closed the connection are
+    /// retried once, after [reconnecting](Self::reconnect).
+    ///
+    /// This applies to [`run_command`](Self::run_command) and everything built on it.
+    /// Commands are retried whether the connection was closed while sending them or while
+    /// waiting for their output, so a retried command runs at least once, and twice if the
+    /// server ran it before closing the connection (see [Reconnecting](Self#reconnecting)).
+    /// Only enable this for commands that are safe to repeat. Clients constructed with
+    /// [`from_stream`](Self::from_stream) can't reconnect, and return the original error.
+    ///
+    /// The new connection is authenticated with the password the server last accepted,
+    /// which the client only keeps while this is enabled, so enable it before
+    /// [authenticating](Self::authenticate). Disabling it wipes the password.
+    ///
+    /// # Arguments
+    /// * `auto_reconnect` - whether to reconnect and retry when the connection is closed.
+    pub fn set_auto_reconnect(&mut self, auto_reconnect: bool) {
+        self.auto_reconnect = auto_reconnect;
+        if !auto_reconnect {
+            self.password = None;
+        }
+    }
+
+    /// Whether the client has successfully [authenticated](Self::authenticate) with the
+    /// server. Commands can only be run once it has, and fail with
+    /// [`RconProtocolError::NotAuthenticated`] before that.
+    #[must_use]
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// Whether a previous operation was cancelled part way through, leaving the
+    /// connection in an unknown state. If this is `true`, all further operations
+    /// will fail with [`RconProtocolError::Desynchronized`].
+    #[must_use]
+    pub fn is_desynchronized(&self) -> bool {
+        self.in_exchange
+    }
+
     /// Disconnect from the server and close the RCON connection.
     ///
     /// # Errors
     /// Returns `Err` if there was an issue closing the connection.
-    pub async fn disconnect(mut self) -> io::Result<()> {
-        self.socket.shutdown().await
+    pub async fn disconnect(mut self) -> Result<()> {
+        Ok(self.socket.shutdown().await?)
+    }
+
+    /// Replace the connection to the server with a new one, and authenticate it with the
+    /// password the server last accepted, if [auto-reconnect](Self::set_auto_reconnect) was
+    /// enabled when it did. Otherwise, the new connection has to be authenticated again with
+    /// [`authenticate`](Self::authenticate). A client constructed with a
+    /// [PROXY protocol header](Self::with_proxy_header) sends it again first.
+    ///
+    /// This makes the client usable again after the server closed the connection, for
+    /// example when it restarted, or after an operation was cancelled (see
+    /// [`is_desynchronized`](Self::is_desynchronized)). The settings of the client are kept.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or on any condition that
+    /// [`authenticate`](Self::authenticate) does. Clients constructed with
+    /// [`from_stream`](Self::from_stream) return an error of kind
+    /// [`Unsupported`](io::ErrorKind::Unsupported).
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let addr = self.addr.clone().ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::Unsupported,
+                "clients constructed from a stream can't reconnect",
+            )
+        })?;
+
+        self.socket = connect_timeout(addr, self.connect_timeout).await?;
+        self.in_exchange = false;
+        self.authenticated = false;
+
+        #[cfg(feature = "proxy-protocol")]
+        if let Some(header) = &self.proxy_header {
+            self.socket.write_all(&header.bytes()).await?;
+        }
+
+        match self.password.clone() {
+            Some(password) => self.authenticate(&password.0).await,
+            None => Ok(()),
+        }
     }
 
     /// Authenticate with the server, with the given password.
     ///
     /// If authentication fails, this method will return [`RconProtocolError::AuthFailed`].
     ///
+    /// The password is encoded straight into the buffer the login packet is sent from,
+    /// which is overwritten with zeros once it was sent, or if the authentication is
+    /// cancelled. The client only keeps a copy of it if
+    /// [auto-reconnect](Self::set_auto_reconnect) is enabled and the server accepts it,
+    /// to authenticate again when [reconnecting](Self::reconnect). That copy is overwritten
+    /// the same way when it is replaced, when auto-reconnect is disabled, or when the
+    /// client is dropped. The `password` passed in is left to the caller.
+    ///
     /// # Arguments
     /// * `password` - A string slice that holds the RCON password.
     ///
     /// # Errors
-    /// Returns the raw `tokio::io::Error` if there was a network error.
+    /// Returns [`Error::Io`](crate::Error::Io) if there was a network error.
     /// Returns an apprpriate [`RconProtocolError`] if the authentication failed for other reasons.
     /// Also returns an error if a timeout is set, and the response is not recieved in that timeframe.
-    pub async fn authenticate(&mut self, password: &str) -> io::Result<()> {
+    pub async fn authenticate(&mut self, password: &str) -> Result<()> {
         let to = self.timeout;
         let fut = self.authenticate_raw(password);
 
         match to {
-            None => fut.await,
-            Some(d) => timeout(d, fut).await.unwrap_or(timeout_err()),
+            None => Ok(fut.await?),
+            Some(d) => Ok(timeout(d, fut).await.unwrap_or(timeout_err())?),
         }
     }
 
@@ -128,23 +470,302 @@ impl RconClient {
     /// # Errors
     /// Returns an error if there was a network issue or an [`RconProtocolError`] for other failures.
     /// Also returns an error if a timeout was set and a response was not recieved in that timeframe.
-    pub async fn run_command(&mut self, command: &str) -> io::Result<String> {
-        let to = self.timeout;
-        let fut = self.run_command_raw(command);
+    pub async fn run_command(&mut self, command: &str) -> Result<String> {
+        self.run_command_with_progress(command, |_| {}).await
+    }
 
-        match to {
-            None => fut.await,
-            Some(d) => timeout(d, fut).await.unwrap_or(timeout_err()),
+    /// Like [`run_command`](Self::run_command), but calls `progress` with the total number
+    /// of bytes of output received so far, every time a packet of the response arrives.
+    ///
+    /// Responses longer than 4096 bytes are split over several packets, so this can be
+    /// used to show progress while a command with a lot of output is running.
+    ///
+    /// # Arguments
+    /// * `command` - A string slice that holds the command to run. Must be ASCII and under 1446 bytes in length.
+    /// * `progress` - Called with the number of bytes received so far, after each packet.
+    ///
+    /// # Errors
+    /// Returns an error under the same conditions as [`run_command`](Self::run_command).
+    pub async fn run_command_with_progress(
+        &mut self,
+        command: &str,
+        mut progress: impl FnMut(usize) + Send,
+    ) -> Result<String> {
+        let payload = self.run_command_bytes(command, &mut progress).await?;
+
+        Ok(decode_payload(&payload, self.utf8)?)
+    }
+
+    /// Run the given command on the server and return the raw bytes of the result.
+    ///
+    /// Unlike [`run_command`](Self::run_command), which rejects responses that are not
+    /// ASCII text, this returns the payload exactly as it was recieved. This is useful
+    /// for plugins that send binary (or otherwise non-ASCII) data over RCON.
+    ///
+    /// # Arguments
+    /// * `command` - A string slice that holds the command to run. Must be ASCII and under 1446 bytes in length.
+    ///
+    /// # Errors
+    /// Returns an error if there was a network issue or an [`RconProtocolError`] for other failures.
+    /// Also returns an error if a timeout was set and a response was not recieved in that timeframe.
+    pub async fn run_command_raw_bytes(&mut self, command: &str) -> Result<Vec<u8>> {
+        self.run_command_bytes(command, &mut |_| {}).await
+    }
+
+    /// Run several commands on the server, one after the other, and return their outputs
+    /// in order.
+    ///
+    /// Each command is run like with [`run_command`](Self::run_command), so a timeout
+    /// (see [`set_timeout`](Self::set_timeout)) applies to each command on its own.
+    /// To save the round trip per command, see [`run_commands_pipelined`](Self::run_commands_pipelined).
+    ///
+    /// # Arguments
+    /// * `commands` - The commands to run. Each must be ASCII and under 1446 bytes in length.
+    ///
+    /// # Errors
+    /// Stops at the first command that fails, and returns [`Error::Command`](crate::Error::Command)
+    /// with the command and its position, wrapping an error for any of the reasons
+    /// [`run_command`](Self::run_command) would return one.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use mc_query::rcon::RconClient;
+    /// use tokio::io::Result;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let mut client = RconClient::new("localhost", 25575).await?;
+    ///     client.authenticate("password").await?;
+    ///
+    ///     let outputs = client.run_commands(&["list", "save-all"]).await?;
+    ///     println!("{}", outputs[0]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_commands(&mut self, commands: &[&str]) -> Result<Vec<String>> {
+        let mut outputs = Vec::with_capacity(commands.len());
+        for (index, command) in commands.iter().enumerate() {
+            let output = self
+                .run_command(command)
+                .await
+                .map_err(|err| crate::Error::Command {
+                    index,
+                    command: (*command).to_string(),
+                    source: Box::new(err),
+                })?;
+            outputs.push(output);
+        }
+
+        Ok(outputs)
+    }
+
+    /// Run several commands on the server, without waiting for the output of each
+    /// command before sending the next one, and return their outputs in order.
+    ///
+    /// All commands are sent at once, each with a distinct request id, and the
+    /// responses are matched to the commands by the request id they carry. This saves a
+    /// round trip per command, which adds up when running many small commands.
+    ///
+    /// Not every server copes with this, so the client falls back to running commands
+    /// one by one with [`run_commands`](Self::run_commands) when the server misbehaves:
+    /// * Servers handle the commands of a connection in the order they arrive, so if the
+    ///   response to a command arrives before any response to an earlier one, the server
+    ///   must have dropped the earlier command (some servers only handle one packet per
+    ///   read from the socket). Such commands are run again once all other responses
+    ///   have been read.
+    /// * Vanilla servers close the connection when a read from the socket holds more than
+    ///   one packet. With [auto-reconnect](Self::set_auto_reconnect) enabled, the client then
+    ///   [reconnects](Self::reconnect), and runs the commands that weren't answered yet
+    ///   again, so like any retried command they may run twice. Otherwise, and for clients
+    ///   constructed with [`from_stream`](Self::from_stream), the error is returned instead.
+    ///
+    /// A server that drops the last commands of a batch can't be told apart from a slow
+    /// one, so set a timeout (see [`set_timeout`](Self::set_timeout)) to avoid waiting for
+    /// their responses forever, or use [`run_commands`](Self::run_commands) with such servers.
+    ///
+    /// # Arguments
+    /// * `commands` - The commands to run. Each must be ASCII and under 1446 bytes in length.
+    ///
+    /// # Errors
+    /// Returns an error under the same conditions as [`run_command`](Self::run_command).
+    /// If a timeout is set, it applies to waiting for each response packet. Commands that
+    /// fail after falling back are reported like [`run_commands`](Self::run_commands) does,
+    /// with their position in `commands`.
+    pub async fn run_commands_pipelined(&mut self, commands: &[&str]) -> Result<Vec<String>> {
+        let (outputs, unanswered) = self.pipelined_bytes(commands).await?;
+
+        let mut outputs = outputs
+            .iter()
+            .map(|output| Ok(decode_payload(output, self.utf8)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        if !unanswered.is_empty() {
+            let rerun: Vec<_> = unanswered.iter().map(|index| commands[*index]).collect();
+            let rerun_outputs = self.run_commands(&rerun).await.map_err(|err| match err {
+                crate::Error::Command {
+                    index,
+                    command,
+                    source,
+                } => crate::Error::Command {
+                    index: unanswered[index],
+                    command,
+                    source,
+                },
+                err => err,
+            })?;
+
+            for (index, output) in unanswered.into_iter().zip(rerun_outputs) {
+                outputs[index] = output;
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Attempt to identify the server software by running the `version` command.
+    ///
+    /// This relies on heuristics over the command's output: Bukkit-derived servers
+    /// (Craftbukkit, Spigot, Paper, etc.) reply with `This server is running <brand>`,
+    /// which is parsed into something like `Paper version git-Paper-196 (MC: 1.19.2)`.
+    /// Vanilla servers do not have a `version` command, so `None` is returned for them,
+    /// as well as for any other output that cannot be recognized.
+    ///
+    /// # Errors
+    /// Returns an error under the same conditions as [`run_command`](Self::run_command).
+    pub async fn server_brand(&mut self) -> Result<Option<String>> {
+        let output = self.run_command("version").await?;
+
+        Ok(parse_brand(&output))
+    }
+
+    /// Follow console output that the server pushes over the connection without being
+    /// asked for it. **Experimental.**
+    ///
+    /// Vanilla servers and Bukkit derivatives (Spigot, Paper, etc.) only ever send
+    /// responses to commands, so for them the returned [`ConsoleTail`] ends after its
+    /// idle timeout without yielding anything. Some server wrappers and plugins forward
+    /// console output to connected RCON clients as unsolicited response packets; this
+    /// reads those packets and yields their content line by line.
+    ///
+    /// Lines are read until no packet arrives within the idle timeout
+    /// ([`CONSOLE_IDLE_TIMEOUT`] by default, see [`ConsoleTail::with_idle_timeout`]).
+    /// The client's timeout (if any) applies to reading each packet once it starts arriving.
+    pub fn tail_console(&mut self) -> ConsoleTail<'_> {
+        ConsoleTail {
+            client: Some(self),
+            reading: None,
+            idle_timeout: CONSOLE_IDLE_TIMEOUT,
+            lines: VecDeque::new(),
+            done: false,
         }
     }
 
+    /// Get the value of a [game rule](https://minecraft.fandom.com/wiki/Game_rule)
+    /// by running `gamerule <rule>`.
+    ///
+    /// # Arguments
+    /// * `rule` - The name of the game rule, for example `keepInventory`.
+    ///
+    /// # Errors
+    /// Returns [`RconProtocolError::UnexpectedOutput`] if the output is not in the expected
+    /// format (for example, if the game rule doesn't exist), and otherwise returns an error
+    /// under the same conditions as [`run_command`](Self::run_command).
+    pub async fn get_gamerule(&mut self, rule: &str) -> Result<String> {
+        let output = self.run_command(&format!("gamerule {rule}")).await?;
+
+        parse_after(&output, "is currently set to: ")
+            .map(str::to_string)
+            .ok_or_else(|| RconProtocolError::UnexpectedOutput.into())
+    }
+
+    /// Set the value of a [game rule](https://minecraft.fandom.com/wiki/Game_rule)
+    /// by running `gamerule <rule> <value>`.
+    ///
+    /// # Arguments
+    /// * `rule` - The name of the game rule, for example `keepInventory`.
+    /// * `value` - The new value, for example `true`.
+    ///
+    /// # Errors
+    /// Returns [`RconProtocolError::UnexpectedOutput`] if the server did not confirm the change
+    /// (for example, if the game rule doesn't exist or the value is invalid), and otherwise
+    /// returns an error under the same conditions as [`run_command`](Self::run_command).
+    pub async fn set_gamerule(&mut self, rule: &str, value: &str) -> Result<()> {
+        let output = self
+            .run_command(&format!("gamerule {rule} {value}"))
+            .await?;
+
+        parse_after(&output, "is now set to: ")
+            .map(|_| ())
+            .ok_or_else(|| RconProtocolError::UnexpectedOutput.into())
+    }
+
+    /// Get the world seed by running `seed`.
+    ///
+    /// # Errors
+    /// Returns [`RconProtocolError::UnexpectedOutput`] if the output is not in the expected
+    /// format, and otherwise returns an error under the same conditions as
+    /// [`run_command`](Self::run_command).
+    pub async fn seed(&mut self) -> Result<i64> {
+        let output = self.run_command("seed").await?;
+
+        parse_after(&output, "Seed: ")
+            .and_then(|seed| seed.trim_matches(['[', ']']).parse().ok())
+            .ok_or_else(|| RconProtocolError::UnexpectedOutput.into())
+    }
+
+    /// Get the number of players online, the maximum number of players, and the names of
+    /// the players online by running `list`.
+    ///
+    /// The following formats of the output are supported (formatting codes are ignored):
+    /// * `There are 2 of a max of 20 players online: a, b` (vanilla 1.13 and later,
+    ///   as well as `of a max 20` in some versions)
+    /// * `There are 2/20 players online:` followed by `a, b` on the next line
+    ///   (vanilla 1.12 and earlier)
+    /// * `There are 2 out of maximum 20 players online.` followed by one line per group,
+    ///   such as `default: a, b` (Bukkit derivatives with `EssentialsX`)
+    ///
+    /// # Errors
+    /// Returns [`RconProtocolError::UnexpectedOutput`] if the output is not in one of the
+    /// formats above, and otherwise returns an error under the same conditions as
+    /// [`run_command`](Self::run_command).
+    pub async fn list_players(&mut self) -> Result<(u32, u32, Vec<String>)> {
+        let output = self.run_command("list").await?;
+
+        parse_list(&output).ok_or_else(|| RconProtocolError::UnexpectedOutput.into())
+    }
+
+    /// Get the server's average ticks per second by running `tps`.
+    ///
+    /// **This requires Paper or Spigot**, vanilla servers don't have a `tps` command.
+    ///
+    /// The output is usually `TPS from last 1m, 5m, 15m: 20.0, 20.0, 20.0`, colored green,
+    /// yellow or red depending on how healthy the values are. Formatting codes are stripped
+    /// before parsing, as are the `*` that mark values capped at 20. Some forks report
+    /// additional averages (such as over the last 5s), so all values found are returned
+    /// in the order the server listed them.
+    ///
+    /// # Errors
+    /// Returns [`RconProtocolError::UnexpectedOutput`] if the output is not in the expected
+    /// format (for example, on vanilla servers), and otherwise returns an error under the
+    /// same conditions as [`run_command`](Self::run_command).
+    pub async fn tps(&mut self) -> Result<Vec<f32>> {
+        let output = self.run_command("tps").await?;
+
+        parse_tps(&output).ok_or_else(|| RconProtocolError::UnexpectedOutput.into())
+    }
+
     async fn authenticate_raw(&mut self, password: &str) -> io::Result<()> {
-        let packet =
-            RconPacket::new(1, RconPacketType::Login, password.to_string()).map_err(Error::from)?;
+        let request_id = self.next_request_id();
+        let login = LoginPacket::new(request_id, password, self.utf8).map_err(Error::from)?;
 
-        self.write_packet(packet).await?;
+        self.authenticated = false;
+        self.begin_exchange()?;
+        self.socket.write_all(&login).await?;
+        drop(login); // wipes the password
 
         let packet = self.read_packet().await?;
+        self.in_exchange = false;
 
         if !matches!(packet.packet_type, RconPacketType::RunCommand) {
             return Err(RconProtocolError::InvalidPacketType.into());
@@ -152,31 +773,71 @@ impl RconClient {
 
         if packet.request_id == -1 {
             return Err(RconProtocolError::AuthFailed.into());
-        } else if packet.request_id != 1 {
+        } else if packet.request_id != request_id {
             return Err(RconProtocolError::RequestIdMismatch.into());
         }
 
+        self.authenticated = true;
+        self.password = self.auto_reconnect.then(|| Password(password.to_string()));
         Ok(())
     }
 
-    async fn run_command_raw(&mut self, command: &str) -> io::Result<String> {
-        let packet = RconPacket::new(1, RconPacketType::RunCommand, command.to_string())
-            .map_err(Error::from)?;
+    /// Run a command, reconnecting and running it again if the connection was closed and
+    /// [auto-reconnect](Self::set_auto_reconnect) is enabled.
+    async fn run_command_bytes(
+        &mut self,
+        command: &str,
+        progress: &mut (dyn FnMut(usize) + Send),
+    ) -> Result<Vec<u8>> {
+        match self.command_bytes_with_timeout(command, progress).await {
+            Err(err) if self.auto_reconnect && self.addr.is_some() && is_dropped(err.kind()) => {
+                self.reconnect().await?;
+                self.command_bytes_with_timeout(command, progress).await
+            }
+            res => res,
+        }
+    }
 
+    async fn command_bytes_with_timeout(
+        &mut self,
+        command: &str,
+        progress: &mut (dyn FnMut(usize) + Send),
+    ) -> Result<Vec<u8>> {
+        let to = self.timeout;
+        let fut = self.command_bytes(command, progress);
+
+        match to {
+            None => Ok(fut.await?),
+            Some(d) => Ok(timeout(d, fut).await.unwrap_or(timeout_err())?),
+        }
+    }
+
+    async fn command_bytes(
+        &mut self,
+        command: &str,
+        progress: &mut (dyn FnMut(usize) + Send),
+    ) -> io::Result<Vec<u8>> {
+        self.ensure_authenticated()?;
+
+        let request_id = self.next_request_id();
+        let packet = self.new_packet(request_id, RconPacketType::RunCommand, command)?;
+
+        self.begin_exchange()?;
         self.write_packet(packet).await?;
 
-        let mut full_payload = String::new();
+        let mut full_payload = Vec::new();
 
         loop {
             let recieved = self.read_packet().await?;
 
             if recieved.request_id == -1 {
                 return Err(RconProtocolError::AuthFailed.into());
-            } else if recieved.request_id != 1 {
+            } else if recieved.request_id != request_id {
                 return Err(RconProtocolError::RequestIdMismatch.into());
             }
 
-            full_payload.push_str(&recieved.payload);
+            full_payload.extend_from_slice(&recieved.payload);
+            progress(full_payload.len());
 
             // wiki says this method of determining if this is the end of the
             // response is not 100% reliable, but this is the best solution imo
@@ -186,22 +847,176 @@ impl RconClient {
             }
         }
 
+        self.in_exchange = false;
         Ok(full_payload)
     }
 
+    /// Send all commands at once, and read their responses. Returns the outputs, and the
+    /// indices of the commands that still need to be run because the server dropped them.
+    async fn pipelined_bytes(&mut self, commands: &[&str]) -> Result<(Vec<Vec<u8>>, Vec<usize>)> {
+        self.ensure_authenticated()?;
+
+        let mut ids = Vec::with_capacity(commands.len());
+        let mut bytes = BytesMut::new();
+        for command in commands {
+            let request_id = self.next_request_id();
+            let packet = self.new_packet(request_id, RconPacketType::RunCommand, command)?;
+
+            ids.push(request_id);
+            bytes.extend_from_slice(&packet.bytes());
+        }
+
+        let mut batch = Batch {
+            ids,
+            outputs: vec![Vec::new(); commands.len()],
+            done: vec![false; commands.len()],
+            dropped: Vec::new(),
+        };
+
+        self.begin_exchange()?;
+        match self.exchange_pipelined(&bytes, &mut batch).await {
+            Ok(()) => self.in_exchange = false,
+            // the server closed the connection instead of answering, so run the rest of
+            // the commands on a new one
+            Err(err) if self.auto_reconnect && self.addr.is_some() && is_dropped(err.kind()) => {
+                self.reconnect().await?;
+
+                for (index, done) in batch.done.iter().enumerate() {
+                    if !done {
+                        batch.outputs[index].clear();
+                        batch.dropped.push(index);
+                    }
+                }
+                batch.dropped.sort_unstable();
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok((batch.outputs, batch.dropped))
+    }
+
+    /// Write the packets of a pipelined batch, and read responses until every command
+    /// either has one, or was dropped by the server.
+    async fn exchange_pipelined(&mut self, bytes: &[u8], batch: &mut Batch) -> io::Result<()> {
+        self.socket.write_all(bytes).await?;
+
+        let mut remaining = batch.ids.len();
+        while remaining > 0 {
+            let recieved = match self.timeout {
+                None => self.read_packet().await,
+                Some(d) => timeout(d, self.read_packet())
+                    .await
+                    .unwrap_or(timeout_err()),
+            }?;
+
+            if recieved.request_id == -1 {
+                return Err(RconProtocolError::AuthFailed.into());
+            }
+            let index = batch
+                .ids
+                .iter()
+                .position(|id| *id == recieved.request_id)
+                .filter(|index| !batch.done[*index])
+                .ok_or(RconProtocolError::RequestIdMismatch)?;
+
+            batch.outputs[index].extend_from_slice(&recieved.payload);
+
+            // same end of response detection as for single commands
+            if recieved.payload.len() < MAX_LEN_CLIENTBOUND {
+                batch.done[index] = true;
+                remaining -= 1;
+
+                // earlier commands without any response were dropped by the server
+                for earlier in 0..index {
+                    if !batch.done[earlier] && batch.outputs[earlier].is_empty() {
+                        batch.done[earlier] = true;
+                        remaining -= 1;
+                        batch.dropped.push(earlier);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check whether the server speaks RCON, by sending it an empty command without
+    /// authenticating. Servers refuse to run the command, but still respond with a
+    /// (failed authentication) packet, which is all that is checked for.
+    #[cfg(feature = "status")]
+    pub(crate) async fn probe(&mut self) -> io::Result<()> {
+        let request_id = self.next_request_id();
+        let packet = RconPacket::new(request_id, RconPacketType::RunCommand, String::new())
+            .map_err(Error::from)?;
+
+        self.begin_exchange()?;
+        self.write_packet(packet).await?;
+        self.read_packet().await?;
+        self.in_exchange = false;
+
+        Ok(())
+    }
+
+    /// Get the request id to use for the next packet, so that every request
+    /// sent over this connection has a distinct id.
+    fn next_request_id(&mut self) -> i32 {
+        let id = self.request_id;
+        // -1 is reserved for failed authentication, and 0 is often used by
+        // servers for unsolicited packets, so wrap around to 1
+        self.request_id = id.checked_add(1).unwrap_or(1);
+
+        id
+    }
+
+    /// Mark the start of a request/response exchange with the server.
+    ///
+    /// Once started, the exchange is only marked as finished when the full response
+    /// has been read. If the future performing it is dropped before then, the flag
+    /// stays set and the client refuses any further operations.
+    fn begin_exchange(&mut self) -> io::Result<()> {
+        if self.in_exchange {
+            return Err(RconProtocolError::Desynchronized.into());
+        }
+
+        self.in_exchange = true;
+        Ok(())
+    }
+
     /// Read a packet from the socket.
     async fn read_packet(&mut self) -> io::Result<RconPacket> {
         let len = self.socket.read_i32_le().await?;
+        if !is_valid_remaining_length(len) {
+            return Err(RconProtocolError::InvalidRconResponse.into());
+        }
 
-        let mut bytes = BytesMut::new();
-        bytes.put_i32_le(len);
+        let mut bytes = BytesMut::zeroed(size_of::<i32>() + len as usize);
+        bytes[..size_of::<i32>()].copy_from_slice(&len.to_le_bytes());
+        self.socket
+            .read_exact(&mut bytes[size_of::<i32>()..])
+            .await?;
 
-        for _ in 0..len {
-            let current = self.socket.read_u8().await?;
-            bytes.put_u8(current);
+        RconPacket::try_from(bytes.freeze()).map_err(Error::from)
+    }
+
+    /// Fail early if the client hasn't authenticated, as the server would otherwise
+    /// respond in confusing ways to the commands that follow.
+    fn ensure_authenticated(&self) -> io::Result<()> {
+        if !self.authenticated {
+            return Err(RconProtocolError::NotAuthenticated.into());
         }
 
-        RconPacket::try_from(bytes.freeze()).map_err(Error::from)
+        Ok(())
+    }
+
+    /// Create a packet to send to the server, with the encoding set for this client.
+    fn new_packet(
+        &self,
+        request_id: i32,
+        packet_type: RconPacketType,
+        payload: &str,
+    ) -> io::Result<RconPacket> {
+        RconPacket::with_encoding(request_id, packet_type, payload.to_string(), self.utf8)
+            .map_err(Error::from)
     }
 
     /// Write a packet to the socket.
@@ -215,25 +1030,854 @@ impl RconClient {
     }
 }
 
+/// The state of the commands sent by [`RconClient::run_commands_pipelined`].
+struct Batch {
+    /// The request id each command was sent with.
+    ids: Vec<i32>,
+    outputs: Vec<Vec<u8>>,
+    /// Whether each command was fully answered, or dropped by the server.
+    done: Vec<bool>,
+    /// The commands the server dropped, which need to be run again.
+    dropped: Vec<usize>,
+}
+
+/// Builder for an [`RconClient`], returned by [`RconClient::builder`].
+///
+/// Options that aren't set keep the defaults of [`RconClient::new`].
+#[derive(Debug, Clone)]
+pub struct RconClientBuilder {
+    host: String,
+    port: u16,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    allow_utf8: bool,
+    auto_reconnect: bool,
+}
+
+impl RconClientBuilder {
+    /// Set how long to wait for each response to arrive, see [`RconClient::set_timeout`].
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long to wait for the connection to be established.
+    /// By default, this is left to the OS, which may take minutes to give up.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set whether commands and their output are sent as UTF-8, see [`RconClient::set_allow_utf8`].
+    #[must_use]
+    pub fn allow_utf8(mut self, allow_utf8: bool) -> Self {
+        self.allow_utf8 = allow_utf8;
+        self
+    }
+
+    /// Set whether to reconnect when the connection is closed during a command, see
+    /// [`RconClient::set_auto_reconnect`].
+    #[must_use]
+    pub fn auto_reconnect(mut self, auto_reconnect: bool) -> Self {
+        self.auto_reconnect = auto_reconnect;
+        self
+    }
+
+    /// Connect to the server, and finish building the client.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or if the connection was not
+    /// established within the [connect timeout](Self::connect_timeout).
+    pub async fn connect(self) -> Result<RconClient> {
+        let addr = host_port(&self.host, self.port);
+        let stream = connect_timeout(&addr, self.connect_timeout).await?;
+
+        let mut client = RconClient::from_stream(stream);
+        client.set_timeout(self.timeout);
+        client.set_allow_utf8(self.allow_utf8);
+        client.set_auto_reconnect(self.auto_reconnect);
+        client.addr = Some(addr);
+        client.connect_timeout = self.connect_timeout;
+
+        Ok(client)
+    }
+}
+
+/// The default time [`ConsoleTail`] waits for more output before ending.
+pub const CONSOLE_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A read of the next console output, which holds on to the client until it completes.
+///
+/// Resolves to `None` once no more output arrived within the idle timeout, or the server
+/// closed the connection.
+type ReadLines<'a> =
+    Pin<Box<dyn Future<Output = (&'a mut RconClient, Option<Result<Vec<String>>>)> + Send + 'a>>;
+
+/// Console output pushed by the server, returned by [`RconClient::tail_console`].
+///
+/// This is a [`Stream`] of lines, for use with stream combinators (such as those of
+/// `futures::StreamExt` or `tokio_stream::StreamExt`). Use [`next_line`](Self::next_line)
+/// to read it line by line without them.
+pub struct ConsoleTail<'a> {
+    /// The client, while no read is in progress.
+    client: Option<&'a mut RconClient>,
+    reading: Option<ReadLines<'a>>,
+    idle_timeout: Duration,
+    lines: VecDeque<String>,
+    done: bool,
+}
+
+impl ConsoleTail<'_> {
+    /// Change how long to wait for more output before ending.
+    #[must_use]
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Get the next line of console output.
+    ///
+    /// Returns `None` once no more output arrived within the idle timeout, the server
+    /// closed the connection, or after an error has been returned.
+    pub async fn next_line(&mut self) -> Option<Result<String>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl Stream for ConsoleTail<'_> {
+    type Item = Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(line) = this.lines.pop_front() {
+                return Poll::Ready(Some(Ok(line)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let idle_timeout = this.idle_timeout;
+            let reading = this.reading.get_or_insert_with(|| {
+                let client = this
+                    .client
+                    .take()
+                    .expect("client is only taken while reading");
+                Box::pin(async move {
+                    let lines = read_console_lines(client, idle_timeout).await;
+                    (client, lines)
+                })
+            });
+
+            let (client, lines) = ready!(reading.as_mut().poll(cx));
+            this.reading = None;
+            this.client = Some(client);
+
+            match lines {
+                None => this.done = true,
+                Some(Ok(lines)) => this.lines.extend(lines),
+                Some(Err(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ConsoleTail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsoleTail")
+            .field("client", &self.client)
+            .field("reading", &self.reading.is_some())
+            .field("idle_timeout", &self.idle_timeout)
+            .field("lines", &self.lines)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+/// Wait for the next packet of console output, and split it into lines.
+async fn read_console_lines(
+    client: &mut RconClient,
+    idle_timeout: Duration,
+) -> Option<Result<Vec<String>>> {
+    // peeking doesn't consume any data, so it is safe to give up on it when
+    // the idle timeout expires
+    let mut buf = [0; 1];
+    match timeout(idle_timeout, client.socket.peek(&mut buf)).await {
+        Err(_) | Ok(Ok(0)) => return None,
+        Ok(Err(err)) => return Some(Err(err.into())),
+        Ok(Ok(_)) => {}
+    }
+
+    let to = client.timeout;
+    let fut = async {
+        client.begin_exchange()?;
+        let packet = client.read_packet().await?;
+        client.in_exchange = false;
+
+        decode_payload(&packet.payload, client.utf8).map_err(Error::from)
+    };
+
+    let payload = match to {
+        None => fut.await,
+        Some(d) => timeout(d, fut).await.unwrap_or(timeout_err()),
+    };
+
+    Some(
+        payload
+            .map(|payload| payload.lines().map(str::to_string).collect())
+            .map_err(Into::into),
+    )
+}
+
+/// Get the (trimmed) rest of the line following `prefix` in the output of a command.
+fn parse_after<'a>(output: &'a str, prefix: &str) -> Option<&'a str> {
+    let (_, rest) = output.split_once(prefix)?;
+    let value = rest.lines().next()?.trim();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Parse the server brand out of the output of the `version` command.
+fn parse_brand(output: &str) -> Option<String> {
+    let output = strip_formatting(output);
+    let (_, rest) = output.split_once("server is running ")?;
+
+    let brand = rest
+        .lines()
+        .next()?
+        .split(" (Implementing API")
+        .next()?
+        .trim()
+        .trim_end_matches('.');
+
+    if brand.is_empty() {
+        None
+    } else {
+        Some(brand.to_string())
+    }
+}
+
+/// Parse the player counts and names out of the output of the `list` command.
+fn parse_list(output: &str) -> Option<(u32, u32, Vec<String>)> {
+    let output = strip_formatting(output);
+    let (_, rest) = output.split_once("There are ")?;
+    let (counts, names) = rest.split_once(" players online")?;
+
+    // works for "2/20", "2 of a max of 20", "2 out of maximum 20" and the like
+    let mut numbers = counts
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|n| !n.is_empty());
+    let online = numbers.next()?.parse().ok()?;
+    let max = numbers.next()?.parse().ok()?;
+    if numbers.next().is_some() {
+        return None;
+    }
+
+    // player names can't contain colons, so anything before one is a label,
+    // such as the group name in the EssentialsX format
+    let names = names
+        .lines()
+        .flat_map(|line| line.rsplit(':').next().unwrap_or(line).split(','))
+        .map(|name| name.trim().trim_start_matches('.').trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    Some((online, max, names))
+}
+
+/// Parse the averages out of the output of the `tps` command.
+fn parse_tps(output: &str) -> Option<Vec<f32>> {
+    let output = strip_formatting(output);
+    let (_, rest) = output.split_once("TPS from last ")?;
+    let (_, values) = rest.split_once(": ")?;
+
+    values
+        .lines()
+        .next()?
+        .split(',')
+        .map(|value| value.trim().trim_start_matches('*').parse().ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::RconClient;
-    use tokio::io;
+    use super::{parse_after, parse_brand, parse_list, parse_tps, RconClient};
+    use crate::errors::{Error, RconProtocolError};
+    use crate::mock::{MockServer, RCON_PASSWORD};
+    use crate::rcon::packet::{RconPacket, RconPacketType};
+    use bytes::{BufMut, BytesMut};
+    use std::time::Duration;
+    use tokio::{
+        io,
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    /// Read a packet sent by the client, as the server.
+    async fn read_request(conn: &mut TcpStream) -> io::Result<RconPacket> {
+        let len = conn.read_i32_le().await?;
+        let mut body = vec![0; usize::try_from(len).unwrap()];
+        conn.read_exact(&mut body).await?;
+
+        let mut bytes = BytesMut::new();
+        bytes.put_i32_le(len);
+        bytes.extend_from_slice(&body);
+
+        Ok(RconPacket::try_from(bytes.freeze()).unwrap())
+    }
+
+    /// Respond to a packet sent by the client, as the server.
+    async fn respond(conn: &mut TcpStream, request: &RconPacket) -> io::Result<()> {
+        let output = format!("ran {}", String::from_utf8_lossy(&request.payload));
+        let packet =
+            RconPacket::with_encoding(request.request_id, RconPacketType::Response, output, true)
+                .unwrap();
+
+        conn.write_all(&packet.bytes()).await
+    }
+
+    #[tokio::test]
+    async fn test_run_command_with_progress() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await?;
+            let request = read_request(&mut conn).await?;
+
+            for payload in ["a".repeat(4096), "b".repeat(4096), "c".repeat(10)] {
+                let packet =
+                    RconPacket::new(request.request_id, RconPacketType::Response, payload).unwrap();
+                conn.write_all(&packet.bytes()).await?;
+            }
+
+            Ok::<_, io::Error>(())
+        });
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        client.authenticated = true; // the mock server has no password
+        let mut progress = Vec::new();
+        let output = client
+            .run_command_with_progress("debug stop", |len| progress.push(len))
+            .await?;
+        assert_eq!(output.len(), 8202);
+        assert_eq!(progress, [4096, 8192, 8202]);
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_commands_pipelined() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await?;
+
+            let mut requests = Vec::new();
+            for _ in 0..3 {
+                requests.push(read_request(&mut conn).await?);
+            }
+
+            let ids: Vec<_> = requests.iter().map(|r| r.request_id).collect();
+            assert!(ids[0] != ids[1] && ids[1] != ids[2]);
+
+            // drop the second command
+            respond(&mut conn, &requests[0]).await?;
+            respond(&mut conn, &requests[2]).await?;
+
+            // which is then sent again on its own
+            let resent = read_request(&mut conn).await?;
+            assert_eq!(resent.payload, "b");
+            respond(&mut conn, &resent).await?;
+
+            Ok::<_, io::Error>(())
+        });
+
+        let mut client =
+            RconClient::with_timeout("127.0.0.1", port, Duration::from_secs(5)).await?;
+        client.authenticated = true; // the mock server has no password
+        let outputs = client.run_commands_pipelined(&["a", "b", "c"]).await?;
+        assert_eq!(outputs, ["ran a", "ran b", "ran c"]);
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_commands_pipelined_closed() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let accept_login = |mut conn: TcpStream| async move {
+                let login = read_request(&mut conn).await?;
+                let packet =
+                    RconPacket::new(login.request_id, RconPacketType::RunCommand, String::new())
+                        .unwrap();
+                conn.write_all(&packet.bytes()).await?;
+                Ok::<_, io::Error>(conn)
+            };
+
+            // like vanilla, close the connection on reading several packets at once
+            let mut conn = accept_login(listener.accept().await?.0).await?;
+            for _ in 0..3 {
+                read_request(&mut conn).await?;
+            }
+            drop(conn);
+
+            // the commands are then run one by one on a new connection
+            let mut conn = accept_login(listener.accept().await?.0).await?;
+            for command in ["a", "b", "c"] {
+                let request = read_request(&mut conn).await?;
+                assert_eq!(request.payload, command.as_bytes());
+                respond(&mut conn, &request).await?;
+            }
+
+            Ok::<_, io::Error>(())
+        });
+
+        let mut client = RconClient::builder("127.0.0.1", port)
+            .timeout(Duration::from_secs(5))
+            .auto_reconnect(true)
+            .connect()
+            .await?;
+        client.authenticate("password").await?;
+        let outputs = client.run_commands_pipelined(&["a", "b", "c"]).await?;
+        assert_eq!(outputs, ["ran a", "ran b", "ran c"]);
+
+        server.await.unwrap()
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let names = |names: &[&str]| names.iter().map(|s| (*s).to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_list("There are 2 of a max of 20 players online: Notch, jeb_"),
+            Some((2, 20, names(&["Notch", "jeb_"])))
+        );
+        assert_eq!(
+            parse_list("There are 0 of a max 20 players online: "),
+            Some((0, 20, Vec::new()))
+        );
+        assert_eq!(
+            parse_list("There are 1/10 players online:\nNotch"),
+            Some((1, 10, names(&["Notch"])))
+        );
+        assert_eq!(
+            parse_list(
+                "§6There are §c3§6 out of maximum §c50§6 players online.\n\
+                 §6admins§r: §fNotch\n§6default§r: §fjeb_, Dinnerbone"
+            ),
+            Some((3, 50, names(&["Notch", "jeb_", "Dinnerbone"])))
+        );
+
+        assert_eq!(parse_list("Unknown command"), None);
+        assert_eq!(parse_list("There are many players online"), None);
+    }
+
+    #[tokio::test]
+    async fn test_tail_console_idle() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        let _conn = listener.accept().await?;
+
+        let mut tail = client
+            .tail_console()
+            .with_idle_timeout(Duration::from_millis(20));
+        assert!(tail.next_line().await.is_none());
+        drop(tail);
+        assert!(!client.is_desynchronized());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tail_console_pushed() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        let (mut conn, _) = listener.accept().await?;
+
+        let packet =
+            RconPacket::new(0, RconPacketType::Response, "first\nsecond".to_string()).unwrap();
+        conn.write_all(&packet.bytes()).await?;
+
+        let mut tail = client
+            .tail_console()
+            .with_idle_timeout(Duration::from_millis(20));
+        assert_eq!(tail.next_line().await.unwrap()?, "first");
+        assert_eq!(tail.next_line().await.unwrap()?, "second");
+        assert!(tail.next_line().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_command() -> io::Result<()> {
+        // a server that accepts the connection but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let mut client =
+            RconClient::with_timeout("127.0.0.1", port, Duration::from_millis(20)).await?;
+        client.authenticated = true; // the mock server has no password
+        let _conn = listener.accept().await?;
+
+        let err = client.run_command("list").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(client.is_desynchronized());
+
+        let err = client.run_command("list").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Rcon(RconProtocolError::Desynchronized)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_length_prefix() -> io::Result<()> {
+        for len in [i32::MAX, -1, 4] {
+            let listener = TcpListener::bind("127.0.0.1:0").await?;
+            let port = listener.local_addr()?.port();
+
+            let server = tokio::spawn(async move {
+                let (mut conn, _) = listener.accept().await?;
+                read_request(&mut conn).await?;
+                conn.write_i32_le(len).await?;
+                conn.write_all(&[0; 64]).await?;
+
+                // keep the connection open until the client gives up
+                conn.read_u8().await.ok();
+                Ok::<_, io::Error>(())
+            });
+
+            let mut client =
+                RconClient::with_timeout("127.0.0.1", port, Duration::from_secs(5)).await?;
+            client.authenticated = true; // the mock server has no password
+            let err = client.run_command("list").await.unwrap_err();
+            assert!(matches!(
+                err,
+                Error::Rcon(RconProtocolError::InvalidRconResponse)
+            ));
+
+            drop(client);
+            server.await.unwrap()?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_utf8_payloads() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await?;
+            let request = read_request(&mut conn).await?;
+            assert_eq!(request.payload, "say h\u{e9}llo");
+            respond(&mut conn, &request).await
+        });
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        client.authenticated = true; // the mock server has no password
+        let err = client.run_command("say h\u{e9}llo").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Rcon(RconProtocolError::NonAsciiPayload)
+        ));
+
+        client.set_allow_utf8(true);
+        assert!(client.allows_utf8());
+        let output = client.run_command("say h\u{e9}llo").await?;
+        assert_eq!(output, "ran say h\u{e9}llo");
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_not_authenticated() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await?;
+            let login = read_request(&mut conn).await?;
+            assert_eq!(login.payload, "password");
+
+            let packet =
+                RconPacket::new(login.request_id, RconPacketType::RunCommand, String::new())
+                    .unwrap();
+            conn.write_all(&packet.bytes()).await?;
+
+            let request = read_request(&mut conn).await?;
+            respond(&mut conn, &request).await
+        });
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        let mut client = RconClient::from_stream(stream);
+        assert!(!client.is_authenticated());
+        let err = client.run_command("list").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Rcon(RconProtocolError::NotAuthenticated)
+        ));
+
+        client.authenticate("password").await?;
+        assert!(client.is_authenticated());
+        assert_eq!(client.run_command("list").await?, "ran list");
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_builder() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let client = RconClient::builder("127.0.0.1", port)
+            .timeout(Duration::from_secs(5))
+            .connect_timeout(Duration::from_secs(5))
+            .allow_utf8(true)
+            .connect()
+            .await?;
+        assert_eq!(client.timeout, Some(Duration::from_secs(5)));
+        assert!(client.allows_utf8());
+        assert!(!client.is_authenticated());
+
+        // nothing listens on a port of a listener that was closed
+        drop(listener);
+        assert!(RconClient::builder("127.0.0.1", port)
+            .connect()
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_reconnect() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            // accept the login, and then close the connection as if restarting
+            for _ in 0..2 {
+                let (mut conn, _) = listener.accept().await?;
+                let login = read_request(&mut conn).await?;
+                assert_eq!(login.payload, "password".as_bytes());
+                let packet =
+                    RconPacket::new(login.request_id, RconPacketType::RunCommand, String::new())
+                        .unwrap();
+                conn.write_all(&packet.bytes()).await?;
+            }
+
+            // the command is run again on the connection after reconnecting
+            let (mut conn, _) = listener.accept().await?;
+            let login = read_request(&mut conn).await?;
+            let packet =
+                RconPacket::new(login.request_id, RconPacketType::RunCommand, String::new())
+                    .unwrap();
+            conn.write_all(&packet.bytes()).await?;
+            let command = read_request(&mut conn).await?;
+            respond(&mut conn, &command).await
+        });
+
+        let mut client = RconClient::builder("127.0.0.1", port)
+            .timeout(Duration::from_secs(5))
+            .auto_reconnect(true)
+            .connect()
+            .await?;
+        client.authenticate("password").await?;
+
+        // reconnecting by hand authenticates the new connection too
+        client.reconnect().await?;
+        assert!(client.is_authenticated());
+        assert_eq!(client.run_command("list").await?, "ran list");
+        server.await.unwrap()?;
+
+        // without auto-reconnect, the password isn't kept to authenticate again with
+        let mock = MockServer::start().await?;
+        let mut client = RconClient::new("127.0.0.1", mock.rcon_port).await?;
+        client.authenticate(RCON_PASSWORD).await?;
+        client.reconnect().await?;
+        assert!(!client.is_authenticated());
+
+        // a client constructed from a stream doesn't know where to reconnect to
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let stream = TcpStream::connect(listener.local_addr()?).await?;
+        let mut client = RconClient::from_stream(stream);
+        let err = client.reconnect().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "proxy-protocol")]
+    #[tokio::test]
+    async fn test_reconnect_proxy_header() -> io::Result<()> {
+        use crate::proxy::{ProxyHeader, ProxyProtocolVersion};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let header = ProxyHeader::new(ProxyProtocolVersion::V2, addr, addr);
+        let expected = header.bytes();
+
+        // the header starts the first connection, and the one made when reconnecting
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut conn, _) = listener.accept().await?;
+                let mut buf = vec![0; expected.len()];
+                conn.read_exact(&mut buf).await?;
+                assert_eq!(buf, expected);
+            }
+
+            Ok::<_, io::Error>(())
+        });
+
+        let mut client = RconClient::with_proxy_header("127.0.0.1", addr.port(), &header).await?;
+        client.reconnect().await?;
+        server.await.unwrap()?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_commands() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await?;
+            for _ in 0..3 {
+                let request = read_request(&mut conn).await?;
+                respond(&mut conn, &request).await?;
+            }
+
+            Ok::<_, io::Error>(())
+        });
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        client.authenticated = true; // the mock server has no password
+        let outputs = client.run_commands(&["list", "tps"]).await?;
+        assert_eq!(outputs, ["ran list", "ran tps"]);
+
+        let err = client
+            .run_commands(&["list", &"a".repeat(2000)])
+            .await
+            .unwrap_err();
+        let Error::Command { index, source, .. } = err else {
+            panic!("expected the failed command");
+        };
+        assert_eq!(index, 1);
+        assert!(matches!(
+            *source,
+            Error::Rcon(RconProtocolError::PayloadTooLong)
+        ));
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_command_too_long() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let mut client = RconClient::new("127.0.0.1", port).await?;
+        client.authenticated = true; // the mock server has no password
+        let (conn, _) = listener.accept().await?;
+
+        let err = client.run_command(&"a".repeat(2000)).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Rcon(RconProtocolError::PayloadTooLong)
+        ));
+        assert!(!client.is_desynchronized());
+
+        // nothing was sent
+        let mut buf = [0u8; 16];
+        assert_eq!(
+            conn.try_read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_after() {
+        let get = "Gamerule keepInventory is currently set to: false";
+        assert_eq!(parse_after(get, "is currently set to: "), Some("false"));
+
+        let set = "Gamerule keepInventory is now set to: true";
+        assert_eq!(parse_after(set, "is now set to: "), Some("true"));
+
+        let seed = "Seed: [-4172144997902289642]";
+        assert_eq!(parse_after(seed, "Seed: "), Some("[-4172144997902289642]"));
+
+        let unknown = "Incorrect argument for command\ngamerule foo<--[HERE]";
+        assert_eq!(parse_after(unknown, "is currently set to: "), None);
+    }
+
+    #[test]
+    fn test_parse_brand() {
+        let paper = "Checking version, please wait...\u{a7}fThis server is running Paper version \
+                     git-Paper-196 (MC: 1.19.2) (Implementing API version 1.19.2-R0.1-SNAPSHOT)\n\
+                     You are running the latest version";
+        assert_eq!(
+            parse_brand(paper).as_deref(),
+            Some("Paper version git-Paper-196 (MC: 1.19.2)")
+        );
+
+        let vanilla = "Unknown or incomplete command, see below for error";
+        assert_eq!(parse_brand(vanilla), None);
+    }
+
+    #[test]
+    fn test_parse_tps() {
+        let paper = "\u{a7}6TPS from last 1m, 5m, 15m: \u{a7}a*20.0, \u{a7}a19.87, \u{a7}e17.5";
+        assert_eq!(parse_tps(paper), Some(vec![20.0, 19.87, 17.5]));
+
+        let purpur = "\u{a7}6TPS from last 5s, 1m, 5m, 15m: \u{a7}c12.1, \u{a7}a20.0, \u{a7}a20.0, \u{a7}a20.0";
+        assert_eq!(parse_tps(purpur), Some(vec![12.1, 20.0, 20.0, 20.0]));
+
+        let vanilla = "Unknown or incomplete command, see below for error";
+        assert_eq!(parse_tps(vanilla), None);
+    }
 
     #[tokio::test]
     async fn test_rcon_command() -> io::Result<()> {
-        let mut client = RconClient::new("localhost", 25575).await?;
-        client.authenticate("mc-query-test").await?;
+        let server = MockServer::start().await?;
+        let mut client = RconClient::new("127.0.0.1", server.rcon_port).await?;
+        client.authenticate(RCON_PASSWORD).await?;
         let response = client.run_command("time set day").await?;
 
-        println!("recieved response: {response}");
+        assert_eq!(response, "Set the time to 1000");
 
         Ok(())
     }
 
     #[tokio::test]
     async fn test_rcon_unauthenticated() -> io::Result<()> {
-        let mut client = RconClient::new("localhost", 25575).await?;
+        let server = MockServer::start().await?;
+        let mut client = RconClient::new("127.0.0.1", server.rcon_port).await?;
         let result = client.run_command("time set day").await;
 
         assert!(result.is_err());
@@ -243,10 +1887,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_rcon_incorrect_password() -> io::Result<()> {
-        let mut client = RconClient::new("localhost", 25575).await?;
+        let server = MockServer::start().await?;
+        let mut client = RconClient::new("127.0.0.1", server.rcon_port).await?;
         let result = client.authenticate("incorrect").await;
 
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(Error::Rcon(RconProtocolError::AuthFailed))
+        ));
 
         Ok(())
     }
@@ -1,6 +1,7 @@
 use crate::errors::RconProtocolError;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use std::mem::size_of;
+use std::{mem::size_of, ops::Deref};
+use zeroize::Zeroize;
 
 use super::{MAX_LEN_CLIENTBOUND, MAX_LEN_SERVERBOUND};
 
@@ -38,27 +39,44 @@ impl TryFrom<i32> for RconPacketType {
 pub(super) struct RconPacket {
     pub request_id: i32,
     pub packet_type: RconPacketType,
-    pub payload: String,
+    pub payload: Bytes,
 }
 
 impl RconPacket {
+    #[cfg(any(feature = "status", test))]
     pub fn new(
         request_id: i32,
         packet_type: RconPacketType,
         payload: String,
     ) -> Result<Self, RconProtocolError> {
-        if !payload.is_ascii() {
+        Self::with_encoding(request_id, packet_type, payload, false)
+    }
+
+    /// Like [`new`](Self::new), but allows any UTF-8 payload if `utf8` is set, instead of
+    /// only ASCII.
+    pub fn with_encoding(
+        request_id: i32,
+        packet_type: RconPacketType,
+        payload: String,
+        utf8: bool,
+    ) -> Result<Self, RconProtocolError> {
+        if !utf8 && !payload.is_ascii() {
             return Err(RconProtocolError::NonAsciiPayload);
         }
 
-        if payload.len() > Ord::max(MAX_LEN_CLIENTBOUND, MAX_LEN_SERVERBOUND) {
+        // responses are only ever built by test servers, everything else is sent to the server
+        let max_len = match packet_type {
+            RconPacketType::Response => MAX_LEN_CLIENTBOUND,
+            RconPacketType::Login | RconPacketType::RunCommand => MAX_LEN_SERVERBOUND,
+        };
+        if payload.len() > max_len {
             return Err(RconProtocolError::PayloadTooLong);
         }
 
         Ok(Self {
             request_id,
             packet_type,
-            payload,
+            payload: Bytes::from(payload),
         })
     }
 
@@ -67,35 +85,45 @@ impl RconPacket {
     }
 }
 
+/// Decode a payload received from the server into a string.
+///
+/// Payloads must be ASCII, with the exception of the section sign (0xa7),
+/// unless `utf8` is set, in which case they must be valid UTF-8.
+pub(super) fn decode_payload(payload: &[u8], utf8: bool) -> Result<String, RconProtocolError> {
+    if utf8 {
+        return String::from_utf8(payload.to_vec()).map_err(|_| RconProtocolError::InvalidUtf8);
+    }
+
+    // 0xa7 is an acceptable (though non-ASCII) character
+    if payload.iter().any(|b| !b.is_ascii() && *b != 0xa7) {
+        return Err(RconProtocolError::NonAsciiPayload);
+    }
+
+    Ok(payload.iter().map(|b| *b as char).collect())
+}
+
 impl TryFrom<Bytes> for RconPacket {
     type Error = RconProtocolError;
 
     fn try_from(mut bytes: Bytes) -> Result<Self, Self::Error> {
+        // the length, request ID, and type, followed by at least the two 0 bytes
+        if bytes.remaining() < size_of::<i32>() + get_remaining_length(&[]) as usize {
+            return Err(RconProtocolError::InvalidRconResponse);
+        }
+
         let len = bytes.get_i32_le(); // length of remaining packet (not including this integer)
         let request_id = bytes.get_i32_le();
         let packet_type = bytes.get_i32_le();
 
-        let mut payload = String::new();
-        loop {
-            let current = bytes.get_u8();
-            if current == 0 {
-                // null terminated ASCII string, so stop reading here
-                break;
-            }
-
-            payload.push(current as char);
-        }
-
-        // if the payload is already normal ASCII (without 0xa7), no need to
-        // check each character to be ASCII or 0xa7
-        if !payload.is_ascii() {
-            for c in payload.chars() {
-                // 0xa7 is an acceptable (though non-ASCII) character
-                if !c.is_ascii() && (c as u8) != 0xa7 {
-                    return Err(RconProtocolError::NonAsciiPayload);
-                }
-            }
+        let payload_len = bytes
+            .iter()
+            .position(|b| *b == 0) // null terminated string, so stop reading here
+            .ok_or(RconProtocolError::InvalidRconResponse)?;
+        let payload = bytes.split_to(payload_len);
+        if bytes.remaining() < 2 {
+            return Err(RconProtocolError::InvalidRconResponse);
         }
+        bytes.advance(1); // skip the null terminator
 
         let pad = bytes.get_u8(); // there must be a remaining 0 byte as padding
         if pad != 0 {
@@ -107,27 +135,83 @@ impl TryFrom<Bytes> for RconPacket {
             return Err(RconProtocolError::InvalidRconResponse);
         }
 
-        Self::new(request_id, packet_type.try_into()?, payload)
+        if payload.len() > MAX_LEN_CLIENTBOUND {
+            return Err(RconProtocolError::PayloadTooLong);
+        }
+
+        Ok(Self {
+            request_id,
+            packet_type: packet_type.try_into()?,
+            payload,
+        })
     }
 }
 
 impl From<RconPacket> for Bytes {
     fn from(packet: RconPacket) -> Self {
-        let len = get_remaining_length(&packet.payload);
-        let packet_type: i32 = packet.packet_type.into();
+        put_packet(packet.request_id, packet.packet_type, &packet.payload).freeze()
+    }
+}
 
-        let mut bytes = BytesMut::new();
+/// Encode a packet, into a buffer of exactly the right size.
+fn put_packet(request_id: i32, packet_type: RconPacketType, payload: &[u8]) -> BytesMut {
+    let len = get_remaining_length(payload);
+    let packet_type: i32 = packet_type.into();
 
-        bytes.put_i32_le(len);
-        bytes.put_i32_le(packet.request_id);
-        bytes.put_i32_le(packet_type);
-        bytes.put(packet.payload.as_bytes());
-        bytes.put_u16(0x00_00);
+    let mut bytes = BytesMut::with_capacity(size_of::<i32>() + len as usize);
 
-        bytes.freeze()
+    bytes.put_i32_le(len);
+    bytes.put_i32_le(request_id);
+    bytes.put_i32_le(packet_type);
+    bytes.put_slice(payload);
+    bytes.put_u16(0x00_00);
+
+    bytes
+}
+
+/// An encoded login packet, which is overwritten with zeros when dropped, so the password
+/// it holds doesn't linger in memory after it was sent.
+pub(super) struct LoginPacket(BytesMut);
+
+impl LoginPacket {
+    /// Encode a login packet straight from the password, without any intermediate copy of it.
+    /// Like [`RconPacket::with_encoding`], the password must be ASCII unless `utf8` is set.
+    pub fn new(request_id: i32, password: &str, utf8: bool) -> Result<Self, RconProtocolError> {
+        if !utf8 && !password.is_ascii() {
+            return Err(RconProtocolError::NonAsciiPayload);
+        }
+        if password.len() > MAX_LEN_SERVERBOUND {
+            return Err(RconProtocolError::PayloadTooLong);
+        }
+
+        Ok(Self(put_packet(
+            request_id,
+            RconPacketType::Login,
+            password.as_bytes(),
+        )))
     }
 }
 
+impl Deref for LoginPacket {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for LoginPacket {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Whether `len` is a valid *remaining length* (see [`get_remaining_length`]) for a
+/// packet sent by the server, which has to be checked before reading the rest of the packet.
+pub(super) fn is_valid_remaining_length(len: i32) -> bool {
+    (get_remaining_length(&[])..=get_remaining_length(&[0; MAX_LEN_CLIENTBOUND])).contains(&len)
+}
+
 /// Get the *remaining length* of the packet given its payload.
 ///
 /// Remaining length here refers to the length of the packet in bytes excluding
@@ -137,6 +221,67 @@ impl From<RconPacket> for Bytes {
 /// As the remainder of the packet is composed of two [i32]s (request ID and type),
 /// the payload, and **TWO** 0 bytes (because rust strings are not null-terminated),
 /// it is the size of two [i32]s + the length of the payload + 2.
-fn get_remaining_length(payload: &str) -> i32 {
+fn get_remaining_length(payload: &[u8]) -> i32 {
     (payload.len() + size_of::<i32>() * 2 + 2) as i32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_payload, LoginPacket, RconPacket, RconPacketType};
+    use crate::errors::RconProtocolError;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_raw_payload() {
+        let bytes =
+            Bytes::from_static(b"\x0c\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\xc3\xa9\x00\x00");
+        let packet = RconPacket::try_from(bytes).unwrap();
+
+        assert_eq!(packet.payload, b"\xc3\xa9".as_slice());
+        assert!(matches!(
+            decode_payload(&packet.payload, false),
+            Err(RconProtocolError::NonAsciiPayload)
+        ));
+        assert_eq!(decode_payload(b"\xa7aok", false).unwrap(), "\u{a7}aok");
+
+        assert_eq!(decode_payload(&packet.payload, true).unwrap(), "\u{e9}");
+        assert!(matches!(
+            decode_payload(b"\xa7aok", true),
+            Err(RconProtocolError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn test_login_packet() {
+        let login = LoginPacket::new(7, "hunter2", false).unwrap();
+        let packet = RconPacket::new(7, RconPacketType::Login, "hunter2".to_string()).unwrap();
+        assert_eq!(&*login, &packet.bytes()[..]);
+
+        assert!(matches!(
+            LoginPacket::new(7, "h\u{fc}nter2", false),
+            Err(RconProtocolError::NonAsciiPayload)
+        ));
+        assert!(LoginPacket::new(7, "h\u{fc}nter2", true).is_ok());
+    }
+
+    #[test]
+    fn test_short_packet() {
+        let full = Bytes::from_static(b"\x0a\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00");
+        assert!(RconPacket::try_from(full.clone()).is_ok());
+
+        for len in [0, 3, 12, full.len() - 1] {
+            assert!(matches!(
+                RconPacket::try_from(full.slice(..len)),
+                Err(RconProtocolError::InvalidRconResponse)
+            ));
+        }
+
+        // the payload ends right at the end of the packet, without the padding byte
+        let unpadded =
+            Bytes::from_static(b"\x0a\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00ab\x00");
+        assert!(matches!(
+            RconPacket::try_from(unpadded),
+            Err(RconProtocolError::InvalidRconResponse)
+        ));
+    }
+}
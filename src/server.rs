@@ -0,0 +1,200 @@
+//! A handle to one server, for talking to it over several protocols without
+//! repeating its address. See documentation for [`Server`] for more information.
+
+use std::time::Duration;
+
+#[cfg(any(feature = "status", feature = "query", feature = "rcon"))]
+use crate::errors::Result;
+#[cfg(feature = "query")]
+use crate::query::{self, BasicStatResponse, FullStatResponse};
+#[cfg(feature = "rcon")]
+use crate::rcon::RconClient;
+#[cfg(feature = "status")]
+use crate::status::{self, data::StatusResponse};
+
+/// A server, along with the ports it runs each protocol on and a default timeout.
+///
+/// Each method delegates to the free function of the same name, so it behaves
+/// exactly like calling that function with this server's host and port.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::Server;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> mc_query::Result<()> {
+///     let server = Server::new("localhost", 25565)
+///         .with_rcon_port(25575)
+///         .with_timeout(Duration::from_secs(5));
+///
+///     println!("{:#?}", server.status().await?);
+///     println!("{:#?}", server.stat_full().await?);
+///
+///     let mut rcon = server.rcon("password").await?;
+///     println!("{}", rcon.run_command("list").await?);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Server {
+    /// The hostname of the server.
+    pub host: String,
+
+    /// The port the server accepts status requests (and players) on.
+    pub port: u16,
+
+    /// The port the server accepts queries on, set by `query.port` in `server.properties`.
+    pub query_port: u16,
+
+    /// The port the server accepts RCON connections on, set by `rcon.port` in `server.properties`.
+    pub rcon_port: u16,
+
+    /// How long to wait for each request, or `None` to wait as long as it takes.
+    pub timeout: Option<Duration>,
+}
+
+impl Server {
+    /// Create a handle to the server at the given host and port.
+    ///
+    /// Query is assumed to run on the same port, like it does by default, and RCON on
+    /// [`DEFAULT_RCON_PORT`](crate::DEFAULT_RCON_PORT). No timeout is set.
+    ///
+    /// # Arguments
+    /// * `host` - A string slice that holds the hostname of the server to connect to.
+    /// * `port` - The port the server is running on.
+    #[must_use]
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            query_port: port,
+            rcon_port: crate::DEFAULT_RCON_PORT,
+            timeout: None,
+        }
+    }
+
+    /// Set the port the server accepts queries on.
+    #[must_use]
+    pub fn with_query_port(mut self, port: u16) -> Self {
+        self.query_port = port;
+        self
+    }
+
+    /// Set the port the server accepts RCON connections on.
+    #[must_use]
+    pub fn with_rcon_port(mut self, port: u16) -> Self {
+        self.rcon_port = port;
+        self
+    }
+
+    /// Set how long to wait for each request.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Get the status of the server, see [`status::status`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`status::status`] does, and also when the
+    /// response is not received within the [timeout](Self::timeout).
+    #[cfg(feature = "status")]
+    pub async fn status(&self) -> Result<StatusResponse> {
+        match self.timeout {
+            None => status::status(&self.host, self.port).await,
+            Some(dur) => status::status_with_timeout(&self.host, self.port, dur).await,
+        }
+    }
+
+    /// Get the basic stat of the server over Query, see [`query::stat_basic`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`query::stat_basic`] does, and also when the
+    /// response is not received within the [timeout](Self::timeout).
+    #[cfg(feature = "query")]
+    pub async fn stat_basic(&self) -> Result<BasicStatResponse> {
+        match self.timeout {
+            None => query::stat_basic(&self.host, self.query_port).await,
+            Some(dur) => query::stat_basic_with_timeout(&self.host, self.query_port, dur).await,
+        }
+    }
+
+    /// Get the full stat of the server over Query, see [`query::stat_full`].
+    ///
+    /// # Errors
+    /// Returns `Err` on any condition that [`query::stat_full`] does, and also when the
+    /// response is not received within the [timeout](Self::timeout).
+    #[cfg(feature = "query")]
+    pub async fn stat_full(&self) -> Result<FullStatResponse> {
+        match self.timeout {
+            None => query::stat_full(&self.host, self.query_port).await,
+            Some(dur) => query::stat_full_with_timeout(&self.host, self.query_port, dur).await,
+        }
+    }
+
+    /// Connect to the server over RCON, and authenticate with the given password.
+    ///
+    /// The [timeout](Self::timeout) applies to connecting, and to each response
+    /// afterwards, see [`RconClient::set_timeout`].
+    ///
+    /// # Arguments
+    /// * `password` - A string slice that holds the RCON password.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network error, or on any condition that
+    /// [`RconClient::authenticate`] does.
+    #[cfg(feature = "rcon")]
+    pub async fn rcon(&self, password: &str) -> Result<RconClient> {
+        let mut builder = RconClient::builder(&self.host, self.rcon_port);
+        if let Some(dur) = self.timeout {
+            builder = builder.timeout(dur).connect_timeout(dur);
+        }
+
+        let mut client = builder.connect().await?;
+        client.authenticate(password).await?;
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Server;
+
+    #[test]
+    fn test_server_defaults() {
+        let server = Server::new("localhost", 25566);
+        assert_eq!(server.query_port, 25566);
+        assert_eq!(server.rcon_port, crate::DEFAULT_RCON_PORT);
+        assert_eq!(server.timeout, None);
+    }
+
+    #[cfg(all(feature = "status", feature = "query", feature = "rcon"))]
+    #[tokio::test]
+    async fn test_server() -> crate::Result<()> {
+        use crate::mock::{MockServer, RCON_PASSWORD};
+        use std::time::Duration;
+
+        let mock = MockServer::start().await?;
+        let server = Server::new("127.0.0.1", mock.status_port)
+            .with_query_port(mock.query_port)
+            .with_rcon_port(mock.rcon_port)
+            .with_timeout(Duration::from_secs(5));
+
+        assert_eq!(server.status().await?.players.online, 1);
+        assert_eq!(server.stat_basic().await?.num_players, 1);
+        assert_eq!(server.stat_full().await?.players, vec!["Notch"]);
+
+        let mut rcon = server.rcon(RCON_PASSWORD).await?;
+        assert_eq!(
+            rcon.run_command("time set day").await?,
+            "Set the time to 1000"
+        );
+        assert!(server.rcon("wrong").await.is_err());
+
+        Ok(())
+    }
+}
@@ -1,63 +1,370 @@
-use crate::varint::{VarInt, CONTINUE_BIT};
+#[cfg(any(feature = "status", feature = "rcon"))]
+use crate::errors::timeout_err;
+use crate::errors::MinecraftProtocolError;
+#[cfg(any(feature = "status", test))]
+use crate::varint::VarInt;
+#[cfg(any(feature = "status", test))]
 use async_trait::async_trait;
-use bytes::{BufMut, BytesMut};
-use std::io::{Error, ErrorKind};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, Result};
+#[cfg(any(feature = "status", test))]
+use std::io::Error;
+#[cfg(any(feature = "status", feature = "rcon", test))]
+use std::io::ErrorKind;
+#[cfg(any(feature = "status", feature = "rcon"))]
+use std::time::Duration;
+use tokio::io::Result;
+#[cfg(any(feature = "status", test))]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(any(feature = "status", feature = "rcon"))]
+use tokio::{
+    net::{TcpStream, ToSocketAddrs},
+    time::timeout,
+};
+
+/// Maximum length of a length-prefixed field that will be read from the socket.
+///
+/// This is the largest length a packet can have according to the protocol
+/// (the maximum value of a 3-byte [`VarInt`]), and protects against malicious
+/// servers sending huge length prefixes to cause excessive allocations.
+pub(crate) const MAX_FIELD_LEN: usize = 2_097_151;
+
+/// Validate the length prefix of a field, returning it as a [`usize`].
+pub(crate) fn check_field_len(len: i32) -> Result<usize> {
+    check_field_len_max(len, MAX_FIELD_LEN)
+}
+
+/// Like [`check_field_len`], but with a lower cap of `max_len` for fields known to be
+/// smaller. A `max_len` above [`MAX_FIELD_LEN`] still can't raise the cap past it.
+pub(crate) fn check_field_len_max(len: i32, max_len: usize) -> Result<usize> {
+    usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= max_len.min(MAX_FIELD_LEN))
+        .ok_or_else(|| MinecraftProtocolError::InvalidLength.into())
+}
+
+/// Join a host and port into an address that can be connected to.
+///
+/// IPv6 literals are put in brackets, since `::1:25565` isn't a valid address, unless
+/// they already are.
+#[cfg(any(
+    feature = "status",
+    feature = "query",
+    feature = "rcon",
+    feature = "bedrock"
+))]
+pub(crate) fn host_port(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+/// Open a TCP connection, giving up with [`ErrorKind::TimedOut`] if it isn't established
+/// within `dur`. Without a timeout, this waits as long as the OS does, which can be minutes
+/// for hosts that silently drop packets.
+#[cfg(any(feature = "status", feature = "rcon"))]
+pub(crate) async fn connect_timeout(
+    addr: impl ToSocketAddrs,
+    dur: Option<Duration>,
+) -> Result<TcpStream> {
+    match dur {
+        None => TcpStream::connect(addr).await,
+        Some(dur) => timeout(dur, TcpStream::connect(addr))
+            .await
+            .unwrap_or_else(|_| timeout_err()),
+    }
+}
+
+/// Whether the error means that the server closed the connection on us.
+#[cfg(any(feature = "status", feature = "rcon"))]
+pub(crate) fn is_dropped(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+    )
+}
 
 /// Trait to allow for reading and writing `VarInt`s from the socket.
 ///
 /// The type is specified [in wiki.vg](https://wiki.vg/Protocol#VarInt_and_VarLong).
+#[cfg(any(feature = "status", test))]
 #[async_trait]
 pub(crate) trait ReadWriteVarInt {
-    /// Read a [VarInt] from the socket.
+    /// Read a [`VarInt`] from the socket.
     /// Returns the parsed value as [i32] in a [Result].
     async fn read_varint(&mut self) -> Result<i32>;
+
+    /// Write a [`VarInt`] to the socket.
+    #[cfg_attr(not(test), allow(dead_code))] // the client builds its packets with `Packet`
+    async fn write_varint(&mut self, value: i32) -> Result<()>;
 }
 
 /// Trait to allow for reading and writing strings from the socket.
 ///
 /// The format for strings is specified [in this table in wiki.vg](https://wiki.vg/Protocol#Data_types).
 /// It is a UTF-8 string prefixed with its size in bytes as a [`VarInt`].
+#[cfg(any(feature = "status", test))]
 #[async_trait]
+#[cfg_attr(not(test), allow(dead_code))] // the client parses strings with `crate::buf`
 pub(crate) trait ReadWriteMinecraftString {
     /// Read a [String] from the socket.
     /// Returns the parsed value recieved from the socket in a [Result].
     async fn read_mc_string(&mut self) -> Result<String>;
+
+    /// Like [`read_mc_string`](Self::read_mc_string), but fails with
+    /// [`MinecraftProtocolError::InvalidLength`] if the string is longer than `max_len`
+    /// bytes, before reading or allocating any of it.
+    async fn read_mc_string_max(&mut self, max_len: usize) -> Result<String>;
+
+    /// Write a [String] to the socket, prefixed with its length.
+    async fn write_mc_string(&mut self, string: &str) -> Result<()>;
+}
+
+/// Trait to allow for reading and writing byte arrays from the socket.
+///
+/// Byte arrays are prefixed with their length as a [`VarInt`], just like strings, and
+/// so are whole packets. Fields already in memory are read with
+/// [`read_mc_bytes_buf`](crate::buf::read_mc_bytes_buf) instead.
+#[cfg(any(feature = "status", test))]
+#[async_trait]
+pub(crate) trait ReadWriteMinecraftBytes {
+    /// Read a length-prefixed byte array from the socket.
+    /// Returns the raw bytes recieved from the socket in a [Result].
+    async fn read_mc_bytes(&mut self) -> Result<Vec<u8>>;
+
+    /// Like [`read_mc_bytes`](Self::read_mc_bytes), but fails with
+    /// [`MinecraftProtocolError::InvalidLength`] if the array is longer than `max_len`
+    /// bytes, before reading or allocating any of it.
+    async fn read_mc_bytes_max(&mut self, max_len: usize) -> Result<Vec<u8>>;
+
+    /// Write a length-prefixed byte array to the socket.
+    /// Fails with [`MinecraftProtocolError::InvalidLength`] if `bytes` is longer than
+    /// [`MAX_FIELD_LEN`], as the other side would reject it anyway.
+    #[cfg_attr(not(test), allow(dead_code))] // the client builds its packets with `Packet`
+    async fn write_mc_bytes(&mut self, bytes: &[u8]) -> Result<()>;
 }
 
+#[cfg(any(feature = "status", test))]
 #[async_trait]
 impl<T> ReadWriteVarInt for T
 where
     T: AsyncRead + AsyncWrite + Unpin + Send,
 {
     async fn read_varint(&mut self) -> Result<i32> {
-        let mut bytes = BytesMut::with_capacity(5);
-
-        loop {
-            let current = self.read_u8().await?;
-            bytes.put_u8(current);
-
-            if current & CONTINUE_BIT == 0 {
-                break;
-            }
-        }
-
-        VarInt::new(bytes.freeze())
+        VarInt::read_from(self)
+            .await?
             .try_into()
             .map_err(|err| Error::new(ErrorKind::InvalidData, err))
     }
+
+    async fn write_varint(&mut self, value: i32) -> Result<()> {
+        self.write_all(&VarInt::from(value)).await
+    }
 }
 
+#[cfg(any(feature = "status", test))]
 #[async_trait]
 impl<T> ReadWriteMinecraftString for T
 where
     T: AsyncRead + AsyncWrite + Unpin + Send,
 {
     async fn read_mc_string(&mut self) -> Result<String> {
-        let len = self.read_varint().await?;
-        let mut buffer = vec![0; len as usize];
-        self.read_exact(&mut buffer).await?;
+        self.read_mc_string_max(MAX_FIELD_LEN).await
+    }
+
+    async fn read_mc_string_max(&mut self, max_len: usize) -> Result<String> {
+        let buffer = self.read_mc_bytes_max(max_len).await?;
 
         String::from_utf8(buffer).map_err(|err| Error::new(ErrorKind::InvalidData, err))
     }
-}
\ No newline at end of file
+
+    async fn write_mc_string(&mut self, string: &str) -> Result<()> {
+        self.write_mc_bytes(string.as_bytes()).await
+    }
+}
+
+#[cfg(any(feature = "status", test))]
+#[async_trait]
+impl<T> ReadWriteMinecraftBytes for T
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn read_mc_bytes(&mut self) -> Result<Vec<u8>> {
+        self.read_mc_bytes_max(MAX_FIELD_LEN).await
+    }
+
+    async fn read_mc_bytes_max(&mut self, max_len: usize) -> Result<Vec<u8>> {
+        let len = check_field_len_max(self.read_varint().await?, max_len)?;
+
+        let mut buffer = vec![0; len];
+        self.read_exact(&mut buffer).await?;
+
+        Ok(buffer)
+    }
+
+    async fn write_mc_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        // lengths that don't even fit an i32 are over the cap as well
+        let len = check_field_len(i32::try_from(bytes.len()).unwrap_or(i32::MAX))?;
+
+        self.write_varint(len as i32).await?;
+        self.write_all(bytes).await
+    }
+}
+
+#[cfg(all(test, feature = "status"))]
+mod tests {
+    use super::{
+        connect_timeout, host_port, MinecraftProtocolError, ReadWriteMinecraftBytes,
+        ReadWriteMinecraftString, ReadWriteVarInt, MAX_FIELD_LEN,
+    };
+    use std::time::Duration;
+    use tokio::{
+        io::{self, AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    #[test]
+    fn test_host_port() {
+        assert_eq!(host_port("localhost", 25565), "localhost:25565");
+        assert_eq!(host_port("127.0.0.1", 25565), "127.0.0.1:25565");
+        assert_eq!(host_port("::1", 25565), "[::1]:25565");
+        assert_eq!(host_port("[::1]", 25565), "[::1]:25565");
+        assert_eq!(host_port("2001:db8::1", 25575), "[2001:db8::1]:25575");
+    }
+
+    #[tokio::test]
+    async fn test_connect_ipv6() -> io::Result<()> {
+        let listener = TcpListener::bind("[::1]:0").await?;
+        let port = listener.local_addr()?.port();
+
+        for host in ["::1", "[::1]"] {
+            TcpStream::connect(host_port(host, port)).await?;
+            listener.accept().await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        connect_timeout(addr, None).await?;
+        connect_timeout(addr, Some(Duration::from_secs(5))).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_mc_bytes() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        client.write_all(b"\x03\x00\x01\xff\x05hello").await?;
+
+        assert_eq!(server.read_mc_bytes().await?, b"\x00\x01\xff");
+        assert_eq!(server.read_mc_bytes().await?, b"hello");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mc_bytes_roundtrip() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        client.write_mc_bytes(b"\x00\x01\xff").await?;
+        client.write_mc_bytes(b"").await?;
+        client.write_mc_bytes(b"hello").await?;
+
+        assert_eq!(server.read_mc_bytes().await?, b"\x00\x01\xff");
+        assert_eq!(server.read_mc_bytes().await?, b"");
+        assert_eq!(server.read_mc_bytes().await?, b"hello");
+
+        // too long to be read back, so it isn't written at all
+        let err = client
+            .write_mc_bytes(&vec![0; MAX_FIELD_LEN + 1])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        drop(client);
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).await?;
+        assert!(rest.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_varint_and_string() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        client.write_varint(25565).await?;
+        client.write_varint(-1).await?;
+        client.write_mc_string("h\u{e9}llo").await?;
+
+        assert_eq!(server.read_varint().await?, 25565);
+        assert_eq!(server.read_varint().await?, -1);
+        assert_eq!(server.read_mc_string().await?, "h\u{e9}llo");
+
+        // the length is that of the UTF-8 encoding, not the number of characters
+        client.write_mc_string("\u{e9}").await?;
+        let mut buf = [0; 3];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(buf, [2, 0xc3, 0xa9]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_varint_too_long() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        client.write_all(&[0xff; 6]).await?;
+
+        let err = server.read_varint().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the rest of the bytes were not read
+        let mut buf = [0; 2];
+        assert_eq!(server.read(&mut buf).await?, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mc_bytes_length_cap() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        // VarInt for i32::MAX
+        client.write_all(b"\xff\xff\xff\xff\x07").await?;
+
+        assert!(server.read_mc_bytes().await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_mc_bytes_max() -> io::Result<()> {
+        let (mut client, mut server) = io::duplex(64);
+
+        // claims 100 bytes but only sends 3, so reading past the prefix would hit EOF
+        client.write_all(b"\x64abc").await?;
+        drop(client);
+
+        let err = server.read_mc_bytes_max(10).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.into_inner().unwrap().downcast_ref(),
+            Some(MinecraftProtocolError::InvalidLength)
+        ));
+
+        let (mut client, mut server) = io::duplex(64);
+        client.write_all(b"\x03abc").await?;
+        assert_eq!(server.read_mc_string_max(3).await?, "abc");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+//! Resolving the [SRV records](https://wiki.vg/Server_List_Ping#SRV_Records) servers
+//! use to advertise where they can be reached.
+//!
+//! A server at `play.example.com` can have its game port anywhere, on any host, as long
+//! as a `_minecraft._tcp.play.example.com` SRV record points there. The vanilla client
+//! looks this record up before connecting, and falls back to the address as entered when
+//! there is none. [`resolve`] does the same, and
+//! [`status_srv`](crate::status::status_srv) uses it to request the status of a server by
+//! the address players would enter.
+//!
+//! Lookups are made with [`hickory_resolver`], using the system's DNS configuration
+//! (`/etc/resolv.conf` on Unix, and the registry on Windows).
+//!
+//! Requires the `srv` feature.
+
+use hickory_resolver::{
+    error::{ResolveError, ResolveErrorKind},
+    proto::rr::rdata::SRV,
+    TokioAsyncResolver,
+};
+use rand::Rng;
+use std::net::IpAddr;
+use tokio::io;
+
+use crate::errors::Result;
+
+/// Look up the `_minecraft._tcp` SRV record of `domain`.
+///
+/// If the domain has several records, one of those with the lowest priority is picked at
+/// random, favouring those with a higher weight, as described in
+/// [RFC 2782](https://www.rfc-editor.org/rfc/rfc2782).
+///
+/// # Returns
+/// The host and port the record points to, or `None` if there is no record, or `domain` is an
+/// IP address.
+///
+/// # Errors
+/// Returns `Err` if the system's DNS configuration couldn't be read, or the lookup failed
+/// for any other reason than there being no record. A lookup that timed out is reported as
+/// [`ErrorKind::TimedOut`](std::io::ErrorKind::TimedOut).
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::srv::lookup;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     if let Some((host, port)) = lookup("play.example.com").await? {
+///         println!("the server is at {host}:{port}");
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn lookup(domain: &str) -> Result<Option<(String, u16)>> {
+    if is_ip_address(domain) {
+        return Ok(None);
+    }
+
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().map_err(io::Error::from)?;
+
+    Ok(lookup_with(&resolver, domain).await?)
+}
+
+/// Resolve the address of a server like the vanilla client does: if `host` has a
+/// `_minecraft._tcp` SRV record, return the host and port it points to, and otherwise
+/// `host` and `port` themselves.
+///
+/// # Arguments
+/// * `host` - The address of the server, as a player would enter it.
+/// * `port` - The port to use if there is no record, usually
+///   [`DEFAULT_STATUS_PORT`](crate::DEFAULT_STATUS_PORT).
+///
+/// # Errors
+/// Returns `Err` under the same conditions as [`lookup`].
+pub async fn resolve(host: &str, port: u16) -> Result<(String, u16)> {
+    Ok(lookup(host)
+        .await?
+        .unwrap_or_else(|| (host.to_string(), port)))
+}
+
+/// Whether `host` is an IP address, which doesn't have any SRV records.
+fn is_ip_address(host: &str) -> bool {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    host.parse::<IpAddr>().is_ok()
+}
+
+/// Look up the `_minecraft._tcp` SRV record of `domain` with the given resolver.
+async fn lookup_with(
+    resolver: &TokioAsyncResolver,
+    domain: &str,
+) -> io::Result<Option<(String, u16)>> {
+    let records = match resolver
+        .srv_lookup(format!("_minecraft._tcp.{domain}"))
+        .await
+    {
+        Ok(lookup) => lookup.iter().cloned().collect::<Vec<_>>(),
+        Err(err) if is_no_records(&err) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let Some(record) = pick(&records, |total| rand::thread_rng().gen_range(0..=total)) else {
+        return Ok(None);
+    };
+
+    // a target of "." means the service is decidedly not available at this domain
+    if record.target().is_root() {
+        return Ok(None);
+    }
+
+    let target = record.target().to_utf8();
+    Ok(Some((
+        target.trim_end_matches('.').to_string(),
+        record.port(),
+    )))
+}
+
+/// Whether the lookup failed because the domain doesn't exist, or has no SRV records.
+fn is_no_records(err: &ResolveError) -> bool {
+    matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. })
+}
+
+/// Pick the record to connect to, per [RFC 2782](https://www.rfc-editor.org/rfc/rfc2782):
+/// among the records with the lowest priority, each is picked with a probability
+/// proportional to its weight, and records with a weight of 0 only rarely.
+///
+/// `roll` is called with the total weight of those records, and returns a random number
+/// between 0 and it (inclusive).
+fn pick(records: &[SRV], roll: impl FnOnce(u32) -> u32) -> Option<&SRV> {
+    let priority = records.iter().map(SRV::priority).min()?;
+
+    // records with a weight of 0 go first, so they are only picked on a roll of 0
+    let mut candidates: Vec<_> = records
+        .iter()
+        .filter(|record| record.priority() == priority)
+        .collect();
+    candidates.sort_by_key(|record| record.weight() != 0);
+
+    let total = candidates
+        .iter()
+        .map(|record| u32::from(record.weight()))
+        .sum();
+    let roll = roll(total);
+
+    let mut sum = 0;
+    candidates.into_iter().find(|record| {
+        sum += u32::from(record.weight());
+        sum >= roll
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ip_address, lookup_with, pick};
+    use bytes::{BufMut, BytesMut};
+    use hickory_resolver::{
+        config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+        proto::rr::{rdata::SRV, Name},
+        TokioAsyncResolver,
+    };
+    use std::net::SocketAddr;
+    use tokio::{io, net::UdpSocket};
+
+    /// A response to `query` with SRV records for the given priority, weight, port and
+    /// target label (under `example.com`, which is pointed to from the question).
+    fn response(query: &[u8], records: &[(u16, u16, u16, &str)]) -> Vec<u8> {
+        // the header and the question for _minecraft._tcp.example.com, leaving out any
+        // additional records
+        let mut res = BytesMut::from(&query[..12 + 29 + 4]);
+        res[2] = 0x81; // a response, with recursion desired
+        res[3] = 0x80; // and available
+        res[6..8].copy_from_slice(&(records.len() as u16).to_be_bytes());
+        res[10..12].copy_from_slice(&[0, 0]);
+
+        for (priority, weight, port, target) in records {
+            res.put_u16(0xc00c); // the name in the question
+            res.put_u16(33);
+            res.put_u16(1);
+            res.put_u32(300);
+            res.put_u16((6 + 1 + target.len() + 2) as u16);
+            res.put_u16(*priority);
+            res.put_u16(*weight);
+            res.put_u16(*port);
+            res.put_u8(target.len() as u8);
+            res.put_slice(target.as_bytes());
+            res.put_u16(0xc000 | (12 + 1 + 10 + 1 + 4)); // example.com, in the question
+        }
+
+        res.to_vec()
+    }
+
+    /// A resolver that only asks the nameserver at `addr`.
+    fn resolver(addr: SocketAddr) -> TokioAsyncResolver {
+        let nameservers = NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true);
+        let config = ResolverConfig::from_parts(None, vec![], nameservers);
+
+        TokioAsyncResolver::tokio(config, ResolverOpts::default())
+    }
+
+    /// Answer one query with the given records.
+    async fn answer(records: &'static [(u16, u16, u16, &str)]) -> io::Result<SocketAddr> {
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            let (len, client) = server.recv_from(&mut buf).await?;
+            server
+                .send_to(&response(&buf[..len], records), client)
+                .await
+        });
+
+        Ok(addr)
+    }
+
+    fn srv(priority: u16, weight: u16, target: &str) -> SRV {
+        SRV::new(priority, weight, 25565, Name::from_ascii(target).unwrap())
+    }
+
+    #[test]
+    fn test_pick() {
+        assert!(pick(&[], |_| 0).is_none());
+
+        let records = [
+            srv(10, 5, "b."),
+            srv(5, 1, "c."),
+            srv(5, 3, "d."),
+            srv(5, 0, "e."),
+        ];
+        let picked = |roll| {
+            pick(&records, |total| {
+                assert_eq!(total, 4);
+                roll
+            })
+        };
+
+        // a roll of 0 picks the record with a weight of 0, and every other roll one of the
+        // others, in proportion to their weight
+        assert_eq!(picked(0), Some(&records[3]));
+        assert_eq!(picked(1), Some(&records[1]));
+        for roll in 2..=4 {
+            assert_eq!(picked(roll), Some(&records[2]));
+        }
+    }
+
+    #[test]
+    fn test_is_ip_address() {
+        assert!(is_ip_address("127.0.0.1"));
+        assert!(is_ip_address("[::1]"));
+        assert!(is_ip_address("::1"));
+        assert!(!is_ip_address("example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_with() -> io::Result<()> {
+        let addr = answer(&[(0, 0, 25570, "mc")]).await?;
+        let target = lookup_with(&resolver(addr), "example.com").await?;
+        assert_eq!(target, Some(("mc.example.com".to_string(), 25570)));
+
+        // no records, which isn't an error
+        let addr = answer(&[]).await?;
+        assert_eq!(lookup_with(&resolver(addr), "example.com").await?, None);
+
+        Ok(())
+    }
+}
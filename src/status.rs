@@ -1,26 +1,58 @@
 //! Get the status of a server using the [Server List Ping](https://wiki.vg/Server_List_Ping) protocol.
 //! See documentation for [`status`] for more information.
 
+mod config;
 pub mod data;
-mod packet;
+mod forge;
+mod legacy;
+pub(crate) mod packet;
+mod scanner;
+mod state;
+mod version;
+
+pub use config::StatusConfig;
+pub use legacy::{
+    status_legacy, status_legacy_beta, status_legacy_beta_with_timeout, status_legacy_with_timeout,
+};
+pub use scanner::{status_many, Scanner, ScannerConfig};
+pub use state::{server_state, ServerState};
+pub use version::protocol_to_version;
 
 use crate::{
-    errors::MinecraftProtocolError,
-    socket::{ReadWriteMinecraftString, ReadWriteVarInt},
-    varint::VarInt,
+    buf::{read_mc_string_buf, read_varint_buf},
+    errors::{timeout_err, MinecraftProtocolError, Result},
+    login::LOGIN_PROTOCOL_VERSION,
+    socket::{check_field_len, connect_timeout, host_port, is_dropped, ReadWriteVarInt},
+    DEFAULT_STATUS_PORT,
+};
+use bytes::{Bytes, BytesMut};
+use rand::random;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
 };
 use tokio::{
-    io::{self, AsyncWriteExt, Interest},
-    net::TcpStream,
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::ToSocketAddrs,
+    time::{sleep, timeout},
 };
 
 use self::{
-    data::StatusResponse,
+    data::{DetailedStatus, LatencyStats, StatusResponse},
     packet::{Packet, PacketId},
 };
 
+pub use crate::latency::SAMPLE_INTERVAL;
+
 /// Ping the server for information following the [Server List Ping](https://wiki.vg/Server_List_Ping) protocol.
 ///
+/// This waits as long as the OS allows for the connection to be established, and then
+/// for the server to respond. Use [`status_with_timeout`] to bound this, or a [`Scanner`]
+/// when scanning many servers.
+///
+/// The handshake advertises `-1` as the protocol version. Use [`status_with_config`]
+/// with a [`protocol_version`](StatusConfig::protocol_version) to send another one.
+///
 /// # Arguments
 /// * `host` - A string slice that holds the hostname of the server to connect to.
 /// * `port` - The port to connect to on that server.
@@ -29,7 +61,7 @@ use self::{
 /// Returns `Err` if there was a network issue or the server sent invalid data.
 ///
 /// # Examples
-/// ```
+/// ```no_run
 /// use mc_query::status;
 /// use tokio::io::Result;
 ///
@@ -41,64 +73,816 @@ use self::{
 ///     Ok(())
 /// }
 /// ```
-pub async fn status(host: &str, port: u16) -> io::Result<StatusResponse> {
-    let mut socket = TcpStream::connect(format!("{host}:{port}")).await?;
+pub async fn status(host: &str, port: u16) -> Result<StatusResponse> {
+    status_with_config(host, port, &StatusConfig::default()).await
+}
+
+/// Similar to [`status`], but with the given [`StatusConfig`].
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `config` - The configuration to use.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue, the server sent invalid data, the
+/// response exceeded [`max_response_bytes`](StatusConfig::max_response_bytes), or the
+/// connection was not established within the [`connect_timeout`](StatusConfig::connect_timeout).
+pub async fn status_with_config(
+    host: &str,
+    port: u16,
+    config: &StatusConfig,
+) -> Result<StatusResponse> {
+    let socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
+
+    match status_on(socket, host, port, config, config.protocol_version).await {
+        Err(err) if config.retry_with_current_protocol && is_dropped(err.kind()) => {
+            let socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
+            Ok(status_on(socket, host, port, config, LOGIN_PROTOCOL_VERSION).await?)
+        }
+        res => Ok(res?),
+    }
+}
+
+/// Similar to [`status`], but connects to the [default port](crate::DEFAULT_STATUS_PORT).
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+pub async fn status_default(host: &str) -> Result<StatusResponse> {
+    status(host, DEFAULT_STATUS_PORT).await
+}
+
+/// Similar to [`status`], but takes the address of the server as a player would enter it,
+/// resolving its [SRV record](crate::srv) like the vanilla client does.
+///
+/// If `host` has a `_minecraft._tcp` SRV record, the status is requested from the host and
+/// port it points to, and otherwise from `host` on the [default port](crate::DEFAULT_STATUS_PORT).
+///
+/// Requires the `srv` feature.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the address of the server, without a port.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_srv;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let data = status_srv("play.example.com").await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "srv")]
+pub async fn status_srv(host: &str) -> Result<StatusResponse> {
+    let (host, port) = crate::srv::resolve(host, DEFAULT_STATUS_PORT).await?;
+
+    status(&host, port).await
+}
+
+/// Similar to [`status`], but sends the given [PROXY protocol](crate::proxy) header
+/// before the handshake. This is needed for servers that only accept connections from
+/// a proxy, such as servers behind TCPShield.
+///
+/// Requires the `proxy-protocol` feature.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `header` - The [`ProxyHeader`](crate::proxy::ProxyHeader) to send.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+#[cfg(feature = "proxy-protocol")]
+pub async fn status_with_proxy_header(
+    host: &str,
+    port: u16,
+    header: &crate::proxy::ProxyHeader,
+) -> Result<StatusResponse> {
+    let config = StatusConfig::default();
+    let mut socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
+    socket.write_all(&header.bytes()).await?;
+
+    Ok(status_on(socket, host, port, &config, config.protocol_version).await?)
+}
+
+/// Ping the server at `connect_addr`, but tell it the client is connecting to `vhost:vport`.
+///
+/// Servers see the hostname and port that the client connected to in the handshake.
+/// Proxies such as BungeeCord and Velocity use these to pick which backend server
+/// to route a connection to (forced hosts), and some servers serve a different
+/// status to each virtual host. This allows checking what a player connecting
+/// through a given domain would see, while connecting directly to a specific
+/// proxy or backend, without relying on DNS.
+///
+/// # Arguments
+/// * `connect_addr` - The address to connect to.
+/// * `vhost` - The hostname to send in the handshake.
+/// * `vport` - The port to send in the handshake.
+/// * `dur` - How long to wait for connecting and for the response, in total.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue, the server sent invalid data, or the
+/// status was not received within `dur`.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_vhost;
+/// use std::time::Duration;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let proxy = "127.0.0.1:25577".parse().unwrap();
+///     let data = status_vhost(proxy, "lobby.example.com", 25565, Duration::from_secs(5)).await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_vhost(
+    connect_addr: SocketAddr,
+    vhost: &str,
+    vport: u16,
+    dur: Duration,
+) -> Result<StatusResponse> {
+    let fut = async {
+        let config = StatusConfig::default();
+        let socket = connect_timeout(connect_addr, config.connect_timeout).await?;
+        status_on(socket, vhost, vport, &config, config.protocol_version).await
+    };
+
+    Ok(timeout(dur, fut).await.unwrap_or(timeout_err())?)
+}
+
+/// Similar to [`status`], but connects to an address that was already resolved, or
+/// anything else that can be turned into one, such as a [`SocketAddr`] or an
+/// `(IpAddr, u16)` tuple.
+///
+/// The handshake sends the IP address and port that the connection was made to as the
+/// server address, since there is no hostname to send. If the server needs a hostname,
+/// for example because it is behind a proxy that routes by it, use [`status_vhost`].
+///
+/// # Arguments
+/// * `addr` - The address of the server. If it resolves to several addresses, they are
+///   tried in order until a connection succeeds.
+/// * `dur` - How long to wait for connecting and for the response, in total.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue, the server sent invalid data, or the
+/// status was not received within `dur`.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_addr;
+/// use std::{net::Ipv6Addr, time::Duration};
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let data = status_addr((Ipv6Addr::LOCALHOST, 25565), Duration::from_secs(5)).await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_addr(addr: impl ToSocketAddrs, dur: Duration) -> Result<StatusResponse> {
+    let fut = async {
+        let config = StatusConfig::default();
+        let socket = connect_timeout(addr, config.connect_timeout).await?;
+        let peer = socket.peer_addr()?;
+
+        let host = peer.ip().to_string();
+        status_on(socket, &host, peer.port(), &config, config.protocol_version).await
+    };
+
+    Ok(timeout(dur, fut).await.unwrap_or(timeout_err())?)
+}
+
+/// Similar to [`status`], but also returns details about how the response was framed,
+/// to help debug servers that send malformed responses.
+/// See [`DetailedStatus`] for more information.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+pub async fn status_detailed(host: &str, port: u16) -> Result<DetailedStatus> {
+    let config = StatusConfig::default();
+    let mut socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
 
-    socket
-        .ready(Interest::READABLE | Interest::WRITABLE)
-        .await?;
+    let (packet_length, data) =
+        request_status(&mut socket, host, port, &config, config.protocol_version).await?;
+    socket.shutdown().await?;
 
+    Ok(DetailedStatus {
+        response: parse_status(&data)?,
+        packet_length,
+        json_length: data.len(),
+    })
+}
+
+/// Similar to [`status`], but also measures the server's latency, by sending a
+/// [Ping](https://wiki.vg/Server_List_Ping#Ping_Request) packet after the status
+/// response has been received, and timing how long it takes for the server to
+/// echo it back.
+///
+/// Only the round trip of the Ping/Pong exchange is measured, so the time spent
+/// connecting and generating the status response is not included.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data,
+/// including an invalid Pong packet.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_with_latency_with_timeout;
+/// use std::time::Duration;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let (data, latency) =
+///         status_with_latency_with_timeout("localhost", 25565, Duration::from_secs(5)).await?;
+///     println!("{} players online, {latency:?} away", data.players.online);
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_with_latency(host: &str, port: u16) -> Result<(StatusResponse, Duration)> {
+    let config = StatusConfig::default();
+    let mut socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
+
+    let (_, data) =
+        request_status(&mut socket, host, port, &config, config.protocol_version).await?;
+    let response = parse_status(&data)?;
+    let latency = ping_on(&mut socket).await?;
+    socket.shutdown().await?;
+
+    Ok((response, latency))
+}
+
+/// Measure the server's latency, like [`status_with_latency`], discarding the status.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+///
+/// # Errors
+/// Returns `Err` for the same reasons as [`status_with_latency`].
+pub async fn ping(host: &str, port: u16) -> Result<Duration> {
+    let (_, latency) = status_with_latency(host, port).await?;
+
+    Ok(latency)
+}
+
+/// Perform the [Server List Ping](https://wiki.vg/Server_List_Ping) over a connection that
+/// was established by the caller, like [`status`] does after connecting.
+///
+/// This allows connecting in ways [`status`] doesn't cover, for example through a SOCKS
+/// proxy, or with socket options set before connecting. Nothing must have been sent or
+/// received over the connection yet, apart from anything a proxy needs to set it up.
+/// The connection is shut down once the status has been received.
+///
+/// # Arguments
+/// * `stream` - A connection to the server.
+/// * `host` - The hostname to send in the handshake, usually the one that was connected to.
+/// * `port` - The port to send in the handshake, usually the one that was connected to.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_on_stream;
+/// use tokio::{io::Result, net::TcpStream};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let stream = TcpStream::connect("127.0.0.1:25565").await?;
+///     stream.set_nodelay(true)?;
+///
+///     let data = status_on_stream(stream, "localhost", 25565).await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_on_stream(
+    stream: impl AsyncRead + AsyncWrite + Unpin + Send,
+    host: &str,
+    port: u16,
+) -> Result<StatusResponse> {
+    let config = StatusConfig::default();
+
+    Ok(status_on(stream, host, port, &config, config.protocol_version).await?)
+}
+
+/// Perform the Server List Ping on an already established connection,
+/// sending the given protocol version in the handshake.
+async fn status_on(
+    mut socket: impl AsyncRead + AsyncWrite + Unpin + Send,
+    host: &str,
+    port: u16,
+    config: &StatusConfig,
+    protocol: i32,
+) -> io::Result<StatusResponse> {
+    let (_, data) = request_status(&mut socket, host, port, config, protocol).await?;
+    socket.shutdown().await?;
+
+    parse_status(&data)
+}
+
+/// Parse the JSON status response, tolerating a leading byte order mark and
+/// surrounding whitespace, which a few servers send.
+fn parse_status(data: &str) -> io::Result<StatusResponse> {
+    let data = data.trim().trim_start_matches('\u{feff}').trim_start();
+
+    serde_json::from_str::<StatusResponse>(data)
+        .map_err(|_| MinecraftProtocolError::InvalidStatusResponse.into())
+}
+
+/// Send the handshake and status request packets, and read the raw JSON status response,
+/// along with the length of the packet it was sent in.
+///
+/// By convention, the protocol version is -1 when the client doesn't know which
+/// version the server is running yet.
+async fn request_status(
+    socket: &mut (impl AsyncRead + AsyncWrite + Unpin + Send),
+    host: &str,
+    port: u16,
+    config: &StatusConfig,
+    protocol: i32,
+) -> io::Result<(usize, String)> {
     // handshake packet
     // https://wiki.vg/Server_List_Ping#Handshake
     let handshake = Packet::builder(PacketId::Handshake)
-        .add_varint(&VarInt::from(-1))
+        .add_varint(protocol)
         .add_string(host)
         .add_u16(port)
-        .add_varint(&VarInt::from(PacketId::Status))
+        .add_varint(PacketId::Status)
         .build();
 
-    socket.write_all(&handshake.bytes()).await?;
-
-    // status request packet
+    // status request packet, which has no fields
     // https://wiki.vg/Server_List_Ping#Status_Request
-    let status_request = Packet::builder(PacketId::Handshake).build();
-    socket.write_all(&status_request.bytes()).await?;
+    let status_request = Packet::builder(PacketId::StatusRequest).build();
+
+    // send both packets in a single write, so they (usually) go out in a single segment
+    // instead of the request waiting for the handshake to be acknowledged
+    let mut request = BytesMut::from(&handshake.bytes()[..]);
+    request.extend_from_slice(&status_request.bytes());
+    socket.write_all(&request).await?;
 
     // listen to status response
     // https://wiki.vg/Server_List_Ping#Status_Response
+    // the packet is read in full before it is parsed, so checking its length up front
+    // bounds the whole read, no matter what the fields inside claim
+    let len = check_field_len(socket.read_varint().await?)?;
+    if len > config.max_response_bytes {
+        return Err(MinecraftProtocolError::ResponseTooLarge.into());
+    }
+
+    let mut packet = vec![0; len];
+    socket.read_exact(&mut packet).await?;
+    let mut packet = Bytes::from(packet);
+
+    let id = read_varint_buf(&mut packet)?;
+    if id != 0 {
+        return Err(MinecraftProtocolError::InvalidStatusResponse.into());
+    }
+
+    Ok((len, read_mc_string_buf(&mut packet)?))
+}
+
+/// Send a ping packet, and measure the time it takes for the server to respond with a pong.
+/// Must be called after the status response has been read.
+async fn ping_on(
+    socket: &mut (impl AsyncRead + AsyncWrite + Unpin + Send),
+) -> io::Result<Duration> {
+    let payload = random::<i64>();
+
+    // ping request packet
+    // https://wiki.vg/Server_List_Ping#Ping_Request
+    let ping = Packet::builder(PacketId::Ping).add_i64(payload).build();
+
+    let start = Instant::now();
+    socket.write_all(&ping.bytes()).await?;
+
+    // pong response packet
+    // https://wiki.vg/Server_List_Ping#Pong_Response
     let _len = socket.read_varint().await?;
     let id = socket.read_varint().await?;
+    let echoed = socket.read_i64().await?;
+    let latency = start.elapsed();
 
-    if id != 0 {
+    if id != 1 || echoed != payload {
         return Err(MinecraftProtocolError::InvalidStatusResponse.into());
     }
 
-    let data = socket.read_mc_string().await?;
-    socket.shutdown().await?;
+    Ok(latency)
+}
 
-    serde_json::from_str::<StatusResponse>(&data)
-        .map_err(|_| MinecraftProtocolError::InvalidStatusResponse.into())
+/// Measure the server's latency by pinging it `samples` times, one after the other.
+///
+/// Each sample opens a new connection and performs a full [Server List Ping](https://wiki.vg/Server_List_Ping),
+/// but only the round trip of the final Ping/Pong exchange is measured, so the time
+/// spent connecting and generating the status response is not included.
+/// Samples are spaced [`SAMPLE_INTERVAL`] apart to avoid measuring a single burst.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `samples` - How many times to ping the server.
+/// * `dur` - How long to wait for each individual sample to complete.
+///
+/// # Errors
+/// Returns `Err` if `samples` is 0, or if any sample fails for any reason [`status`]
+/// does, or is not completed within `dur`.
+pub async fn status_latency_samples(
+    host: &str,
+    port: u16,
+    samples: usize,
+    dur: Duration,
+) -> Result<LatencyStats> {
+    let config = StatusConfig::default();
+    let mut latencies = Vec::with_capacity(samples);
+
+    for i in 0..samples {
+        if i != 0 {
+            sleep(SAMPLE_INTERVAL).await;
+        }
+
+        let sample = async {
+            let mut socket = connect_timeout(host_port(host, port), config.connect_timeout).await?;
+            request_status(&mut socket, host, port, &config, config.protocol_version).await?;
+            let latency = ping_on(&mut socket).await?;
+            socket.shutdown().await?;
+
+            Ok::<_, io::Error>(latency)
+        };
+
+        latencies.push(timeout(dur, sample).await.unwrap_or(timeout_err())?);
+    }
+
+    LatencyStats::from_samples(latencies).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "at least one sample is required",
+        )
+        .into()
+    })
 }
 
 create_timeout!(status, StatusResponse);
+create_timeout!(status_detailed, DetailedStatus);
+create_timeout!(status_with_latency, (StatusResponse, Duration));
+create_timeout!(ping, Duration);
 
 #[cfg(test)]
 mod tests {
-    use super::status;
-    use tokio::io::Result;
+    use super::{
+        packet::{Packet, PacketId},
+        parse_status, ping_with_timeout, status, status_addr, status_detailed, status_on_stream,
+        status_vhost, status_with_config, status_with_latency_with_timeout, status_with_timeout,
+        StatusConfig,
+    };
+    use crate::{
+        buf::{read_mc_string_buf, read_varint_buf},
+        login::LOGIN_PROTOCOL_VERSION,
+        mock::MockServer,
+        socket::ReadWriteVarInt,
+    };
+    use bytes::{Buf, Bytes};
+    use std::time::Duration;
+    use tokio::{
+        io::{duplex, AsyncReadExt, AsyncWriteExt, ErrorKind, Result},
+        net::TcpListener,
+    };
+
+    const STATUS_JSON: &str =
+        r#"{"version":{"name":"1.20.1","protocol":763},"players":{"max":20,"online":0}}"#;
+
+    /// Accept status requests, and answer the first one with a protocol version of at least
+    /// `min_protocol` with `json`, closing the connection of any others. Returns the protocol
+    /// version, hostname, and port that the client sent in the answered handshake.
+    async fn serve_status(
+        listener: TcpListener,
+        json: &str,
+        min_protocol: i32,
+    ) -> Result<(i32, String, u16)> {
+        loop {
+            let (mut socket, _) = listener.accept().await?;
+
+            let len = socket.read_varint().await?;
+            let mut handshake = vec![0; usize::try_from(len).unwrap()];
+            socket.read_exact(&mut handshake).await?;
+            let mut handshake = Bytes::from(handshake);
+            read_varint_buf(&mut handshake)?; // packet id
+            let protocol = read_varint_buf(&mut handshake)?;
+            let host = read_mc_string_buf(&mut handshake)?;
+            let port = handshake.get_u16();
+
+            let mut request = [0; 2];
+            socket.read_exact(&mut request).await?;
+
+            if protocol < min_protocol {
+                continue;
+            }
+
+            let response = Packet::builder(PacketId::StatusResponse)
+                .add_string(json)
+                .build();
+            socket.write_all(&response.bytes()).await?;
+
+            // echo a ping as the pong, if the client sends one
+            let mut ping = [0; 10];
+            if socket.read_exact(&mut ping).await.is_ok() {
+                socket.write_all(&ping).await?;
+            }
+
+            return Ok((protocol, host, port));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_fds() -> usize {
+        std::fs::read_dir("/proc/self/fd").unwrap().count()
+    }
+
+    /// Other tests open and close sockets concurrently, so the open file descriptors
+    /// can only be counted reliably in a process of its own, running just
+    /// [`timeout_no_fd_leak`].
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_timeout_no_fd_leak() -> std::io::Result<()> {
+        let status = std::process::Command::new(std::env::current_exe()?)
+            .args(["status::tests::timeout_no_fd_leak", "--exact", "--ignored"])
+            .status()?;
+        assert!(status.success());
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore = "run in its own process by test_timeout_no_fd_leak"]
+    async fn timeout_no_fd_leak() -> Result<()> {
+        // connections to this listener are established by the OS, but are never
+        // accepted, so the server never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let before = open_fds();
+        for _ in 0..100 {
+            let result = status_with_timeout("127.0.0.1", port, Duration::from_millis(5)).await;
+            assert!(result.unwrap_err().is_timeout());
+        }
+        assert_eq!(open_fds(), before);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_response_bytes() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 256];
+            let _ = socket.read(&mut buf).await;
+
+            // announce a 2 MiB packet, then keep streaming
+            let _ = socket.write_all(b"\xff\xff\x7f\x00\xfa\xff\x7f").await;
+            let chunk = [b'a'; 4096];
+            while socket.write_all(&chunk).await.is_ok() {}
+        });
+
+        let config = StatusConfig {
+            max_response_bytes: 1024,
+            ..StatusConfig::default()
+        };
+        let err = status_with_config("127.0.0.1", port, &config)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "response too large");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_vhost() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(serve_status(listener, STATUS_JSON, i32::MIN));
+
+        let data = status_vhost(addr, "lobby.example.com", 25565, Duration::from_secs(5)).await?;
+        assert_eq!(data.version.protocol, 763);
+
+        let (_, host, port) = server.await.unwrap()?;
+        assert_eq!(host, "lobby.example.com");
+        assert_eq!(port, 25565);
+        assert_ne!(addr.port(), port);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_addr() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(serve_status(listener, STATUS_JSON, i32::MIN));
+
+        let data = status_addr(addr, Duration::from_secs(5)).await?;
+        assert_eq!(data.version.protocol, 763);
+
+        let (_, host, port) = server.await.unwrap()?;
+        assert_eq!(host, "127.0.0.1");
+        assert_eq!(port, addr.port());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_current_protocol() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let server = tokio::spawn(serve_status(listener, STATUS_JSON, 0));
+
+        // without retrying, the dropped connection is an error
+        let err = status_with_timeout("127.0.0.1", port, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let config = StatusConfig {
+            retry_with_current_protocol: true,
+            ..StatusConfig::default()
+        };
+        let data = status_with_config("127.0.0.1", port, &config).await?;
+        assert_eq!(data.version.protocol, 763);
+
+        let (protocol, _, _) = server.await.unwrap()?;
+        assert_eq!(protocol, LOGIN_PROTOCOL_VERSION);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let server = tokio::spawn(serve_status(listener, STATUS_JSON, i32::MIN));
+
+        let config = StatusConfig {
+            protocol_version: 47,
+            ..StatusConfig::default()
+        };
+        status_with_config("127.0.0.1", port, &config).await?;
+
+        let (protocol, _, _) = server.await.unwrap()?;
+        assert_eq!(protocol, 47);
+
+        Ok(())
+    }
 
     #[tokio::test]
-    async fn test_hypixel_status() -> Result<()> {
-        let data = status("mc.hypixel.net", 25565).await?;
-        println!("{data:#?}");
+    async fn test_status_detailed() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(serve_status(listener, STATUS_JSON, i32::MIN));
+
+        let detailed = status_detailed("127.0.0.1", port).await?;
+        assert_eq!(detailed.json_length, STATUS_JSON.len());
+        assert_eq!(detailed.packet_length, STATUS_JSON.len() + 2);
+        assert!(detailed.is_well_framed());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_with_latency() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(serve_status(listener, STATUS_JSON, i32::MIN));
+
+        let (data, latency) =
+            status_with_latency_with_timeout("127.0.0.1", port, Duration::from_secs(5)).await?;
+        assert_eq!(data.version.protocol, 763);
+        assert!(latency < Duration::from_secs(5));
+
+        // a server that doesn't answer pings
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let response = Packet::builder(PacketId::StatusResponse)
+                .add_string(STATUS_JSON)
+                .build();
+            socket.write_all(&response.bytes()).await?;
+            let mut buf = Vec::new();
+            socket.read_to_end(&mut buf).await
+        });
+
+        let err = ping_with_timeout("127.0.0.1", port, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
 
         Ok(())
     }
 
+    #[test]
+    fn test_parse_status_bom() {
+        let json = STATUS_JSON;
+
+        let data = parse_status(&format!("\u{feff}{json}")).unwrap();
+        assert_eq!(data.version.protocol, 763);
+        assert!(parse_status(&format!(" \u{feff}\n{json}\r\n")).is_ok());
+        assert!(parse_status("\u{feff}").is_err());
+    }
+
+    /// Request the status over an in-memory connection, whose server side sends `response`
+    /// and then closes its end.
+    async fn status_from(response: &[u8]) -> crate::Result<super::StatusResponse> {
+        let (client, mut server) = duplex(4096);
+        server.write_all(response).await?;
+        server.shutdown().await?;
+
+        let res = status_on_stream(client, "localhost", 25565).await;
+        drop(server);
+        res
+    }
+
+    #[tokio::test]
+    async fn test_status_on_stream() -> Result<()> {
+        let response = Packet::builder(PacketId::StatusResponse)
+            .add_string(STATUS_JSON)
+            .build()
+            .bytes();
+        let data = status_from(&response).await?;
+        assert_eq!(data.version.protocol, 763);
+
+        // the client sends the handshake and the status request
+        let (client, mut server) = duplex(4096);
+        server.write_all(&response).await?;
+        status_on_stream(client, "localhost", 25565).await?;
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await?;
+        assert_eq!(request[1..3], [0x00, 0xff]);
+        assert!(request.ends_with(&[0x63, 0xdd, 0x01, 0x01, 0x00]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_on_stream_invalid() {
+        let malformed = Packet::builder(PacketId::StatusResponse)
+            .add_string(r#"{"version":"#)
+            .build()
+            .bytes();
+        let err = status_from(&malformed).await.unwrap_err();
+        assert_eq!(err.to_string(), "invalid status response");
+
+        let wrong_id = Packet::builder(PacketId::Ping)
+            .add_string(STATUS_JSON)
+            .build()
+            .bytes();
+        let err = status_from(&wrong_id).await.unwrap_err();
+        assert_eq!(err.to_string(), "invalid status response");
+
+        let response = Packet::builder(PacketId::StatusResponse)
+            .add_string(STATUS_JSON)
+            .build()
+            .bytes();
+        let err = status_from(&response[..response.len() - 1])
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
     #[tokio::test]
     async fn test_local_status() -> Result<()> {
-        let data = status("localhost", 25565).await?;
-        println!("{data:#?}");
+        let server = MockServer::start().await?;
+        let data = status("127.0.0.1", server.status_port).await?;
+        assert_eq!(data.version.protocol, 763);
+        assert_eq!(data.players.online, 1);
 
         Ok(())
     }
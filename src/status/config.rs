@@ -0,0 +1,83 @@
+//! Configuration for the [Server List Ping](https://wiki.vg/Server_List_Ping).
+
+use std::time::Duration;
+
+/// Configuration for [`status_with_config`](super::status_with_config).
+///
+/// The [`Default`] configuration matches the behaviour of [`status`](super::status).
+///
+/// # Examples
+///
+/// ```no_run
+/// use mc_query::status::{status_with_config, StatusConfig};
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let config = StatusConfig {
+///         max_response_bytes: 64 * 1024,
+///         ..StatusConfig::default()
+///     };
+///
+///     let data = status_with_config("localhost", 25565, &config).await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatusConfig {
+    /// The maximum number of bytes the whole status response packet may take up,
+    /// not counting its length prefix. Responses announcing a larger packet are rejected
+    /// before any of it is read, which protects scanners against servers that send
+    /// endless or oversized responses.
+    ///
+    /// This is applied on top of the limit on the length of individual strings.
+    ///
+    /// Defaults to 1 MiB, which leaves plenty of room for large modpack responses
+    /// with a favicon.
+    pub max_response_bytes: usize,
+
+    /// Whether to request the status again if the server closes the connection right
+    /// after the status request, this time sending a real protocol version
+    /// ([`LOGIN_PROTOCOL_VERSION`](crate::login::LOGIN_PROTOCOL_VERSION)) in the handshake.
+    ///
+    /// Clients send `-1` as the protocol version when they don't know which version the
+    /// server runs, which some anti-bot plugins use to reject status requests from
+    /// anything that isn't a real client. As a closed connection usually means that
+    /// something is actually wrong with the server, this would only hide real failures
+    /// for most servers, so it is off by default.
+    pub retry_with_current_protocol: bool,
+
+    /// The protocol version to send in the handshake.
+    ///
+    /// Some servers respond differently depending on the version of the client, for
+    /// example with a different MOTD, or by marking the server as incompatible. See
+    /// [the wiki.vg page](https://wiki.vg/Protocol_version_numbers) for the numbers
+    /// of each version.
+    ///
+    /// Defaults to `-1`, which clients send when they don't know which version the
+    /// server runs.
+    pub protocol_version: i32,
+
+    /// How long to wait for the connection to be established, separately from the
+    /// timeout of the request as a whole.
+    ///
+    /// Connecting to a host that silently drops packets takes as long as the OS is
+    /// willing to wait, often well over a minute, so this lets unreachable servers fail
+    /// fast while still giving reachable ones plenty of time to respond.
+    ///
+    /// Defaults to `None`, which leaves it to the OS.
+    pub connect_timeout: Option<Duration>,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            max_response_bytes: 1024 * 1024,
+            retry_with_current_protocol: false,
+            protocol_version: -1,
+            connect_timeout: None,
+        }
+    }
+}
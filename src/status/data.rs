@@ -1,11 +1,38 @@
 //! Implementation of the [Server List Ping](https://wiki.vg/Server_List_Ping) protocol
 
+use super::{
+    forge,
+    packet::{Packet, PacketId},
+    protocol_to_version,
+};
+#[cfg(feature = "query")]
+use crate::query::FullStatResponse;
+use crate::{
+    chat::{legacy_color_name, parse_color, strip_formatting, SECTION_SIGN},
+    errors::{self, MinecraftProtocolError},
+    varint::varint_len,
+};
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::io;
+
+pub use super::forge::IGNORE_SERVER_ONLY;
+pub use crate::latency::LatencyStats;
+
+/// The number of characters that fit on one line of the MOTD in the vanilla server list.
+///
+/// The vanilla client actually wraps the MOTD by rendered pixel width (270 pixels),
+/// which is about 45 characters of the default font.
+pub const MOTD_LINE_WIDTH: usize = 45;
+
+/// The prefix of the data URI the favicon is sent as.
+const FAVICON_PREFIX: &str = "data:image/png;base64,";
 
 /// Response from the server with status information.
 /// Represents [this JSON object](https://wiki.vg/Server_List_Ping#Status_Response)
 /// to be serialized and deserialized.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StatusResponse {
     /// Information about the game and protocol version.
     /// See [Version] for more information.
@@ -30,12 +57,652 @@ pub struct StatusResponse {
     /// Only returned for servers post 1.19.1
     #[serde(rename = "enforcesSecureChat")]
     pub enforces_secure_chat: Option<bool>,
+
+    /// Does the server prevent chat reports?
+    /// Only returned by servers running the No Chat Reports mod.
+    #[serde(rename = "preventsChatReports", default)]
+    pub prevents_chat_reports: Option<bool>,
+
+    /// The channels and mods of a Forge server.
+    /// Only returned by Forge servers for 1.13 and later.
+    ///
+    /// This is `None` if the data is missing or not in the expected format.
+    #[serde(
+        rename = "forgeData",
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub forge_data: Option<ForgeData>,
+
+    /// The mods of a Forge server for 1.12 and earlier.
+    /// See [`forge_mods`](Self::forge_mods) to get the mods of any Forge server.
+    ///
+    /// This is `None` if the data is missing or not in the expected format.
+    #[serde(
+        rename = "modinfo",
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mod_info: Option<ModInfo>,
+
+    /// The modpack the server runs, for launchers to install before joining.
+    /// Only returned by servers with a mod that advertises it, such as
+    /// `BetterCompatibilityChecker`.
+    ///
+    /// This is `None` if the data is missing or not in the expected format.
+    #[serde(
+        rename = "modpackData",
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modpack_data: Option<ModpackData>,
+
+    /// Any other fields in the response, which are not part of the vanilla protocol.
+    /// Mods, plugins, and proxies sometimes advertise extra information here.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl StatusResponse {
+    /// Get the chat-related capabilities the server advertises.
+    /// See [`Capabilities`] for more information.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            previews_chat: self.previews_chat.unwrap_or_default(),
+            enforces_secure_chat: self.enforces_secure_chat.unwrap_or_default(),
+            prevents_chat_reports: self.prevents_chat_reports.unwrap_or_default(),
+        }
+    }
+
+    /// Encode this status as the [Status Response](https://wiki.vg/Server_List_Ping#Status_Response)
+    /// packet a server sends, prefixed with its length.
+    ///
+    /// This is useful for writing mock servers for tests, or proxies that rewrite the
+    /// status of the servers behind them.
+    ///
+    /// # Panics
+    /// Panics if the response can't be serialized to JSON, which can't happen for
+    /// responses deserialized from JSON.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::status::data::StatusResponse;
+    ///
+    /// let status: StatusResponse = serde_json::from_str(
+    ///     r#"{"version": {"name": "1.20.1", "protocol": 763}, "players": {"max": 20, "online": 0}}"#,
+    /// ).unwrap();
+    ///
+    /// let packet = status.to_status_packet();
+    /// // the JSON follows the length of the packet, its ID and the length of the JSON
+    /// let json = serde_json::to_string(&status).unwrap();
+    /// assert!(packet.ends_with(json.as_bytes()));
+    /// ```
+    #[must_use]
+    pub fn to_status_packet(&self) -> Bytes {
+        let json = serde_json::to_string(self).expect("status responses are valid JSON");
+
+        Packet::builder(PacketId::StatusResponse)
+            .add_string(&json)
+            .build()
+            .bytes()
+    }
+
+    /// The mods installed on a Forge server, from [`forge_data`](Self::forge_data) for
+    /// servers for 1.13 and later, or [`mod_info`](Self::mod_info) for older ones.
+    ///
+    /// Returns an empty slice for servers that don't report any mods.
+    #[must_use]
+    pub fn forge_mods(&self) -> &[ForgeMod] {
+        if let Some(forge) = &self.forge_data {
+            &forge.mods
+        } else if let Some(info) = &self.mod_info {
+            &info.mod_list
+        } else {
+            &[]
+        }
+    }
+
+    /// The base64 encoded PNG image of the server's favicon, without the
+    /// `data:image/png;base64,` prefix it is sent with.
+    ///
+    /// Returns `None` if the server has no favicon, or if the favicon doesn't start with
+    /// that prefix.
+    #[must_use]
+    pub fn favicon_base64(&self) -> Option<&str> {
+        self.favicon.as_deref()?.strip_prefix(FAVICON_PREFIX)
+    }
+
+    /// The PNG image of the server's favicon, decoded from the base64 it is sent as.
+    ///
+    /// Returns `None` if the server has no favicon.
+    ///
+    /// # Errors
+    /// Returns [`MinecraftProtocolError::InvalidFavicon`] if the favicon doesn't start with
+    /// the `data:image/png;base64,` prefix, or isn't valid base64.
+    pub fn favicon_png(&self) -> Option<errors::Result<Vec<u8>>> {
+        let favicon = self.favicon.as_deref()?;
+
+        Some(
+            favicon
+                .strip_prefix(FAVICON_PREFIX)
+                .and_then(decode_base64)
+                .ok_or_else(|| MinecraftProtocolError::InvalidFavicon.into()),
+        )
+    }
+
+    /// Whether the server (probably) accepts Bedrock Edition clients through
+    /// [GeyserMC](https://geysermc.org).
+    ///
+    /// This is a heuristic: it looks for Geyser or Floodgate markers in the fields not
+    /// modeled by this struct ([`extra`](Self::extra)) and in the version name, which some
+    /// Geyser setups advertise. Servers can run Geyser without advertising it at all.
+    #[must_use]
+    pub fn accepts_bedrock(&self) -> bool {
+        let is_marker = |s: &str| {
+            let s = s.to_ascii_lowercase();
+            s.contains("geyser") || s.contains("floodgate")
+        };
+
+        is_marker(&self.version.name) || self.extra.keys().any(|key| is_marker(key))
+    }
+
+    /// The player info forwarding mode the server advertises, for example `modern` or
+    /// `legacy`, read from the `ipForwarding` field in [`extra`](Self::extra).
+    ///
+    /// This is not part of the vanilla protocol: a few proxy backends advertise it
+    /// through plugins, and what the values mean depends on the plugin. Most servers
+    /// behind a proxy don't advertise it at all.
+    #[must_use]
+    pub fn ip_forwarding(&self) -> Option<&str> {
+        self.extra.get("ipForwarding")?.as_str()
+    }
+
+    /// The fields in [`extra`](Self::extra) whose names hint at the server being a
+    /// backend behind a proxy, such as `ipForwarding`, or ones mentioning Velocity,
+    /// BungeeCord or Waterfall.
+    ///
+    /// Like [`ip_forwarding`](Self::ip_forwarding), these markers are non-standard and
+    /// specific to the plugins that add them, so an empty list doesn't mean the server
+    /// is not behind a proxy.
+    #[must_use]
+    pub fn proxy_markers(&self) -> Vec<&str> {
+        let mut markers: Vec<_> = self
+            .extra
+            .keys()
+            .map(String::as_str)
+            .filter(|key| {
+                let key = key.to_ascii_lowercase();
+                ["forwarding", "velocity", "bungee", "waterfall"]
+                    .iter()
+                    .any(|marker| key.contains(marker))
+            })
+            .collect();
+        markers.sort_unstable();
+
+        markers
+    }
+
+    /// Compare this (older) response to a newer one from the same server,
+    /// and report what changed between the two.
+    ///
+    /// Note that servers only include a sample of online players in their response,
+    /// so [`StatusDiff::joined`] and [`StatusDiff::left`] only reflect changes to that
+    /// sample. Use [`StatusDiff::online_delta`] for the change in the actual player count.
+    #[must_use]
+    pub fn diff(&self, other: &StatusResponse) -> StatusDiff {
+        let before = self.players.sample.as_deref().unwrap_or_default();
+        let after = other.players.sample.as_deref().unwrap_or_default();
+
+        let missing_from = |players: &[Sample], from: &[Sample]| {
+            players
+                .iter()
+                .filter(|player| from.iter().all(|p| p.id != player.id))
+                .map(|player| player.name.clone())
+                .collect()
+        };
+
+        StatusDiff {
+            online_delta: other.players.online as i64 - self.players.online as i64,
+            joined: missing_from(after, before),
+            left: missing_from(before, after),
+            version_changed: self.version.name != other.version.name
+                || self.version.protocol != other.version.protocol,
+            motd_changed: self.motd != other.motd,
+        }
+    }
+}
+
+#[cfg(feature = "query")]
+impl From<FullStatResponse> for StatusResponse {
+    /// Convert a full query response into a status response, so code can handle a
+    /// single type no matter which protocol was used to get it.
+    ///
+    /// The conversion is lossy, since the query doesn't report everything the status does:
+    /// * [`version.protocol`](Version::protocol) is `-1`, because only the version
+    ///   name is known.
+    /// * [`motd`](StatusResponse::motd) is the plain text MOTD as a
+    ///   [`ChatObject::JsonPrimitive`] string, since the query doesn't send chat objects.
+    /// * [`players.sample`](Players::sample) is `None`, since the query doesn't report
+    ///   player UUIDs.
+    /// * [`favicon`](StatusResponse::favicon) and the chat settings are `None`.
+    ///
+    /// Player counts that don't fit in a [`u32`] are saturated.
+    fn from(res: FullStatResponse) -> Self {
+        let count = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+
+        Self {
+            version: Version {
+                name: res.version,
+                protocol: -1,
+            },
+            players: Players {
+                max: count(res.max_players),
+                online: count(res.num_players),
+                sample: None,
+            },
+            motd: Some(ChatObject::JsonPrimitive(res.motd.into())),
+            favicon: None,
+            previews_chat: None,
+            enforces_secure_chat: None,
+            prevents_chat_reports: None,
+            forge_data: None,
+            mod_info: None,
+            modpack_data: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+impl From<LegacyStatusResponse> for StatusResponse {
+    /// Convert a [legacy](super::status_legacy) status response into a modern one, so code
+    /// can handle a single type no matter which protocol was used to get it.
+    ///
+    /// The conversion is lossy, since old servers report a lot less:
+    /// * [`version`](StatusResponse::version) has an empty name and a protocol of `-1`
+    ///   if the server didn't report it.
+    /// * [`motd`](StatusResponse::motd) is the MOTD as a [`ChatObject::JsonPrimitive`]
+    ///   string, including any formatting codes.
+    /// * [`players.sample`](Players::sample) is `None`.
+    /// * [`favicon`](StatusResponse::favicon) and the chat settings are `None`.
+    fn from(res: LegacyStatusResponse) -> Self {
+        let version = res.to_version().unwrap_or(Version {
+            name: String::new(),
+            protocol: -1,
+        });
+
+        Self {
+            version,
+            players: Players {
+                max: res.max,
+                online: res.online,
+                sample: None,
+            },
+            motd: Some(ChatObject::JsonPrimitive(res.motd.into())),
+            favicon: None,
+            previews_chat: None,
+            enforces_secure_chat: None,
+            prevents_chat_reports: None,
+            forge_data: None,
+            mod_info: None,
+            modpack_data: None,
+            extra: HashMap::new(),
+        }
+    }
+}
+
+/// The changes between two [`StatusResponse`]s of the same server.
+///
+/// Returned by [`StatusResponse::diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StatusDiff {
+    /// The change in the number of online players (positive if players joined).
+    pub online_delta: i64,
+
+    /// Names of the players that appeared in the player sample.
+    pub joined: Vec<String>,
+
+    /// Names of the players that disappeared from the player sample.
+    pub left: Vec<String>,
+
+    /// Did the game or protocol version change?
+    pub version_changed: bool,
+
+    /// Did the MOTD (including its formatting) change?
+    pub motd_changed: bool,
+}
+
+impl StatusDiff {
+    /// Returns `true` if nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Chat-related capabilities advertised by the server.
+///
+/// Servers that don't report a capability at all are treated as not having it.
+/// Not intended to be used directly, but only through [`StatusResponse::capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    /// Does the server preview chat?
+    pub previews_chat: bool,
+
+    /// Does the server use signed chat messages?
+    pub enforces_secure_chat: bool,
+
+    /// Does the server prevent chat reports (using the No Chat Reports mod)?
+    pub prevents_chat_reports: bool,
+}
+
+/// Response from a pre-1.7 server to the [legacy Server List Ping](https://wiki.vg/Server_List_Ping#1.6).
+///
+/// Returned by [`status_legacy`](super::status_legacy).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LegacyStatusResponse {
+    /// The version of the [Protocol](https://wiki.vg/Protocol) being used.
+    /// Only reported by servers from 1.4 onwards.
+    pub protocol: Option<u64>,
+
+    /// The game version (e.g: 1.6.4).
+    /// Only reported by servers from 1.4 onwards.
+    pub version: Option<String>,
+
+    /// The "motd" - message shown in the server list by the client.
+    pub motd: String,
+
+    /// The number of players currently online.
+    pub online: u32,
+
+    /// The maximum number of players allowed on the server.
+    pub max: u32,
+}
+
+impl LegacyStatusResponse {
+    /// Get the version information in the same form as a modern [`StatusResponse`],
+    /// so both kinds of response can be handled uniformly.
+    ///
+    /// Returns `None` if the server didn't report its protocol and version.
+    #[must_use]
+    pub fn to_version(&self) -> Option<Version> {
+        Some(Version {
+            name: self.version.clone()?,
+            protocol: self.protocol? as i64,
+        })
+    }
+}
+
+/// A [`StatusResponse`], along with details about how it was framed on the wire.
+///
+/// Returned by [`status_detailed`](super::status_detailed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetailedStatus {
+    /// The parsed status response.
+    pub response: StatusResponse,
+
+    /// The length of the status response packet, as announced by the length prefix
+    /// sent by the server.
+    pub packet_length: usize,
+
+    /// The length in bytes of the JSON status response.
+    pub json_length: usize,
+}
+
+impl DetailedStatus {
+    /// The length the status response packet should have had, given the length of the
+    /// JSON it contains: one byte for the packet ID, the length prefix of the JSON
+    /// string, and the JSON itself.
+    #[must_use]
+    pub fn expected_packet_length(&self) -> usize {
+        // lengths past `i32::MAX` can't be framed anyway; they'd need the longest prefix
+        let prefix_len = varint_len(i32::try_from(self.json_length).unwrap_or(i32::MAX));
+
+        1 + prefix_len + self.json_length
+    }
+
+    /// Whether the packet length announced by the server matches the length of the
+    /// content of the packet.
+    ///
+    /// A mismatch means that the server doesn't conform to the protocol: it sent a
+    /// packet with data after the JSON, which clients ignore. (Packets too short for
+    /// the JSON they claim to contain are rejected when reading the status.)
+    #[must_use]
+    pub fn is_well_framed(&self) -> bool {
+        self.packet_length == self.expected_packet_length()
+    }
+}
+
+/// The network channels and mods of a Forge server, as advertised in its status response.
+///
+/// Forge servers for 1.18 and later pack their mods and channels into a compact string
+/// (the `d` field) instead of listing them, which is decoded into [`mods`](Self::mods)
+/// and [`channels`](Self::channels) as well.
+///
+/// Not intended to be used directly, but only as a part of [`StatusResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawForgeData")]
+pub struct ForgeData {
+    /// The network channels registered by the server's mods.
+    pub channels: Vec<ForgeChannel>,
+
+    /// The mods installed on the server.
+    pub mods: Vec<ForgeMod>,
+
+    /// The version of the Forge networking protocol.
+    #[serde(rename = "fmlNetworkVersion")]
+    pub fml_network_version: Option<u32>,
+
+    /// Whether the server left out some of its mods and channels, to keep the
+    /// response small.
+    pub truncated: bool,
+}
+
+/// [`ForgeData`] as it is sent, before the packed mod list is decoded.
+#[derive(Deserialize)]
+struct RawForgeData {
+    #[serde(default)]
+    channels: Vec<ForgeChannel>,
+    #[serde(default)]
+    mods: Vec<ForgeMod>,
+    #[serde(rename = "fmlNetworkVersion", default)]
+    fml_network_version: Option<u32>,
+    #[serde(default)]
+    truncated: bool,
+    #[serde(default)]
+    d: Option<String>,
+}
+
+impl TryFrom<RawForgeData> for ForgeData {
+    type Error = io::Error;
+
+    fn try_from(raw: RawForgeData) -> io::Result<Self> {
+        let mut data = Self {
+            channels: raw.channels,
+            mods: raw.mods,
+            fml_network_version: raw.fml_network_version,
+            truncated: raw.truncated,
+        };
+
+        if let Some(packed) = raw.d {
+            let list = forge::decode(&packed)?;
+            data.channels.extend(list.channels);
+            data.mods.extend(list.mods);
+            data.truncated |= list.truncated;
+        }
+
+        Ok(data)
+    }
+}
+
+/// The mods of a Forge server for 1.12 and earlier, as advertised in its status response.
+///
+/// Not intended to be used directly, but only as a part of [`StatusResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModInfo {
+    /// The type of the server, `FML` for Forge servers.
+    #[serde(rename = "type")]
+    pub mod_type: String,
+
+    /// The mods installed on the server.
+    #[serde(rename = "modList", default)]
+    pub mod_list: Vec<ForgeMod>,
+}
+
+impl ForgeData {
+    /// The number of mods installed on the server.
+    #[must_use]
+    pub fn mod_count(&self) -> usize {
+        self.mods.len()
+    }
+
+    /// The number of network channels registered by the server's mods.
+    #[must_use]
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Whether a mod with the given ID (for example `jei`) is installed on the server.
+    /// Mod IDs are compared case-insensitively.
+    #[must_use]
+    pub fn has_mod(&self, mod_id: &str) -> bool {
+        self.mods
+            .iter()
+            .any(|m| m.mod_id.eq_ignore_ascii_case(mod_id))
+    }
+}
+
+/// A network channel registered by a mod on a Forge server.
+///
+/// Not intended to be used directly, but only as a part of [`ForgeData`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForgeChannel {
+    /// The name of the channel, for example `forge:handshake`.
+    #[serde(rename = "res")]
+    pub name: String,
+
+    /// The version of the channel.
+    pub version: String,
+
+    /// Whether clients must have the channel to join.
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A mod installed on a Forge server.
+///
+/// Not intended to be used directly, but only as a part of [`ForgeData`] or [`ModInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForgeMod {
+    /// The mod's ID, for example `jei`.
+    #[serde(rename = "modId", alias = "modid")]
+    pub mod_id: String,
+
+    /// The mod's version.
+    ///
+    /// This is [`IGNORE_SERVER_ONLY`] for mods that are only needed on the server.
+    #[serde(rename = "modmarker", alias = "version")]
+    pub version: String,
+}
+
+/// The modpack a server runs, as advertised in its status response.
+///
+/// All fields are optional, since different mods and launchers fill in different ones.
+///
+/// Not intended to be used directly, but only as a part of [`StatusResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModpackData {
+    /// The ID of the modpack's project on the platform it is hosted on,
+    /// for example `CurseForge`.
+    #[serde(rename = "projectID", default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<u64>,
+
+    /// The name of the modpack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The version of the modpack, for example `1.2.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// The ID of the modpack's version (its file) on the platform it is hosted on.
+    #[serde(rename = "versionID", default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<u64>,
+
+    /// The release type of the version, for example `release` or `beta`.
+    #[serde(
+        rename = "releaseType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub release_type: Option<String>,
+
+    /// Where the modpack can be downloaded from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Decode standard (padded) base64, ignoring whitespace, which some servers
+/// wrap the favicon with. Returns `None` if the input is not valid base64.
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let input = input
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    if input.len() % 4 != 0 {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    let chunks = input.len() / 4;
+    for (i, chunk) in input.chunks_exact(4).enumerate() {
+        // padding is only allowed at the end
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && i + 1 != chunks) {
+            return None;
+        }
+
+        let mut bits = 0u32;
+        for &c in &chunk[..4 - padding] {
+            bits = bits << 6 | u32::from(sextet(c)?);
+        }
+        bits <<= 6 * padding;
+
+        let [_, a, b, c] = bits.to_be_bytes();
+        output.extend_from_slice(&[a, b, c][..3 - padding]);
+    }
+
+    Some(output)
+}
+
+/// Deserialize an optional field, treating data that isn't in the expected format
+/// as if the field was missing, instead of failing to deserialize the whole response.
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    Ok(T::deserialize(value).ok())
 }
 
 /// Struct that stores information about players on the server.
 ///
 /// Not intended to be used directly, but only as a part of [`StatusResponse`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Players {
     /// The maximum number of players allowed on the server.
     pub max: u32,
@@ -51,7 +718,7 @@ pub struct Players {
 /// A player listed on the server's list ping information.
 ///
 /// Not intended to be used directly, but only as a part of [`StatusResponse`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Sample {
     /// The player's username.
     pub name: String,
@@ -63,19 +730,33 @@ pub struct Sample {
 /// Struct that stores version information about the server.
 ///
 /// Not intended to be used directly, but only as a part of [`StatusResponse`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Version {
     /// The game version (e.g: 1.19.1)
     pub name: String,
     /// The version of the [Protocol](https://wiki.vg/Protocol) being used.
     ///
     /// See [the wiki.vg page](https://wiki.vg/Protocol_version_numbers) for a
-    /// reference on what versions these correspond to.
+    /// reference on what versions these correspond to, or use
+    /// [`release_name`](Self::release_name).
     pub protocol: i64,
 }
 
+impl Version {
+    /// The name of the release that uses this [protocol](Self::protocol) version, see
+    /// [`protocol_to_version`].
+    ///
+    /// Unlike [`name`](Self::name), this isn't made up by the server, so it's still
+    /// readable when the server puts something else there, like the name of its proxy.
+    #[must_use]
+    pub fn release_name(&self) -> Option<&'static str> {
+        protocol_to_version(self.protocol)
+    }
+}
+
 /// Represents a chat object (the MOTD is sent as a chat object).
-#[derive(Debug, Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ChatObject {
     /// An individual chat object
@@ -85,11 +766,399 @@ pub enum ChatObject {
     Array(Vec<ChatObject>),
 
     /// Unknown data - raw JSON
+    ///
+    /// Any JSON that isn't recognized as one of the other variants (a plain string,
+    /// a number, or an object with unexpected field types) ends up here, so
+    /// deserializing a [`ChatObject`] never fails on valid JSON.
     JsonPrimitive(serde_json::Value),
 }
 
+impl ChatObject {
+    /// Lay the MOTD out the way the vanilla client shows it in the server list:
+    /// as two lines of plain text, each at most [`MOTD_LINE_WIDTH`] characters long.
+    ///
+    /// Formatting (including legacy `§` codes) is dropped. Explicit line breaks in the
+    /// text take priority; otherwise the text is wrapped at the last space that fits
+    /// on the first line. Anything that doesn't fit on the two lines is cut off.
+    #[must_use]
+    pub fn motd_preview(&self) -> (String, String) {
+        let text = self.to_plain_text();
+
+        let (first, rest) = match text.split_once('\n') {
+            Some((first, rest)) => (truncate(first, MOTD_LINE_WIDTH), rest),
+            None => wrap(&text, MOTD_LINE_WIDTH),
+        };
+        let second = truncate(rest.lines().next().unwrap_or_default(), MOTD_LINE_WIDTH);
+
+        (first.trim_end().to_string(), second.trim().to_string())
+    }
+
+    /// Get the text content of this chat object on a single line, for example for
+    /// a table cell in a list of servers.
+    ///
+    /// Like [`to_plain_text`](Self::to_plain_text), but the lines are trimmed, and joined
+    /// with a space. Blank lines are skipped. See [`to_single_line_with`](Self::to_single_line_with)
+    /// to join them with something else.
+    #[must_use]
+    pub fn to_single_line(&self) -> String {
+        self.to_single_line_with(" ")
+    }
+
+    /// Like [`to_single_line`](Self::to_single_line), but the lines are joined with
+    /// the given separator.
+    #[must_use]
+    pub fn to_single_line_with(&self, separator: &str) -> String {
+        self.to_plain_text()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Get the text content of this chat object, without any formatting
+    /// (including legacy `§` codes).
+    ///
+    /// The text of every component is concatenated in order, descending into `extra`
+    /// and arrays. Plain JSON strings (as sent by older servers) are returned as they are,
+    /// and numbers and booleans are rendered the way the client displays them.
+    ///
+    /// Translation components are rendered as their translation key, with their
+    /// arguments substituted in. See [`to_plain_text_with`](Self::to_plain_text_with)
+    /// to translate them instead.
+    #[must_use]
+    pub fn to_plain_text(&self) -> String {
+        self.to_plain_text_with(|_| None)
+    }
+
+    /// Like [`to_plain_text`](Self::to_plain_text), but translation keys are resolved
+    /// with the given function. Keys it returns `None` for are kept as they are.
+    ///
+    /// The translated text can contain `%s` (or positional `%1$s`) placeholders,
+    /// which are replaced with the component's arguments.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::status::data::ChatObject;
+    ///
+    /// let motd: ChatObject = serde_json::from_str(
+    ///     r#"{"translate": "multiplayer.player.joined", "with": ["Notch"]}"#,
+    /// ).unwrap();
+    ///
+    /// let text = motd.to_plain_text_with(|key| match key {
+    ///     "multiplayer.player.joined" => Some("%s joined the game".to_string()),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(text, "Notch joined the game");
+    /// ```
+    #[must_use]
+    pub fn to_plain_text_with(&self, resolver: impl Fn(&str) -> Option<String>) -> String {
+        let mut text = String::new();
+        self.push_plain_text(&mut text, &resolver);
+
+        strip_formatting(&text)
+    }
+
+    /// Parse a string formatted with legacy `§` codes (such as `§cHello §lWorld`) into
+    /// chat components with the equivalent colors and styles.
+    ///
+    /// Some servers and proxies send the MOTD like this, as a plain string. Like in the
+    /// client, color codes reset any styles before them, and `§r` resets everything.
+    /// Hex colors in the `§x§r§r§g§g§b§b` format used by `BungeeCord` are supported as well.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::status::data::ChatObject;
+    ///
+    /// let motd = ChatObject::from_legacy_string("\u{a7}cHello \u{a7}lWorld");
+    /// let ChatObject::Object(motd) = motd else { unreachable!() };
+    /// let parts = motd.extra.unwrap();
+    ///
+    /// let ChatObject::Object(world) = &parts[1] else { unreachable!() };
+    /// assert_eq!(world.text.as_deref(), Some("World"));
+    /// assert_eq!(world.color.as_deref(), Some("red"));
+    /// assert_eq!(world.bold, Some(true));
+    /// ```
+    #[must_use]
+    pub fn from_legacy_string(text: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut current = ChatComponentObject::default();
+        let mut segment = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != SECTION_SIGN {
+                segment.push(c);
+                continue;
+            }
+            let Some(code) = chars.next() else {
+                break;
+            };
+
+            if !segment.is_empty() {
+                let next = current.style();
+                current.text = Some(std::mem::take(&mut segment));
+                parts.push(Self::Object(std::mem::replace(&mut current, next)));
+            }
+
+            match code.to_ascii_lowercase() {
+                'x' => {
+                    // §x followed by 6 hex digits, each prefixed with §
+                    let mut hex = String::from("#");
+                    while hex.len() < 7 && chars.next_if_eq(&SECTION_SIGN).is_some() {
+                        match chars.next() {
+                            Some(digit) if digit.is_ascii_hexdigit() => hex.push(digit),
+                            _ => break,
+                        }
+                    }
+                    if hex.len() == 7 {
+                        current = ChatComponentObject::reset(hex);
+                    }
+                }
+                'l' => current.bold = Some(true),
+                'o' => current.italic = Some(true),
+                'n' => current.underlined = Some(true),
+                'm' => current.strikethrough = Some(true),
+                'k' => current.obfuscated = Some(true),
+                'r' => current = ChatComponentObject::reset("reset".to_string()),
+                code => {
+                    if let Some(name) = legacy_color_name(code) {
+                        current = ChatComponentObject::reset(name.to_string());
+                    }
+                }
+            }
+        }
+
+        if !segment.is_empty() {
+            current.text = Some(segment);
+            parts.push(Self::Object(current));
+        }
+
+        if parts.len() == 1 {
+            return parts.remove(0);
+        }
+        Self::Object(ChatComponentObject {
+            text: Some(String::new()),
+            extra: (!parts.is_empty()).then_some(parts),
+            ..ChatComponentObject::default()
+        })
+    }
+
+    /// Render this chat object for a terminal, with its colors and styles as
+    /// [ANSI escape codes](https://en.wikipedia.org/wiki/ANSI_escape_code).
+    ///
+    /// The `color`, `bold`, `italic`, `underlined`, and `strikethrough` fields are rendered,
+    /// and inherited by child components as they are in the client. Legacy `§` codes in the
+    /// text are rendered as well. Named and hex colors are both emitted as 24-bit colors,
+    /// which most modern terminals support.
+    ///
+    /// Formatting is reset before every piece of text, so styles never bleed from one
+    /// component into the next, and at the end of the output.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::status::data::ChatObject;
+    ///
+    /// let motd: ChatObject = serde_json::from_str(
+    ///     r#"{"text": "A ", "extra": [{"text": "Minecraft", "color": "gold", "bold": true}]}"#,
+    /// ).unwrap();
+    ///
+    /// assert_eq!(motd.to_ansi(), "\x1b[0mA \x1b[0m\x1b[1;38;2;255;170;0mMinecraft\x1b[0m");
+    /// ```
+    #[must_use]
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        self.push_ansi(&mut out, AnsiStyle::default());
+
+        if !out.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+
+    /// Append this chat object to `out` as ANSI-formatted text, inheriting `style`.
+    fn push_ansi(&self, out: &mut String, style: AnsiStyle) {
+        match self {
+            Self::Object(component) => {
+                let style = style.with(component);
+
+                let mut text = String::new();
+                component.push_own_text(&mut text, &|_| None);
+                style.push_text(out, &text);
+
+                for child in component.extra.iter().flatten() {
+                    child.push_ansi(out, style);
+                }
+            }
+            Self::Array(children) => {
+                for child in children {
+                    child.push_ansi(out, style);
+                }
+            }
+            Self::JsonPrimitive(_) => {
+                let mut text = String::new();
+                self.push_plain_text(&mut text, &|_| None);
+                style.push_text(out, &text);
+            }
+        }
+    }
+
+    /// Append the text content of this chat object to `out`, without any formatting.
+    fn push_plain_text(&self, out: &mut String, resolver: &dyn Fn(&str) -> Option<String>) {
+        match self {
+            Self::Object(component) => {
+                component.push_own_text(out, resolver);
+
+                for child in component.extra.iter().flatten() {
+                    child.push_plain_text(out, resolver);
+                }
+            }
+            Self::Array(children) => {
+                for child in children {
+                    child.push_plain_text(out, resolver);
+                }
+            }
+            Self::JsonPrimitive(serde_json::Value::String(text)) => out.push_str(text),
+            // the client displays numbers and booleans in place of text components as well
+            Self::JsonPrimitive(
+                value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)),
+            ) => {
+                out.push_str(&value.to_string());
+            }
+            Self::JsonPrimitive(_) => {}
+        }
+    }
+}
+
+/// The formatting of a piece of chat, as rendered by [`ChatObject::to_ansi`].
+#[allow(clippy::struct_excessive_bools)] // mirrors the fields of ChatComponentObject
+#[derive(Debug, Clone, Copy, Default)]
+struct AnsiStyle {
+    color: Option<u32>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+}
+
+impl AnsiStyle {
+    /// The style of a component, inheriting anything it doesn't set from `self`.
+    fn with(mut self, component: &ChatComponentObject) -> Self {
+        match component.color.as_deref() {
+            Some("reset") => self.color = None,
+            Some(color) => self.color = parse_color(color).or(self.color),
+            None => {}
+        }
+
+        self.bold = component.bold.unwrap_or(self.bold);
+        self.italic = component.italic.unwrap_or(self.italic);
+        self.underlined = component.underlined.unwrap_or(self.underlined);
+        self.strikethrough = component.strikethrough.unwrap_or(self.strikethrough);
+        self
+    }
+
+    /// Append `text` in this style, applying any legacy `§` codes in it.
+    fn push_text(self, out: &mut String, text: &str) {
+        if text.contains(SECTION_SIGN) {
+            // the components it is split into don't contain any codes anymore
+            ChatObject::from_legacy_string(text).push_ansi(out, self);
+        } else {
+            self.push_segment(out, text);
+        }
+    }
+
+    /// Append a piece of text without any `§` codes in this style.
+    fn push_segment(self, out: &mut String, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underlined {
+            codes.push("4".to_string());
+        }
+        if self.strikethrough {
+            codes.push("9".to_string());
+        }
+        if let Some(rgb) = self.color {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            codes.push(format!("38;2;{r};{g};{b}"));
+        }
+
+        out.push_str("\x1b[0m");
+        if !codes.is_empty() {
+            out.push_str("\x1b[");
+            out.push_str(&codes.join(";"));
+            out.push('m');
+        }
+        out.push_str(text);
+    }
+}
+
+/// Replace the `%s`, `%<n>$s`, and `%%` placeholders in a translation with the given arguments.
+fn substitute_args(format: &str, args: &[String]) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut next_arg = 0;
+    let mut rest = format;
+
+    while let Some(i) = rest.find('%') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('%') {
+            result.push('%');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('s') {
+            result.push_str(args.get(next_arg).map_or("", String::as_str));
+            next_arg += 1;
+            rest = after;
+        } else if let Some((index, after)) = rest.split_once("$s") {
+            match index.parse::<usize>() {
+                Ok(n) if n > 0 => {
+                    result.push_str(args.get(n - 1).map_or("", String::as_str));
+                    rest = after;
+                }
+                _ => result.push('%'),
+            }
+        } else {
+            result.push('%');
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Cut `text` off after `width` characters.
+fn truncate(text: &str, width: usize) -> &str {
+    match text.char_indices().nth(width) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+/// Split `text` into a first line of at most `width` characters, breaking at
+/// the last space if possible, and the remainder.
+fn wrap(text: &str, width: usize) -> (&str, &str) {
+    let first = truncate(text, width);
+    if first.len() == text.len() {
+        return (text, "");
+    }
+
+    match first.rfind(' ') {
+        Some(i) if i > 0 => (&text[..i], &text[i + 1..]),
+        _ => (first, &text[first.len()..]),
+    }
+}
+
 /// A piece of a `ChatObject`
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct ChatComponentObject {
     /// Text of the chat message
     pub text: Option<String>,
@@ -98,6 +1167,10 @@ pub struct ChatComponentObject {
     /// See [wiki.vg](https://wiki.vg/Chat#Translation_component)
     pub translate: Option<String>,
 
+    /// Arguments to substitute into the translated text (for `%s` placeholders),
+    /// if this is a translation component.
+    pub with: Option<Vec<ChatObject>>,
+
     /// Displays the keybind for the specified key, or the string itself if unknown.
     pub keybind: Option<String>,
 
@@ -147,8 +1220,59 @@ pub struct ChatComponentObject {
     pub extra: Option<Vec<ChatObject>>,
 }
 
+impl ChatComponentObject {
+    /// An empty component with the same color and styles as this one.
+    fn style(&self) -> Self {
+        Self {
+            color: self.color.clone(),
+            bold: self.bold,
+            italic: self.italic,
+            underlined: self.underlined,
+            strikethrough: self.strikethrough,
+            obfuscated: self.obfuscated,
+            ..Self::default()
+        }
+    }
+
+    /// An empty component with the given color, and all styles turned off.
+    fn reset(color: String) -> Self {
+        Self {
+            color: Some(color),
+            bold: Some(false),
+            italic: Some(false),
+            underlined: Some(false),
+            strikethrough: Some(false),
+            obfuscated: Some(false),
+            ..Self::default()
+        }
+    }
+
+    /// Append the text of this component itself (not its children) to `out`.
+    fn push_own_text(&self, out: &mut String, resolver: &dyn Fn(&str) -> Option<String>) {
+        if let Some(text) = &self.text {
+            out.push_str(text);
+        } else if let Some(key) = &self.translate {
+            let args = self
+                .with
+                .iter()
+                .flatten()
+                .map(|arg| {
+                    let mut text = String::new();
+                    arg.push_plain_text(&mut text, resolver);
+                    text
+                })
+                .collect::<Vec<_>>();
+
+            let format = resolver(key).unwrap_or_else(|| key.clone());
+            out.push_str(&substitute_args(&format, &args));
+        } else if let Some(keybind) = &self.keybind {
+            out.push_str(keybind);
+        }
+    }
+}
+
 /// `ClickEvent` data for a chat component
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ChatClickEvent {
     // These are not renamed on purpose. (server returns them in snake_case)
     /// Opens the URL in the user's default browser. Protocol must be `http` or `https`
@@ -171,7 +1295,7 @@ pub struct ChatClickEvent {
 }
 
 /// `HoverEvent` data for a chat component
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChatHoverEvent {
     // These are not renamed on purpose. (server returns them in snake_case)
     /// Text to show when the item is hovered over
@@ -186,3 +1310,513 @@ pub struct ChatHoverEvent {
     /// Displays information about the entity with the given NBT
     pub show_entity: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChatObject, DetailedStatus, LegacyStatusResponse, StatusResponse, IGNORE_SERVER_ONLY,
+    };
+    use crate::buf::{read_mc_string_buf, read_varint_buf};
+    use serde_json::json;
+
+    #[test]
+    fn test_forge_data() {
+        // trimmed down response of a Forge 1.16.5 server
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.16.5", "protocol": 754 },
+            "players": { "max": 20, "online": 0 },
+            "description": { "text": "A Minecraft Server" },
+            "forgeData": {
+                "channels": [
+                    { "res": "forge:tier_sorting", "version": "1.0", "required": false },
+                    { "res": "jei:channel", "version": "7.7.1.153", "required": true },
+                    { "res": "minecraft:register", "version": "FML2", "required": true },
+                ],
+                "mods": [
+                    { "modId": "forge", "modmarker": "ANY" },
+                    { "modId": "minecraft", "modmarker": "1.16.5" },
+                    { "modId": "jei", "modmarker": "7.7.1.153" },
+                ],
+                "fmlNetworkVersion": 2,
+            },
+        }))
+        .unwrap();
+
+        let forge = status.forge_data.unwrap();
+        assert_eq!(forge.mod_count(), 3);
+        assert_eq!(forge.channel_count(), 3);
+        assert_eq!(forge.fml_network_version, Some(2));
+        assert!(forge.channels[1].required);
+        assert!(forge.has_mod("jei"));
+        assert!(forge.has_mod("JEI"));
+        assert!(!forge.has_mod("create"));
+
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.16.5", "protocol": 754 },
+            "players": { "max": 20, "online": 0 },
+            "forgeData": { "mods": "not a list" },
+        }))
+        .unwrap();
+        assert!(status.forge_data.is_none());
+    }
+
+    #[test]
+    fn test_forge_packed_data() {
+        // a server-only mod `spark`, packed the way Forge 1.18+ servers do it
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "forgeData": {
+                "channels": [],
+                "mods": [],
+                "truncated": false,
+                "fmlNetworkVersion": 3,
+                "d": "\u{b}\0\0\u{202}\u{4c14}\u{b83}\u{3726}\u{d}",
+            },
+        }))
+        .unwrap();
+
+        let forge = status.forge_data.as_ref().unwrap();
+        assert_eq!(forge.fml_network_version, Some(3));
+        assert!(!forge.truncated);
+        assert_eq!(status.forge_mods().len(), 1);
+        assert!(forge.has_mod("spark"));
+        assert_eq!(forge.mods[0].version, IGNORE_SERVER_ONLY);
+    }
+
+    #[test]
+    fn test_mod_info() {
+        // trimmed down response of a Forge 1.12.2 server
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.12.2", "protocol": 340 },
+            "players": { "max": 20, "online": 0 },
+            "modinfo": {
+                "type": "FML",
+                "modList": [
+                    { "modid": "minecraft", "version": "1.12.2" },
+                    { "modid": "forge", "version": "14.23.5.2859" },
+                ],
+            },
+        }))
+        .unwrap();
+
+        assert!(status.forge_data.is_none());
+        let info = status.mod_info.as_ref().unwrap();
+        assert_eq!(info.mod_type, "FML");
+        let mods = status.forge_mods();
+        assert_eq!(mods.len(), 2);
+        assert_eq!(mods[1].mod_id, "forge");
+        assert_eq!(mods[1].version, "14.23.5.2859");
+    }
+
+    #[test]
+    fn test_modpack_data() {
+        // response of a server with BetterCompatibilityChecker
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "description": { "text": "A Minecraft Server" },
+            "modpackData": {
+                "projectID": 715_572,
+                "name": "All the Mods 9",
+                "version": "0.2.44",
+                "versionID": 5_125_809,
+                "releaseType": "release",
+                "isMetadata": true,
+            },
+        }))
+        .unwrap();
+
+        let modpack = status.modpack_data.unwrap();
+        assert_eq!(modpack.project_id, Some(715_572));
+        assert_eq!(modpack.name.as_deref(), Some("All the Mods 9"));
+        assert_eq!(modpack.version.as_deref(), Some("0.2.44"));
+        assert_eq!(modpack.version_id, Some(5_125_809));
+        assert_eq!(modpack.release_type.as_deref(), Some("release"));
+        assert_eq!(modpack.url, None);
+
+        let status: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "modpackData": { "projectID": "not a number" },
+        }))
+        .unwrap();
+        assert!(status.modpack_data.is_none());
+    }
+
+    #[test]
+    fn test_detailed_status_framing() {
+        let detailed = |packet_length, json_length| DetailedStatus {
+            response: status_with_description(&json!("hello")),
+            packet_length,
+            json_length,
+        };
+
+        assert!(detailed(102, 100).is_well_framed());
+        assert_eq!(detailed(0, 200).expected_packet_length(), 203);
+        assert_eq!(detailed(0, 0).expected_packet_length(), 2);
+        assert_eq!(detailed(0, 2_097_152).expected_packet_length(), 2_097_157);
+        assert!(!detailed(110, 100).is_well_framed());
+    }
+
+    #[cfg(feature = "query")]
+    #[test]
+    fn test_from_full_stat() {
+        use crate::query::FullStatResponse;
+        use std::collections::HashMap;
+
+        let query = FullStatResponse {
+            motd: "A Minecraft Server".to_string(),
+            game_type: "SMP".to_string(),
+            game_id: "MINECRAFT".to_string(),
+            version: "1.20.1".to_string(),
+            plugins: String::new(),
+            map: "world".to_string(),
+            num_players: 1,
+            max_players: 20,
+            host_port: 25565,
+            host_ip: "127.0.0.1".to_string(),
+            players: vec!["Notch".to_string()],
+            extra: HashMap::new(),
+        };
+
+        let status = StatusResponse::from(query);
+        assert_eq!(status.version.name, "1.20.1");
+        assert_eq!(status.version.protocol, -1);
+        assert_eq!((status.players.online, status.players.max), (1, 20));
+        assert_eq!(status.motd.unwrap().to_plain_text(), "A Minecraft Server");
+        assert!(status.favicon.is_none());
+    }
+
+    #[test]
+    fn test_from_legacy() {
+        let legacy = LegacyStatusResponse {
+            protocol: Some(78),
+            version: Some("1.6.4".to_string()),
+            motd: "A Minecraft Server".to_string(),
+            online: 3,
+            max: 20,
+        };
+
+        let status = StatusResponse::from(legacy);
+        assert_eq!(status.version.name, "1.6.4");
+        assert_eq!(status.version.protocol, 78);
+        assert_eq!((status.players.online, status.players.max), (3, 20));
+        assert_eq!(status.motd.unwrap().to_plain_text(), "A Minecraft Server");
+    }
+
+    fn status_with_description(description: &serde_json::Value) -> StatusResponse {
+        let json = json!({
+            "version": { "name": "1.19.4", "protocol": 762 },
+            "players": { "max": 20, "online": 0 },
+            "description": description,
+        });
+
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_to_status_packet() {
+        let status = status_with_description(&json!({ "text": "A Minecraft Server" }));
+
+        let mut packet = status.to_status_packet();
+        let len = read_varint_buf(&mut packet).unwrap();
+        assert_eq!(usize::try_from(len).unwrap(), packet.len());
+        assert_eq!(read_varint_buf(&mut packet).unwrap(), 0);
+
+        let json = read_mc_string_buf(&mut packet).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StatusResponse>(&json).unwrap(),
+            status
+        );
+        assert!(packet.is_empty());
+    }
+
+    #[test]
+    fn test_clone_eq() {
+        let status = status_with_description(&json!({ "text": "hello", "extra": ["world"] }));
+        let cloned = status.clone();
+        assert_eq!(status, cloned);
+
+        let mut changed = cloned.clone();
+        changed.players.online += 1;
+        assert_ne!(status, changed);
+
+        let versions = std::collections::HashSet::from([status.version, cloned.version]);
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[test]
+    fn test_status_diff() {
+        let before: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.19.4", "protocol": 762 },
+            "players": { "max": 20, "online": 2, "sample": [
+                { "name": "alice", "id": "4566e69f-c907-48ee-8d71-d7ba5aa00d20" },
+                { "name": "bob", "id": "a7a2f2d5-9d0b-4d7c-8f4f-9b0c8c3e1c11" },
+            ] },
+            "description": "hello",
+        }))
+        .unwrap();
+        let after: StatusResponse = serde_json::from_value(json!({
+            "version": { "name": "1.19.4", "protocol": 762 },
+            "players": { "max": 20, "online": 4, "sample": [
+                { "name": "bob", "id": "a7a2f2d5-9d0b-4d7c-8f4f-9b0c8c3e1c11" },
+                { "name": "carol", "id": "0d1b3d54-1f5a-4a5b-a7c4-2b7d0f2a9e33" },
+            ] },
+            "description": "hello",
+        }))
+        .unwrap();
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.online_delta, 2);
+        assert_eq!(diff.joined, ["carol"]);
+        assert_eq!(diff.left, ["alice"]);
+        assert!(!diff.version_changed);
+        assert!(!diff.motd_changed);
+
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_accepts_bedrock() {
+        let json = json!({
+            "version": { "name": "Paper 1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "description": "hello",
+            "geyser": { "bedrockPort": 19132 },
+        });
+        let status: StatusResponse = serde_json::from_value(json).unwrap();
+        assert!(status.accepts_bedrock());
+        assert_eq!(status.extra["geyser"]["bedrockPort"], 19132);
+
+        let status = status_with_description(&json!("hello"));
+        assert!(!status.accepts_bedrock());
+        assert!(status.extra.is_empty());
+    }
+
+    #[test]
+    fn test_favicon_base64() {
+        let mut status = status_with_description(&json!("hello"));
+        assert_eq!(status.favicon_base64(), None);
+
+        status.favicon = Some("data:image/png;base64,iVBORw0KGgo=".to_string());
+        assert_eq!(status.favicon_base64(), Some("iVBORw0KGgo="));
+
+        status.favicon = Some("iVBORw0KGgo=".to_string());
+        assert_eq!(status.favicon_base64(), None);
+    }
+
+    #[test]
+    fn test_favicon_png() {
+        let mut status = status_with_description(&json!("hello"));
+        assert!(status.favicon_png().is_none());
+
+        status.favicon = Some("data:image/png;base64,iVBORw0KGgo=".to_string());
+        assert_eq!(status.favicon_png().unwrap().unwrap(), b"\x89PNG\r\n\x1a\n");
+
+        status.favicon = Some("data:image/png;base64,iVBO\nRw0K\nGgoA".to_string());
+        assert_eq!(
+            status.favicon_png().unwrap().unwrap(),
+            b"\x89PNG\r\n\x1a\n\0"
+        );
+
+        for invalid in [
+            "iVBORw0KGgo=",
+            "data:image/png;base64,iVBORw0KGgo",
+            "data:image/png;base64,iVBO=w0KGgo=",
+            "data:image/png;base64,iVBORw0K!go=",
+        ] {
+            status.favicon = Some(invalid.to_string());
+            assert!(status.favicon_png().unwrap().is_err());
+        }
+    }
+
+    #[test]
+    fn test_proxy_markers() {
+        let json = json!({
+            "version": { "name": "Paper 1.20.1", "protocol": 763 },
+            "players": { "max": 20, "online": 0 },
+            "description": "hello",
+            "ipForwarding": "modern",
+            "velocityInfo": { "version": "3.2.0" },
+            "geyser": { "bedrockPort": 19132 },
+        });
+        let status: StatusResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(status.ip_forwarding(), Some("modern"));
+        assert_eq!(status.proxy_markers(), ["ipForwarding", "velocityInfo"]);
+
+        let status = status_with_description(&json!("hello"));
+        assert_eq!(status.ip_forwarding(), None);
+        assert!(status.proxy_markers().is_empty());
+    }
+
+    #[test]
+    fn test_prevents_chat_reports() {
+        let json = r#"{
+            "version": { "name": "1.19.4", "protocol": 762 },
+            "players": { "max": 20, "online": 1 },
+            "description": { "text": "A Minecraft Server" },
+            "enforcesSecureChat": false,
+            "preventsChatReports": true
+        }"#;
+        let status: StatusResponse = serde_json::from_str(json).unwrap();
+
+        assert_eq!(status.prevents_chat_reports, Some(true));
+        assert!(status.capabilities().prevents_chat_reports);
+        assert!(!status.capabilities().enforces_secure_chat);
+    }
+
+    #[test]
+    fn test_description_shapes() {
+        let motd = status_with_description(&json!({ "text": "hello" })).motd;
+        assert!(matches!(motd, Some(ChatObject::Object(_))));
+
+        let motd = status_with_description(&json!([{ "text": "hello" }, "world"])).motd;
+        assert!(matches!(motd, Some(ChatObject::Array(v)) if v.len() == 2));
+
+        let motd = status_with_description(&json!("hello")).motd;
+        assert!(matches!(motd, Some(ChatObject::JsonPrimitive(v)) if v == "hello"));
+
+        let motd = status_with_description(&json!(42)).motd;
+        assert!(matches!(motd, Some(ChatObject::JsonPrimitive(v)) if v == 42));
+
+        let motd = status_with_description(&json!(null)).motd;
+        assert!(motd.is_none());
+
+        // an object with fields of the wrong type is kept as raw JSON
+        let motd = status_with_description(&json!({ "text": 1, "bold": "yes" })).motd;
+        assert!(matches!(motd, Some(ChatObject::JsonPrimitive(v)) if v["bold"] == "yes"));
+    }
+
+    fn chat(json: &str) -> ChatObject {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let motd = chat(
+            r#"{"text": "A ", "bold": true, "extra": [
+                {"text": "Minecraft", "color": "green", "extra": [" Server"]},
+                ["\u00a7l!", 1, true, null]
+            ]}"#,
+        );
+        assert_eq!(motd.to_plain_text(), "A Minecraft Server!1true");
+
+        assert_eq!(
+            chat(r#""\u00a76A Minecraft Server""#).to_plain_text(),
+            "A Minecraft Server"
+        );
+        assert_eq!(chat(r#"{"text": 1, "bold": "yes"}"#).to_plain_text(), "");
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        let motd = chat(
+            r##"{"text": "", "color": "red", "extra": [
+                {"text": "A ", "bold": true},
+                {"text": "Minecraft", "color": "#00ff80", "italic": true},
+                {"text": "\u00a7nServer", "underlined": false, "strikethrough": true}
+            ]}"##,
+        );
+
+        assert_eq!(
+            motd.to_ansi(),
+            "\x1b[0m\x1b[1;38;2;255;85;85mA \
+             \x1b[0m\x1b[3;38;2;0;255;128mMinecraft\
+             \x1b[0m\x1b[4;9;38;2;255;85;85mServer\x1b[0m"
+        );
+
+        assert_eq!(
+            chat(r#""\u00a76gold\u00a7lbold\u00a7r plain""#).to_ansi(),
+            "\x1b[0m\x1b[38;2;255;170;0mgold\x1b[0m\x1b[1;38;2;255;170;0mbold\x1b[0m plain\x1b[0m"
+        );
+        assert_eq!(chat(r#"{"text": ""}"#).to_ansi(), "");
+    }
+
+    #[test]
+    fn test_from_legacy_string() {
+        let motd = ChatObject::from_legacy_string("\u{a7}cHello \u{a7}lWorld\u{a7}r!");
+        assert_eq!(motd.to_plain_text(), "Hello World!");
+
+        let ChatObject::Object(motd) = motd else {
+            panic!("expected an object");
+        };
+        let parts = motd
+            .extra
+            .unwrap()
+            .into_iter()
+            .map(|part| match part {
+                ChatObject::Object(part) => part,
+                _ => panic!("expected an object"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].text.as_deref(), Some("Hello "));
+        assert_eq!(parts[0].color.as_deref(), Some("red"));
+        assert_eq!(parts[0].bold, Some(false));
+        assert_eq!(parts[1].color.as_deref(), Some("red"));
+        assert_eq!(parts[1].bold, Some(true));
+        assert_eq!(parts[2].color.as_deref(), Some("reset"));
+        assert_eq!(parts[2].bold, Some(false));
+
+        let hex =
+            ChatObject::from_legacy_string("\u{a7}x\u{a7}1\u{a7}2\u{a7}a\u{a7}B\u{a7}3\u{a7}4hex");
+        assert!(matches!(hex, ChatObject::Object(c) if c.color.as_deref() == Some("#12aB34")));
+
+        let plain = ChatObject::from_legacy_string("plain");
+        assert!(
+            matches!(plain, ChatObject::Object(c) if c.text.as_deref() == Some("plain") && c.color.is_none())
+        );
+        assert_eq!(ChatObject::from_legacy_string("").to_plain_text(), "");
+    }
+
+    #[test]
+    fn test_plain_text_translate() {
+        let motd = chat(
+            r#"{"translate": "chat.type.text", "with": [{"text": "Steve"}, "hi"], "extra": ["!"]}"#,
+        );
+
+        assert_eq!(motd.to_plain_text(), "chat.type.text!");
+        assert_eq!(
+            motd.to_plain_text_with(|key| (key == "chat.type.text").then(|| "<%s> %s".into())),
+            "<Steve> hi!"
+        );
+        assert_eq!(
+            motd.to_plain_text_with(|_| Some("%2$s from %1$s (100%%)".into())),
+            "hi from Steve (100%)!"
+        );
+    }
+
+    #[test]
+    fn test_motd_preview_newline() {
+        let motd =
+            chat(r#"{"text": "\u00a7aA Minecraft Server", "extra": [{"text": "\nsecond line"}]}"#);
+
+        assert_eq!(
+            motd.motd_preview(),
+            ("A Minecraft Server".to_string(), "second line".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_single_line() {
+        let motd = chat(r#"{"text": "  §aA Minecraft Server\n\n", "extra": ["§6 second line "]}"#);
+
+        assert_eq!(motd.to_single_line(), "A Minecraft Server second line");
+        assert_eq!(
+            motd.to_single_line_with(" | "),
+            "A Minecraft Server | second line"
+        );
+    }
+
+    #[test]
+    fn test_motd_preview_wrap() {
+        let motd = chat(
+            r#""This is a very long message of the day that does not fit on a single line of the list""#,
+        );
+        let (first, second) = motd.motd_preview();
+
+        assert_eq!(first, "This is a very long message of the day that");
+        assert_eq!(second, "does not fit on a single line of the list");
+    }
+}
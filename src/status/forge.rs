@@ -0,0 +1,214 @@
+//! Decoding the packed mod list that Forge servers for 1.18 and later send in their
+//! status response, in the `d` field of `forgeData`.
+//!
+//! The mod list is serialized into a buffer, which is then packed 15 bits per character
+//! into a string, as that takes up less space in the JSON than base64. See Forge's
+//! `ServerStatusPing` for the encoding side.
+
+use bytes::{Buf, Bytes};
+use tokio::io::{self, ErrorKind};
+
+use super::data::{ForgeChannel, ForgeMod};
+use crate::buf::{read_mc_string_buf, read_varint_buf};
+
+/// The version Forge reports for mods that are only needed on the server, so clients
+/// can join without them.
+pub const IGNORE_SERVER_ONLY: &str = "OHNOES\u{1f631}\u{1f631}\u{1f631}\u{1f631}";
+
+/// The mod list of a Forge server, decoded from the `d` field.
+#[derive(Debug)]
+pub(super) struct PackedModList {
+    pub truncated: bool,
+    pub mods: Vec<ForgeMod>,
+    pub channels: Vec<ForgeChannel>,
+}
+
+/// Unpack the string in the `d` field into the bytes it encodes.
+///
+/// The first two characters hold the length of the data, 15 bits each, and every
+/// character after that holds the next 15 bits of the data, least significant first.
+fn unpack(packed: &str) -> io::Result<Vec<u8>> {
+    let mut chars = packed.chars().map(|c| u32::from(c) & 0x7fff);
+
+    let (Some(low), Some(high)) = (chars.next(), chars.next()) else {
+        return Err(ErrorKind::UnexpectedEof.into());
+    };
+    let len = (low | high << 15) as usize;
+
+    let mut data = Vec::with_capacity(len);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in chars {
+        buffer |= c << bits;
+        bits += 15;
+
+        while bits >= 8 {
+            data.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    }
+
+    if data.len() < len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    // the last character is padded with zeroes, which may look like extra bytes
+    data.truncate(len);
+
+    Ok(data)
+}
+
+/// Read a boolean, which takes up a single byte.
+fn read_bool(buf: &mut Bytes) -> io::Result<bool> {
+    if !buf.has_remaining() {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(buf.get_u8() != 0)
+}
+
+/// Read a channel, after its name has been read.
+fn read_channel(buf: &mut Bytes, name: String) -> io::Result<ForgeChannel> {
+    Ok(ForgeChannel {
+        name,
+        version: read_mc_string_buf(buf)?,
+        required: read_bool(buf)?,
+    })
+}
+
+/// Decode the mod list packed into the `d` field of `forgeData`.
+pub(super) fn decode(packed: &str) -> io::Result<PackedModList> {
+    let mut buf = Bytes::from(unpack(packed)?);
+
+    let truncated = read_bool(&mut buf)?;
+
+    if buf.remaining() < 2 {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    let mod_count = buf.get_u16();
+
+    let mut mods = Vec::with_capacity(mod_count.into());
+    let mut channels = Vec::new();
+    for _ in 0..mod_count {
+        // the lowest bit tells whether the mod is only needed on the server
+        let flags = read_varint_buf(&mut buf)?;
+        let channel_count = flags >> 1;
+
+        let mod_id = read_mc_string_buf(&mut buf)?;
+        let version = if flags & 1 == 0 {
+            read_mc_string_buf(&mut buf)?
+        } else {
+            IGNORE_SERVER_ONLY.to_string()
+        };
+
+        for _ in 0..channel_count {
+            let path = read_mc_string_buf(&mut buf)?;
+            channels.push(read_channel(&mut buf, format!("{mod_id}:{path}"))?);
+        }
+
+        mods.push(ForgeMod { mod_id, version });
+    }
+
+    // channels that don't belong to a mod
+    let channel_count = read_varint_buf(&mut buf)?;
+    for _ in 0..channel_count {
+        let name = read_mc_string_buf(&mut buf)?;
+        channels.push(read_channel(&mut buf, name)?);
+    }
+
+    Ok(PackedModList {
+        truncated,
+        mods,
+        channels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, unpack, IGNORE_SERVER_ONLY};
+    use bytes::{BufMut, BytesMut};
+
+    /// Pack bytes the way Forge does.
+    fn pack(data: &[u8]) -> String {
+        let len = u32::try_from(data.len()).unwrap();
+        let mut units = vec![len & 0x7fff, (len >> 15) & 0x7fff];
+
+        let mut buffer = 0u32;
+        let mut bits = 0;
+        for &byte in data {
+            buffer |= u32::from(byte) << bits;
+            bits += 8;
+            if bits >= 15 {
+                units.push(buffer & 0x7fff);
+                buffer >>= 15;
+                bits -= 15;
+            }
+        }
+        if bits > 0 {
+            units.push(buffer & 0x7fff);
+        }
+
+        units
+            .into_iter()
+            .map(|u| char::from_u32(u).unwrap())
+            .collect()
+    }
+
+    fn put_string(buf: &mut BytesMut, string: &str) {
+        buf.put_u8(u8::try_from(string.len()).unwrap());
+        buf.put_slice(string.as_bytes());
+    }
+
+    #[test]
+    fn test_unpack() {
+        for len in 0..40 {
+            let data = (0..len).map(|i| (i * 37 + 11) as u8).collect::<Vec<_>>();
+            assert_eq!(unpack(&pack(&data)).unwrap(), data);
+        }
+
+        assert!(unpack("\u{5}").is_err());
+        assert!(unpack("\u{5}\0\u{7fff}").is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut data = BytesMut::new();
+        data.put_u8(0); // not truncated
+        data.put_u16(2);
+
+        // a mod with one channel
+        data.put_u8(1 << 1);
+        put_string(&mut data, "forge");
+        put_string(&mut data, "47.1.0");
+        put_string(&mut data, "tier_sorting");
+        put_string(&mut data, "1.0");
+        data.put_u8(0);
+
+        // a server-only mod without channels
+        data.put_u8(1);
+        put_string(&mut data, "spark");
+
+        // a channel that doesn't belong to a mod
+        data.put_u8(1);
+        put_string(&mut data, "minecraft:register");
+        put_string(&mut data, "FML3");
+        data.put_u8(1);
+
+        let list = decode(&pack(&data)).unwrap();
+        assert!(!list.truncated);
+        assert_eq!(list.mods.len(), 2);
+        assert_eq!(list.mods[0].mod_id, "forge");
+        assert_eq!(list.mods[0].version, "47.1.0");
+        assert_eq!(list.mods[1].mod_id, "spark");
+        assert_eq!(list.mods[1].version, IGNORE_SERVER_ONLY);
+
+        assert_eq!(list.channels.len(), 2);
+        assert_eq!(list.channels[0].name, "forge:tier_sorting");
+        assert!(!list.channels[0].required);
+        assert_eq!(list.channels[1].name, "minecraft:register");
+        assert_eq!(list.channels[1].version, "FML3");
+        assert!(list.channels[1].required);
+
+        assert!(decode(&pack(&data[..data.len() - 1])).is_err());
+    }
+}
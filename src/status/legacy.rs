@@ -0,0 +1,266 @@
+//! Implementation of the [legacy Server List Ping](https://wiki.vg/Server_List_Ping#1.6)
+//! used by servers older than 1.7.
+
+use bytes::{BufMut, BytesMut};
+use tokio::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::data::LegacyStatusResponse;
+use crate::{
+    errors::{MinecraftProtocolError, Result},
+    socket::host_port,
+};
+
+/// The protocol version sent in the legacy ping (that of 1.6.4).
+const LEGACY_PROTOCOL_VERSION: u8 = 78;
+
+/// Ping a pre-1.7 server for information following the
+/// [legacy Server List Ping](https://wiki.vg/Server_List_Ping#1.6) protocol.
+///
+/// This sends the ping used by 1.6 clients, which servers from 1.4 onwards respond to.
+/// Most modern servers respond to it as well.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_legacy;
+/// use tokio::io::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let data = status_legacy("localhost", 25565).await?;
+///     println!("{data:#?}");
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn status_legacy(host: &str, port: u16) -> Result<LegacyStatusResponse> {
+    let mut socket = TcpStream::connect(host_port(host, port)).await?;
+
+    // https://wiki.vg/Server_List_Ping#Client_to_server
+    let host_utf16 = encode_utf16(host);
+
+    let mut request = BytesMut::new();
+    request.put_slice(&[0xfe, 0x01, 0xfa]);
+    put_legacy_string(&mut request, &encode_utf16("MC|PingHost"));
+    request.put_u16((7 + host_utf16.len()) as u16); // length of the rest of the data
+    request.put_u8(LEGACY_PROTOCOL_VERSION);
+    put_legacy_string(&mut request, &host_utf16);
+    request.put_i32(port as i32);
+
+    socket.write_all(&request).await?;
+
+    let response = read_kick_packet(&mut socket).await?;
+    socket.shutdown().await?;
+
+    Ok(parse_response(&response)?)
+}
+
+/// Ping a beta (pre-1.4) server for information, following the
+/// [oldest Server List Ping](https://wiki.vg/Server_List_Ping#Beta_1.8_to_1.3).
+///
+/// This sends a single `0xFE` byte, and only works for servers that don't answer
+/// [`status_legacy`], since the response doesn't include the protocol and version.
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+///
+/// # Errors
+/// Returns `Err` if there was a network issue or the server sent invalid data,
+/// including a response that isn't in the `<motd>§<online>§<max>` format.
+pub async fn status_legacy_beta(host: &str, port: u16) -> Result<LegacyStatusResponse> {
+    let mut socket = TcpStream::connect(host_port(host, port)).await?;
+
+    // https://wiki.vg/Server_List_Ping#Beta_1.8_to_1.3
+    socket.write_all(&[0xfe]).await?;
+
+    let response = read_kick_packet(&mut socket).await?;
+    socket.shutdown().await?;
+
+    Ok(parse_beta_response(&response)?)
+}
+
+create_timeout!(status_legacy, LegacyStatusResponse);
+create_timeout!(status_legacy_beta, LegacyStatusResponse);
+
+/// Encode a string as UTF-16BE bytes.
+fn encode_utf16(string: &str) -> Vec<u8> {
+    string.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// Write a legacy string (UTF-16BE, prefixed with its length in characters).
+fn put_legacy_string(bytes: &mut BytesMut, utf16: &[u8]) {
+    bytes.put_u16((utf16.len() / 2) as u16);
+    bytes.put_slice(utf16);
+}
+
+/// Read the kick packet the server responds with, and decode its message.
+async fn read_kick_packet(socket: &mut TcpStream) -> io::Result<String> {
+    let id = socket.read_u8().await?;
+    if id != 0xff {
+        return Err(MinecraftProtocolError::InvalidStatusResponse.into());
+    }
+
+    let len = socket.read_u16().await? as usize;
+    let mut buffer = vec![0; len * 2];
+    socket.read_exact(&mut buffer).await?;
+
+    decode_utf16(&buffer)
+}
+
+/// Decode UTF-16BE bytes into a string.
+fn decode_utf16(bytes: &[u8]) -> io::Result<String> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+
+    String::from_utf16(&units).map_err(|_| MinecraftProtocolError::InvalidStatusResponse.into())
+}
+
+/// Parse the message of the kick packet sent in response to the ping.
+///
+/// The message has the format `§1\0<protocol>\0<version>\0<motd>\0<online>\0<max>`.
+fn parse_response(response: &str) -> io::Result<LegacyStatusResponse> {
+    let fields = response
+        .strip_prefix("\u{a7}1\0")
+        .ok_or(MinecraftProtocolError::InvalidStatusResponse)?
+        .split('\0')
+        .collect::<Vec<_>>();
+
+    let [protocol, version, motd, online, max] = fields[..] else {
+        return Err(MinecraftProtocolError::InvalidStatusResponse.into());
+    };
+
+    Ok(LegacyStatusResponse {
+        protocol: protocol.parse().ok(),
+        version: Some(version.to_string()),
+        motd: motd.to_string(),
+        online: online
+            .parse()
+            .map_err(|_| MinecraftProtocolError::InvalidStatusResponse)?,
+        max: max
+            .parse()
+            .map_err(|_| MinecraftProtocolError::InvalidStatusResponse)?,
+    })
+}
+
+/// Parse the message of the kick packet sent in response to the beta ping.
+///
+/// The message has the format `<motd>§<online>§<max>`.
+fn parse_beta_response(response: &str) -> io::Result<LegacyStatusResponse> {
+    // the counts can't contain a section sign, but the MOTD might
+    let mut fields = response.rsplitn(3, '\u{a7}');
+
+    let (Some(max), Some(online), Some(motd)) = (fields.next(), fields.next(), fields.next())
+    else {
+        return Err(MinecraftProtocolError::InvalidStatusResponse.into());
+    };
+
+    Ok(LegacyStatusResponse {
+        protocol: None,
+        version: None,
+        motd: motd.to_string(),
+        online: online
+            .parse()
+            .map_err(|_| MinecraftProtocolError::InvalidStatusResponse)?,
+        max: max
+            .parse()
+            .map_err(|_| MinecraftProtocolError::InvalidStatusResponse)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_utf16, encode_utf16, parse_beta_response, parse_response, status_legacy};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, Result},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn test_parse_1_6_4_response() {
+        // kick packet message (after the 0xff id and length) from a vanilla 1.6.4 server
+        let bytes = b"\x00\xa7\x00\x31\x00\x00\x00\x37\x00\x38\x00\x00\x00\x31\x00\x2e\x00\x36\
+                      \x00\x2e\x00\x34\x00\x00\x00\x41\x00\x20\x00\x4d\x00\x69\x00\x6e\x00\x65\
+                      \x00\x63\x00\x72\x00\x61\x00\x66\x00\x74\x00\x20\x00\x53\x00\x65\x00\x72\
+                      \x00\x76\x00\x65\x00\x72\x00\x00\x00\x33\x00\x00\x00\x32\x00\x30";
+
+        let response = parse_response(&decode_utf16(bytes).unwrap()).unwrap();
+
+        assert_eq!(response.protocol, Some(78));
+        assert_eq!(response.version.as_deref(), Some("1.6.4"));
+        assert_eq!(response.motd, "A Minecraft Server");
+        assert_eq!(response.online, 3);
+        assert_eq!(response.max, 20);
+
+        let version = response.to_version().unwrap();
+        assert_eq!(version.name, "1.6.4");
+        assert_eq!(version.protocol, 78);
+    }
+
+    #[tokio::test]
+    async fn test_status_legacy() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+
+            let mut request = vec![0; 3 + 2 + 22 + 2 + 1 + 2 + 18 + 4];
+            socket.read_exact(&mut request).await?;
+
+            let message = encode_utf16("\u{a7}1\x0078\x001.6.4\x00A Minecraft Server\x003\x0020");
+            let mut response = vec![0xff];
+            response.extend_from_slice(&u16::try_from(message.len() / 2).unwrap().to_be_bytes());
+            response.extend_from_slice(&message);
+            socket.write_all(&response).await?;
+
+            Ok::<_, std::io::Error>(request)
+        });
+
+        let response = status_legacy("127.0.0.1", port).await?;
+        assert_eq!(response.protocol, Some(78));
+        assert_eq!(response.motd, "A Minecraft Server");
+        assert_eq!((response.online, response.max), (3, 20));
+
+        let request = server.await.unwrap()?;
+        assert_eq!(request[..3], [0xfe, 0x01, 0xfa]);
+        assert_eq!(request[3..5], [0, 11]); // "MC|PingHost"
+        assert_eq!(request[27..29], [0, 7 + 18]);
+        assert_eq!(request[29], 78);
+        assert_eq!(request[request.len() - 4..], i32::from(port).to_be_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_beta_response() {
+        let response = parse_beta_response("A Minecraft Server\u{a7}3\u{a7}20").unwrap();
+        assert_eq!(response.protocol, None);
+        assert_eq!(response.version, None);
+        assert_eq!(response.motd, "A Minecraft Server");
+        assert_eq!((response.online, response.max), (3, 20));
+
+        let response = parse_beta_response("\u{a7}aGreen\u{a7}0\u{a7}8").unwrap();
+        assert_eq!(response.motd, "\u{a7}aGreen");
+
+        assert!(parse_beta_response("A Minecraft Server\u{a7}20").is_err());
+        assert!(parse_beta_response("A Minecraft Server\u{a7}three\u{a7}20").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_response() {
+        assert!(parse_response("\u{a7}1\x0078\x001.6.4").is_err());
+        assert!(parse_response("A Minecraft Server\u{a7}3\u{a7}20").is_err());
+    }
+}
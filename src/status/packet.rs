@@ -1,17 +1,33 @@
-use crate::{errors::MinecraftProtocolError, varint::VarInt};
+use crate::{
+    errors::MinecraftProtocolError,
+    varint::{put_varint, varint_len},
+};
 use bytes::{BufMut, Bytes, BytesMut};
 
+/// The IDs of the packets this crate sends and receives, plus the states a handshake can
+/// switch to, which are sent as `VarInt`s too.
+///
+/// IDs are only unique within a state and direction, so several variants share a value.
 #[derive(Debug)]
-pub(super) enum PacketId {
-    Handshake = 0,
-    Status = 1,
+pub(crate) enum PacketId {
+    Handshake,
+    StatusRequest,
+    StatusResponse,
+    Status,
+    Ping,
+    Login,
+    LoginStart,
 }
 
 impl From<PacketId> for u8 {
     fn from(id: PacketId) -> Self {
         match id {
-            PacketId::Handshake => 0,
-            PacketId::Status => 1,
+            PacketId::Handshake
+            | PacketId::StatusRequest
+            | PacketId::StatusResponse
+            | PacketId::LoginStart => 0,
+            PacketId::Status | PacketId::Ping => 1,
+            PacketId::Login => 2,
         }
     }
 }
@@ -28,15 +44,14 @@ impl TryFrom<u8> for PacketId {
     }
 }
 
-impl From<PacketId> for VarInt {
+impl From<PacketId> for i32 {
     fn from(id: PacketId) -> Self {
-        let number: u8 = id.into();
-        VarInt::from(number as i32)
+        u8::from(id).into()
     }
 }
 
 #[derive(Debug)]
-pub(super) struct Packet {
+pub(crate) struct Packet {
     id: u8,
     payload: Bytes,
 }
@@ -53,11 +68,11 @@ impl Packet {
 
 impl From<Packet> for Bytes {
     fn from(packet: Packet) -> Self {
-        let len: i32 = (VarInt::from(packet.id as i32).bytes().len() + packet.payload.len()) as i32;
-        let mut bytes = BytesMut::new();
+        let len = (varint_len(packet.id.into()) + packet.payload.len()) as i32;
+        let mut bytes = BytesMut::with_capacity(varint_len(len) + len as usize);
 
-        bytes.extend_from_slice(&VarInt::from(len).bytes());
-        bytes.put_u8(packet.id);
+        put_varint(&mut bytes, len);
+        put_varint(&mut bytes, packet.id.into());
         bytes.extend_from_slice(&packet.payload);
 
         bytes.freeze()
@@ -65,7 +80,7 @@ impl From<Packet> for Bytes {
 }
 
 #[derive(Debug)]
-pub(super) struct PacketBuilder {
+pub(crate) struct PacketBuilder {
     id: PacketId,
     bytes: BytesMut,
 }
@@ -78,15 +93,15 @@ impl PacketBuilder {
         }
     }
 
-    pub fn add_varint(mut self, varint: &VarInt) -> Self {
-        self.bytes.extend_from_slice(varint);
+    pub fn add_varint(mut self, value: impl Into<i32>) -> Self {
+        put_varint(&mut self.bytes, value.into());
         self
     }
 
-    pub fn add_string(self, string: &str) -> Self {
-        let mut inst = self.add_varint(&VarInt::from(string.len() as i32));
-        inst.bytes.put(string.as_bytes());
-        inst
+    pub fn add_string(mut self, string: &str) -> Self {
+        put_varint(&mut self.bytes, string.len() as i32);
+        self.bytes.put(string.as_bytes());
+        self
     }
 
     pub fn add_u16(mut self, short: u16) -> Self {
@@ -94,6 +109,16 @@ impl PacketBuilder {
         self
     }
 
+    pub fn add_bool(mut self, boolean: bool) -> Self {
+        self.bytes.put_u8(boolean.into());
+        self
+    }
+
+    pub fn add_i64(mut self, long: i64) -> Self {
+        self.bytes.put_i64(long);
+        self
+    }
+
     pub fn build(self) -> Packet {
         Packet {
             id: self.id.into(),
@@ -101,3 +126,15 @@ impl PacketBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Packet, PacketId};
+
+    #[test]
+    fn test_status_request() {
+        // a length of 1, the packet ID, and no fields
+        let request = Packet::builder(PacketId::StatusRequest).build();
+        assert_eq!(request.bytes()[..], [0x01, 0x00]);
+    }
+}
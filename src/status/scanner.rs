@@ -0,0 +1,318 @@
+//! Scanning many servers for their status at once.
+
+use std::{net::SocketAddr, panic::resume_unwind, sync::Arc, time::Duration};
+use tokio::{net::ToSocketAddrs, sync::Semaphore, task::JoinSet, time::timeout};
+
+use super::{data::StatusResponse, status_on, StatusConfig};
+use crate::{
+    errors::{timeout_err, Error, Result},
+    socket::{connect_timeout, host_port},
+};
+
+/// Configuration for a [`Scanner`].
+#[derive(Debug, Clone)]
+pub struct ScannerConfig {
+    /// The maximum number of connections open at the same time. Requests beyond this
+    /// wait for another one to finish before connecting. Values below 1 are treated as 1.
+    ///
+    /// Every connection takes up a file descriptor, so this should be well below the
+    /// limit on open files of the process (see `ulimit -n`).
+    ///
+    /// Defaults to 256.
+    pub concurrency: usize,
+
+    /// How long to wait for connecting and for the response, in total, for each server.
+    /// Time spent waiting for a free connection slot is not included.
+    ///
+    /// Defaults to 5 seconds.
+    pub timeout: Duration,
+
+    /// The `SO_LINGER` option to set on each connection, see
+    /// [`TcpStream::set_linger`](tokio::net::TcpStream::set_linger).
+    ///
+    /// Setting this to `Some(Duration::ZERO)` makes the OS reset connections as soon as
+    /// they are closed, instead of keeping them around in `TIME_WAIT`, which can
+    /// exhaust local ports when connecting to a lot of servers in a short time.
+    ///
+    /// Defaults to `None`, which leaves the OS default in place.
+    pub linger: Option<Duration>,
+
+    /// The configuration for each status request.
+    pub status: StatusConfig,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 256,
+            timeout: Duration::from_secs(5),
+            linger: None,
+            status: StatusConfig::default(),
+        }
+    }
+}
+
+/// Requests the status of many servers, with a bound on the number of connections
+/// open at the same time.
+///
+/// Cloning a scanner is cheap, and clones share the same bound, so a clone can be moved
+/// into each task of a scan. Connections are closed as soon as each request finishes,
+/// whether it succeeded, failed, or timed out, so their file descriptors are freed
+/// right away.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::{Scanner, ScannerConfig};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let scanner = Scanner::new(ScannerConfig {
+///         concurrency: 1000,
+///         timeout: Duration::from_secs(3),
+///         linger: Some(Duration::ZERO),
+///         ..ScannerConfig::default()
+///     });
+///
+///     let mut tasks = Vec::new();
+///     for last in 1..=254 {
+///         let scanner = scanner.clone();
+///         let addr = format!("192.0.2.{last}:25565").parse().unwrap();
+///         tasks.push(tokio::spawn(async move { (addr, scanner.status(addr).await) }));
+///     }
+///
+///     for task in tasks {
+///         if let (addr, Ok(data)) = task.await.unwrap() {
+///             println!("{addr}: {}", data.version.name);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    config: Arc<ScannerConfig>,
+    permits: Arc<Semaphore>,
+}
+
+impl Scanner {
+    /// Create a new scanner with the given configuration.
+    #[must_use]
+    pub fn new(config: ScannerConfig) -> Self {
+        let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+
+        Self {
+            config: Arc::new(config),
+            permits,
+        }
+    }
+
+    /// The configuration of this scanner.
+    #[must_use]
+    pub fn config(&self) -> &ScannerConfig {
+        &self.config
+    }
+
+    /// Request the status of the server at `addr`, waiting for a free connection slot first.
+    ///
+    /// # Errors
+    /// Returns `Err` if there was a network issue, the server sent invalid data, or the
+    /// status was not received within the [`timeout`](ScannerConfig::timeout). The error
+    /// is wrapped in [`Error::Server`] with `addr`.
+    pub async fn status(&self, addr: SocketAddr) -> Result<StatusResponse> {
+        self.status_on_addr(addr, &addr.ip().to_string(), addr.port())
+            .await
+    }
+
+    /// Request the status of the server at `host` and `port`, waiting for a free connection
+    /// slot first. Resolving `host` happens after the slot is taken, and counts towards the
+    /// [`timeout`](ScannerConfig::timeout).
+    ///
+    /// # Errors
+    /// Returns `Err` if `host` could not be resolved, there was a network issue, the server
+    /// sent invalid data, or the status was not received within the
+    /// [`timeout`](ScannerConfig::timeout). The error is wrapped in [`Error::Server`]
+    /// with `host` and `port`.
+    pub async fn status_host(&self, host: &str, port: u16) -> Result<StatusResponse> {
+        self.status_on_addr(host_port(host, port), host, port).await
+    }
+
+    /// Connect to `addr`, and request the status with `host` and `port` in the handshake.
+    async fn status_on_addr(
+        &self,
+        addr: impl ToSocketAddrs,
+        host: &str,
+        port: u16,
+    ) -> Result<StatusResponse> {
+        // the semaphore is never closed, so this always gets a permit
+        let _permit = self.permits.acquire().await.ok();
+
+        let fut = async {
+            let config = &self.config.status;
+            let socket = connect_timeout(addr, config.connect_timeout).await?;
+            socket.set_linger(self.config.linger)?;
+
+            status_on(socket, host, port, config, config.protocol_version).await
+        };
+
+        timeout(self.config.timeout, fut)
+            .await
+            .unwrap_or(timeout_err())
+            .map_err(|err| Error::from(err).with_addr(host_port(host, port)))
+    }
+}
+
+/// Request the status of all `servers` concurrently, with at most `concurrency` connections
+/// open at the same time.
+///
+/// This is a shorthand for spawning a task per server with a [`Scanner`]. Use one directly
+/// for more control over the connections.
+///
+/// # Arguments
+/// * `servers` - The hostnames and ports of the servers.
+/// * `dur` - How long to wait for each server, see [`ScannerConfig::timeout`].
+/// * `concurrency` - The most connections open at the same time, see
+///   [`ScannerConfig::concurrency`].
+///
+/// # Returns
+/// The result for each server, along with its index in `servers`, in the order they
+/// finished. A server that fails doesn't affect the others, and its error is wrapped in
+/// [`Error::Server`] with its address.
+///
+/// # Examples
+/// ```no_run
+/// use mc_query::status::status_many;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let servers = [
+///         ("mc.hypixel.net".to_string(), 25565),
+///         ("localhost".to_string(), 25565),
+///     ];
+///
+///     for (i, res) in status_many(&servers, Duration::from_secs(5), 64).await {
+///         match res {
+///             Ok(data) => println!("{}: {} online", servers[i].0, data.players.online),
+///             Err(err) => println!("{err}"),
+///         }
+///     }
+/// }
+/// ```
+pub async fn status_many(
+    servers: &[(String, u16)],
+    dur: Duration,
+    concurrency: usize,
+) -> Vec<(usize, Result<StatusResponse>)> {
+    let scanner = Scanner::new(ScannerConfig {
+        concurrency,
+        timeout: dur,
+        ..ScannerConfig::default()
+    });
+
+    let mut tasks = JoinSet::new();
+    for (i, (host, port)) in servers.iter().cloned().enumerate() {
+        let scanner = scanner.clone();
+        tasks.spawn(async move { (i, scanner.status_host(&host, port).await) });
+    }
+
+    let mut results = Vec::with_capacity(servers.len());
+    while let Some(res) = tasks.join_next().await {
+        match res {
+            Ok(res) => results.push(res),
+            Err(err) => resume_unwind(err.into_panic()),
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{status_many, Scanner, ScannerConfig};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::{io::Result, net::TcpListener, time::sleep};
+
+    #[tokio::test]
+    async fn test_scanner_bounded_concurrency() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let open = Arc::new(AtomicUsize::new(0));
+        let max_open = Arc::new(AtomicUsize::new(0));
+        {
+            let (open, max_open) = (open.clone(), max_open.clone());
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    let now = open.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_open.fetch_max(now, Ordering::SeqCst);
+
+                    let open = open.clone();
+                    tokio::spawn(async move {
+                        sleep(Duration::from_millis(20)).await;
+                        // the connection is only closed after it stops being counted
+                        open.fetch_sub(1, Ordering::SeqCst);
+                        drop(socket);
+                    });
+                }
+            });
+        }
+
+        let scanner = Scanner::new(ScannerConfig {
+            concurrency: 2,
+            timeout: Duration::from_secs(5),
+            linger: Some(Duration::ZERO),
+            ..ScannerConfig::default()
+        });
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let scanner = scanner.clone();
+                tokio::spawn(async move { scanner.status(addr).await })
+            })
+            .collect();
+        for task in tasks {
+            assert!(task.await.unwrap().is_err());
+        }
+
+        assert_eq!(max_open.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status_many() -> Result<()> {
+        // a server that closes every connection right away
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        // a port nothing listens on
+        let closed = TcpListener::bind("127.0.0.1:0").await?;
+        let closed_port = closed.local_addr()?.port();
+        drop(closed);
+
+        let servers = [
+            ("127.0.0.1".to_string(), port),
+            ("127.0.0.1".to_string(), closed_port),
+            ("localhost".to_string(), port),
+        ];
+        let mut results = status_many(&servers, Duration::from_secs(5), 2).await;
+        results.sort_by_key(|(i, _)| *i);
+
+        assert_eq!(results.len(), 3);
+        for (i, (index, res)) in results.iter().enumerate() {
+            assert_eq!(*index, i);
+            let (host, port) = &servers[i];
+            let err = res.as_ref().unwrap_err();
+            assert_eq!(err.addr(), Some(format!("{host}:{port}").as_str()));
+        }
+
+        Ok(())
+    }
+}
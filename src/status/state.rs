@@ -0,0 +1,99 @@
+//! Distinguishing servers that are restarting from servers that are down.
+
+use std::time::Duration;
+
+use super::{data::StatusResponse, status_with_timeout};
+use crate::socket::is_dropped;
+
+/// Phrases in the MOTD that indicate the server is still starting up.
+/// Server hosts commonly show these while a server is being started.
+const STARTING_MOTD_PHRASES: [&str; 4] = ["starting", "loading", "booting", "please wait"];
+
+/// The state of a server, as determined by [`server_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServerState {
+    /// The server responded to the status ping normally.
+    Online,
+
+    /// The server appears to be starting up (or restarting).
+    Starting,
+
+    /// The server could not be reached.
+    Offline,
+}
+
+/// Determine whether the server is online, starting up, or offline.
+///
+/// This is useful for monitoring tools that want to avoid raising false alarms while a
+/// server restarts. As the protocol has no way for servers to report that they're starting,
+/// this relies on the following heuristics:
+///
+/// * If the server responds to the status ping with a MOTD containing phrases like
+///   "starting" or "loading" (as shown by many server hosts and proxies while the server
+///   boots), it is [`Starting`](ServerState::Starting).
+/// * If the connection is accepted, but then closed or reset before the server responds,
+///   it is [`Starting`](ServerState::Starting). This happens while a server has already bound
+///   its port, but is not yet ready to handle connections.
+/// * Any other response is [`Online`](ServerState::Online).
+/// * If the connection can't be established at all, the response is not recieved within `dur`,
+///   or the server sends invalid data, it is [`Offline`](ServerState::Offline).
+///
+/// # Arguments
+/// * `host` - A string slice that holds the hostname of the server to connect to.
+/// * `port` - The port to connect to on that server.
+/// * `dur` - How long to wait for the server to respond.
+pub async fn server_state(host: &str, port: u16, dur: Duration) -> ServerState {
+    match status_with_timeout(host, port, dur).await {
+        Ok(response) if is_starting_motd(&response) => ServerState::Starting,
+        Ok(_) => ServerState::Online,
+        Err(err) if is_dropped(err.kind()) => ServerState::Starting,
+        Err(_) => ServerState::Offline,
+    }
+}
+
+fn is_starting_motd(response: &StatusResponse) -> bool {
+    let Some(motd) = &response.motd else {
+        return false;
+    };
+
+    let motd = motd.to_plain_text().to_lowercase();
+    STARTING_MOTD_PHRASES
+        .iter()
+        .any(|phrase| motd.contains(phrase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{server_state, ServerState};
+    use std::time::Duration;
+    use tokio::{io, net::TcpListener};
+
+    #[tokio::test]
+    async fn test_starting_connection_closed() -> io::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+
+        // accept connections, but close them immediately
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+
+        let state = server_state("127.0.0.1", port, Duration::from_secs(1)).await;
+        assert_eq!(state, ServerState::Starting);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline() -> io::Result<()> {
+        // bind and immediately release a port, so nothing is listening on it
+        let port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+
+        let state = server_state("127.0.0.1", port, Duration::from_secs(1)).await;
+        assert_eq!(state, ServerState::Offline);
+
+        Ok(())
+    }
+}
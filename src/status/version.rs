@@ -0,0 +1,88 @@
+//! Map [protocol version numbers](https://wiki.vg/Protocol_version_numbers) to the
+//! names of the releases that use them.
+
+/// Release protocol numbers and the name of the latest release using each, sorted by
+/// protocol number. Releases from 1.8 onwards are listed.
+const RELEASES: [(i64, &str); 39] = [
+    (47, "1.8.9"),
+    (107, "1.9"),
+    (108, "1.9.1"),
+    (109, "1.9.2"),
+    (110, "1.9.4"),
+    (210, "1.10.2"),
+    (315, "1.11"),
+    (316, "1.11.2"),
+    (335, "1.12"),
+    (338, "1.12.1"),
+    (340, "1.12.2"),
+    (393, "1.13"),
+    (401, "1.13.1"),
+    (404, "1.13.2"),
+    (477, "1.14"),
+    (480, "1.14.1"),
+    (485, "1.14.2"),
+    (490, "1.14.3"),
+    (498, "1.14.4"),
+    (573, "1.15"),
+    (575, "1.15.1"),
+    (578, "1.15.2"),
+    (735, "1.16"),
+    (736, "1.16.1"),
+    (751, "1.16.2"),
+    (753, "1.16.3"),
+    (754, "1.16.5"),
+    (755, "1.17"),
+    (756, "1.17.1"),
+    (757, "1.18.1"),
+    (758, "1.18.2"),
+    (759, "1.19"),
+    (760, "1.19.2"),
+    (761, "1.19.3"),
+    (762, "1.19.4"),
+    (763, "1.20.1"),
+    (764, "1.20.2"),
+    (765, "1.20.4"),
+    (766, "1.20.6"),
+];
+
+/// Get the name of the release that uses the given protocol version, like `1.19.2`
+/// for `760`.
+///
+/// Several releases often share a protocol number (1.19.1 and 1.19.2 both use `760`),
+/// in which case the latest of them is returned, since servers on the older ones
+/// accept the same clients. Snapshots, pre-releases, and releases before 1.8 are not
+/// known, and return `None`.
+///
+/// # Arguments
+/// * `protocol` - The protocol version, as in [`Version::protocol`](super::data::Version::protocol).
+///
+/// # Examples
+/// ```
+/// use mc_query::status::protocol_to_version;
+///
+/// assert_eq!(protocol_to_version(763), Some("1.20.1"));
+/// assert_eq!(protocol_to_version(-1), None);
+/// ```
+#[must_use]
+pub fn protocol_to_version(protocol: i64) -> Option<&'static str> {
+    RELEASES
+        .binary_search_by_key(&protocol, |(number, _)| *number)
+        .ok()
+        .map(|index| RELEASES[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{protocol_to_version, RELEASES};
+
+    #[test]
+    fn test_protocol_to_version() {
+        assert!(RELEASES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        assert_eq!(protocol_to_version(47), Some("1.8.9"));
+        assert_eq!(protocol_to_version(760), Some("1.19.2"));
+        assert_eq!(protocol_to_version(766), Some("1.20.6"));
+        assert_eq!(protocol_to_version(5), None);
+        assert_eq!(protocol_to_version(0x4000_0090), None); // a snapshot
+    }
+}
@@ -1,12 +1,103 @@
-use bytes::Bytes;
+//! The variable-length integers of the Minecraft protocol, for building custom packets.
+//!
+//! See [`VarInt`] for details on the format, which [`VarLong`] shares.
+
+use bytes::{BufMut, Bytes, BytesMut};
 use std::ops::Deref;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ErrorKind};
 
 use crate::errors::MinecraftProtocolError;
 
 pub(crate) const SEGMENT_BITS: u8 = 0x7f; // 0111 1111
 pub(crate) const CONTINUE_BIT: u8 = 0x80; // 1000 0000
 
-pub(crate) struct VarInt {
+/// The maximum number of bytes a [`VarInt`] can take up.
+pub const MAX_VARINT_LEN: usize = 5;
+
+/// Append `value` to `buf` as a `VarInt`, without any intermediate allocation.
+pub(crate) fn put_varint(buf: &mut impl BufMut, value: i32) {
+    let mut value = value as u32;
+
+    loop {
+        let temp = (value & SEGMENT_BITS as u32) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buf.put_u8(temp);
+            return;
+        }
+        buf.put_u8(temp | CONTINUE_BIT);
+    }
+}
+
+/// The number of bytes `value` takes up as a `VarInt`.
+#[cfg(any(feature = "status", test))]
+pub(crate) fn varint_len(value: i32) -> usize {
+    let bits = (u32::BITS - (value as u32).leading_zeros()).max(1);
+
+    bits.div_ceil(7) as usize
+}
+
+/// Append `value` to `buf` as a `VarLong`, without any intermediate allocation.
+pub(crate) fn put_varlong(buf: &mut impl BufMut, value: i64) {
+    let mut value = value as u64;
+
+    loop {
+        let temp = (value & SEGMENT_BITS as u64) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buf.put_u8(temp);
+            return;
+        }
+        buf.put_u8(temp | CONTINUE_BIT);
+    }
+}
+
+/// Read the bytes of a `VarInt` or `VarLong` from a stream, one byte at a time, stopping
+/// at its last byte, or failing with `err` after `max_len` bytes.
+async fn read_encoded(
+    reader: &mut (impl AsyncRead + Unpin),
+    max_len: usize,
+    err: MinecraftProtocolError,
+) -> io::Result<Bytes> {
+    let mut bytes = BytesMut::with_capacity(max_len);
+
+    loop {
+        let current = reader.read_u8().await?;
+        bytes.put_u8(current);
+
+        if current & CONTINUE_BIT == 0 {
+            return Ok(bytes.freeze());
+        }
+
+        // a peer could otherwise keep the loop going for as long as it likes
+        if bytes.len() == max_len {
+            return Err(io::Error::new(ErrorKind::InvalidData, err));
+        }
+    }
+}
+
+/// A [`VarInt`](https://wiki.vg/Protocol#VarInt_and_VarLong): an `i32` encoded in 1 to 5
+/// bytes, with small values taking up fewer bytes.
+///
+/// Each byte holds the next 7 bits of the value, least significant first, and has its most
+/// significant bit set if more bytes follow. Negative values are encoded as their two's
+/// complement, as if they were a `u32`, so they always take up the full 5 bytes. Encodings
+/// longer than 5 bytes are invalid.
+///
+/// # Examples
+/// ```
+/// use mc_query::varint::VarInt;
+///
+/// let varint = VarInt::from(25565);
+/// assert_eq!(varint.as_bytes(), b"\xdd\xc7\x01");
+/// assert_eq!(varint.len(), 3);
+///
+/// assert_eq!(i32::try_from(VarInt::from(-1)).unwrap(), -1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarInt {
     bytes: Bytes,
 }
 
@@ -15,46 +106,78 @@ impl VarInt {
         Self { bytes }
     }
 
-    pub(crate) fn bytes(&self) -> Bytes {
-        self.bytes.clone()
+    /// The number of bytes this `VarInt` takes up, between 1 and [`MAX_VARINT_LEN`].
+    #[allow(clippy::len_without_is_empty)] // a VarInt is never empty
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
     }
-}
 
-impl From<i32> for VarInt {
-    fn from(value: i32) -> Self {
-        let mut value = (value as u64) & 0xffff_ffff;
-        let mut buffer = vec![];
+    /// The encoded bytes of this `VarInt`.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
 
-        loop {
-            let temp = (value & SEGMENT_BITS as u64) as u8;
-            value >>= 7;
+    /// Read a `VarInt` from a stream, one byte at a time, stopping at its last byte.
+    ///
+    /// # Errors
+    /// Returns `Err` if reading from the stream fails, or with [`ErrorKind::InvalidData`]
+    /// if the `VarInt` is longer than [`MAX_VARINT_LEN`] bytes. No more than that many
+    /// bytes are read in that case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mc_query::varint::VarInt;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let mut stream: &[u8] = b"\xdd\xc7\x01rest";
+    /// let value: i32 = VarInt::read_from(&mut stream).await?.try_into().unwrap();
+    /// assert_eq!(value, 25565);
+    /// assert_eq!(stream, b"rest");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_from(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Self> {
+        read_encoded(
+            reader,
+            MAX_VARINT_LEN,
+            MinecraftProtocolError::InvalidVarInt,
+        )
+        .await
+        .map(Self::new)
+    }
 
-            if value != 0 {
-                buffer.push(temp | CONTINUE_BIT);
-            } else {
-                buffer.push(temp);
-            }
+    /// Write this `VarInt` to a stream.
+    ///
+    /// # Errors
+    /// Returns `Err` if writing to the stream fails.
+    pub async fn write_to(&self, writer: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+        writer.write_all(&self.bytes).await
+    }
+}
 
-            if value == 0 {
-                break;
-            }
-        }
+impl From<i32> for VarInt {
+    fn from(value: i32) -> Self {
+        let mut bytes = BytesMut::with_capacity(MAX_VARINT_LEN);
+        put_varint(&mut bytes, value);
 
         Self {
-            bytes: Bytes::from(Box::from(buffer)),
+            bytes: bytes.freeze(),
         }
     }
 }
 
-impl TryInto<i32> for VarInt {
+impl TryFrom<VarInt> for i32 {
     type Error = MinecraftProtocolError;
 
-    fn try_into(self) -> Result<i32, Self::Error> {
-        let mut value: i32 = 0;
+    fn try_from(varint: VarInt) -> Result<Self, Self::Error> {
+        let mut value: Self = 0;
         let mut position = 0;
 
-        for current_byte in self.bytes {
-            value |= ((current_byte & SEGMENT_BITS) as i32) << position;
+        for current_byte in varint.bytes {
+            value |= ((current_byte & SEGMENT_BITS) as Self) << position;
 
             if current_byte & CONTINUE_BIT == 0 {
                 return Ok(value);
@@ -66,7 +189,8 @@ impl TryInto<i32> for VarInt {
             }
         }
 
-        unreachable!();
+        // the bytes ended while the last one said more were to follow
+        Err(MinecraftProtocolError::InvalidVarInt)
     }
 }
 
@@ -84,9 +208,126 @@ impl Deref for VarInt {
     }
 }
 
+/// The maximum number of bytes a [`VarLong`] can take up.
+pub const MAX_VARLONG_LEN: usize = 10;
+
+/// A [`VarLong`](https://wiki.vg/Protocol#VarInt_and_VarLong): an `i64` encoded in 1 to 10
+/// bytes, the same way a [`VarInt`] encodes an `i32`.
+///
+/// Negative values are encoded as their two's complement, as if they were a `u64`, so they
+/// always take up the full 10 bytes. Encodings longer than 10 bytes are invalid.
+///
+/// # Examples
+/// ```
+/// use mc_query::varint::VarLong;
+///
+/// let varlong = VarLong::from(2_147_483_648);
+/// assert_eq!(varlong.as_bytes(), b"\x80\x80\x80\x80\x08");
+/// assert_eq!(varlong.len(), 5);
+///
+/// assert_eq!(i64::try_from(VarLong::from(i64::MIN)).unwrap(), i64::MIN);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VarLong {
+    bytes: Bytes,
+}
+
+impl VarLong {
+    pub(crate) fn new(bytes: Bytes) -> Self {
+        Self { bytes }
+    }
+
+    /// The number of bytes this `VarLong` takes up, between 1 and [`MAX_VARLONG_LEN`].
+    #[allow(clippy::len_without_is_empty)] // a VarLong is never empty
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The encoded bytes of this `VarLong`.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Read a `VarLong` from a stream, one byte at a time, stopping at its last byte.
+    ///
+    /// # Errors
+    /// Returns `Err` if reading from the stream fails, or with [`ErrorKind::InvalidData`]
+    /// if the `VarLong` is longer than [`MAX_VARLONG_LEN`] bytes. No more than that many
+    /// bytes are read in that case.
+    pub async fn read_from(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Self> {
+        read_encoded(
+            reader,
+            MAX_VARLONG_LEN,
+            MinecraftProtocolError::InvalidVarLong,
+        )
+        .await
+        .map(Self::new)
+    }
+
+    /// Write this `VarLong` to a stream.
+    ///
+    /// # Errors
+    /// Returns `Err` if writing to the stream fails.
+    pub async fn write_to(&self, writer: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+        writer.write_all(&self.bytes).await
+    }
+}
+
+impl From<i64> for VarLong {
+    fn from(value: i64) -> Self {
+        let mut bytes = BytesMut::with_capacity(MAX_VARLONG_LEN);
+        put_varlong(&mut bytes, value);
+
+        Self {
+            bytes: bytes.freeze(),
+        }
+    }
+}
+
+impl TryFrom<VarLong> for i64 {
+    type Error = MinecraftProtocolError;
+
+    fn try_from(varlong: VarLong) -> Result<Self, Self::Error> {
+        let mut value: Self = 0;
+        let mut position = 0;
+
+        for current_byte in varlong.bytes {
+            value |= ((current_byte & SEGMENT_BITS) as Self) << position;
+
+            if current_byte & CONTINUE_BIT == 0 {
+                return Ok(value);
+            }
+
+            position += 7;
+            if position >= 64 {
+                return Err(MinecraftProtocolError::InvalidVarLong);
+            }
+        }
+
+        // the bytes ended while the last one said more were to follow
+        Err(MinecraftProtocolError::InvalidVarLong)
+    }
+}
+
+impl From<VarLong> for Bytes {
+    fn from(varlong: VarLong) -> Self {
+        varlong.bytes
+    }
+}
+
+impl Deref for VarLong {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::VarInt;
+    use super::{put_varint, put_varlong, varint_len, VarInt, VarLong};
     use crate::errors::MinecraftProtocolError;
     use bytes::Bytes;
     use std::collections::HashMap;
@@ -101,7 +342,7 @@ mod tests {
             (128, b"\x80\x01"),
             (255, b"\xff\x01"),
             (25565, b"\xdd\xc7\x01"),
-            (2097151, b"\xff\xff\x7f"),
+            (2_097_151, b"\xff\xff\x7f"),
             (i32::MAX, b"\xff\xff\xff\xff\x07"),
             (-1, b"\xff\xff\xff\xff\x0f"),
             (i32::MIN, b"\x80\x80\x80\x80\x08"),
@@ -111,6 +352,11 @@ mod tests {
             let varint: VarInt = k.into();
             assert_eq!(varint.bytes.len(), v.len());
             assert_eq!(varint.bytes, v);
+
+            let mut buf = Vec::new();
+            put_varint(&mut buf, k);
+            assert_eq!(buf, v);
+            assert_eq!(varint_len(k), v.len());
         }
     }
 
@@ -124,7 +370,7 @@ mod tests {
             (128, b"\x80\x01"),
             (255, b"\xff\x01"),
             (25565, b"\xdd\xc7\x01"),
-            (2097151, b"\xff\xff\x7f"),
+            (2_097_151, b"\xff\xff\x7f"),
             (i32::MAX, b"\xff\xff\xff\xff\x07"),
             (-1, b"\xff\xff\xff\xff\x0f"),
             (i32::MIN, b"\x80\x80\x80\x80\x08"),
@@ -150,5 +396,86 @@ mod tests {
 
             assert_eq!(x, k);
         }
+
+        let truncated = VarInt::new(Bytes::from(b"\x80\x80".as_slice()));
+        let x: Result<i32, _> = truncated.try_into();
+        assert!(matches!(x, Err(MinecraftProtocolError::InvalidVarInt)));
+
+        let empty = VarInt::new(Bytes::new());
+        let x: Result<i32, _> = empty.try_into();
+        assert!(matches!(x, Err(MinecraftProtocolError::InvalidVarInt)));
+    }
+
+    const VARLONG_CASES: [(i64, &[u8]); 9] = [
+        (0, b"\x00"),
+        (1, b"\x01"),
+        (127, b"\x7f"),
+        (128, b"\x80\x01"),
+        (25565, b"\xdd\xc7\x01"),
+        (2_147_483_647, b"\xff\xff\xff\xff\x07"),
+        (i64::MAX, b"\xff\xff\xff\xff\xff\xff\xff\xff\x7f"),
+        (-1, b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"),
+        (i64::MIN, b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x01"),
+    ];
+
+    #[tokio::test]
+    async fn test_read_write() -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        VarInt::from(300).write_to(&mut buf).await?;
+        VarInt::from(-1).write_to(&mut buf).await?;
+
+        let mut stream = buf.as_slice();
+        let first = VarInt::read_from(&mut stream).await?;
+        assert_eq!(first.len(), 2);
+        let first: i32 = first.try_into().unwrap();
+        assert_eq!(first, 300);
+        let second: i32 = VarInt::read_from(&mut stream).await?.try_into().unwrap();
+        assert_eq!(second, -1);
+        assert!(VarInt::read_from(&mut stream).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_write_varlong() -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        VarLong::from(i64::MAX).write_to(&mut buf).await?;
+        buf.extend_from_slice(&[0xff; 11]);
+
+        let mut stream = buf.as_slice();
+        let value: i64 = VarLong::read_from(&mut stream).await?.try_into().unwrap();
+        assert_eq!(value, i64::MAX);
+        assert!(VarLong::read_from(&mut stream).await.is_err());
+        assert_eq!(stream, [0xff]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_into_varlong() {
+        for (k, v) in VARLONG_CASES {
+            let varlong: VarLong = k.into();
+            assert_eq!(varlong.bytes, v);
+
+            let mut buf = Vec::new();
+            put_varlong(&mut buf, k);
+            assert_eq!(buf, v);
+        }
+    }
+
+    #[test]
+    fn test_from_varlong() {
+        for (k, v) in VARLONG_CASES {
+            let x: i64 = VarLong::new(Bytes::from(v)).try_into().unwrap();
+            assert_eq!(x, k);
+        }
+
+        let too_long = VarLong::new(Bytes::from_static(&[0xff; 11]));
+        let x: Result<i64, _> = too_long.try_into();
+        assert!(matches!(x, Err(MinecraftProtocolError::InvalidVarLong)));
+
+        let truncated = VarLong::new(Bytes::from_static(b"\x80\x80"));
+        let x: Result<i64, _> = truncated.try_into();
+        assert!(matches!(x, Err(MinecraftProtocolError::InvalidVarLong)));
     }
 }